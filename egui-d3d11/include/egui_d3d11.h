@@ -0,0 +1,127 @@
+#ifndef EGUI_D3D11_H
+#define EGUI_D3D11_H
+
+/* Generated with cbindgen from src/ffi.rs, don't edit manually. */
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/**
+ * Opaque app handle.
+ */
+typedef struct EguiApp EguiApp;
+
+/**
+ * Opaque painter handle, draws above the game on a layer without any windows.
+ */
+typedef struct EguiPainter EguiPainter;
+
+/**
+ * Called every frame to draw the UI, `painter` is only valid during the call.
+ */
+typedef void (*EguiUiCallback)(EguiPainter *painter, void *user_data);
+
+/**
+ * Calls the original `ResizeBuffers` and returns its `HRESULT`.
+ */
+typedef int32_t (*EguiResizeCallback)(void *user_data);
+
+/**
+ * Receives numeric code of the failed step and `HRESULT` of the failed call.
+ * Codes are the values of `ErrorCode`, they never change between versions.
+ */
+typedef void (*EguiErrorCallback)(uint32_t code, int32_t hresult);
+
+/**
+ * Creates app for `IDXGISwapChain*`. Returns null on failure.
+ * `ui` is called with `user_data` every frame.
+ */
+EguiApp *egui_d3d11_create(void *swap_chain, EguiUiCallback ui, void *user_data);
+
+/**
+ * Destroys app created with [`egui_d3d11_create`]. Null is ignored.
+ */
+void egui_d3d11_destroy(EguiApp *app);
+
+/**
+ * Should be called before the original `Present`.
+ */
+void egui_d3d11_present(const EguiApp *app, void *swap_chain, uint32_t sync_interval, uint32_t flags);
+
+/**
+ * Should be called **INSTEAD** of `ResizeBuffers`, `original` has to call the original one.
+ */
+int32_t egui_d3d11_resize_buffers(const EguiApp *app,
+                                  void *swap_chain,
+                                  EguiResizeCallback original,
+                                  void *user_data);
+
+/**
+ * Should be called on each `WndProc`. Returns `true` if the original procedure
+ * should be called, otherwise `WndProc` should return 1.
+ */
+bool egui_d3d11_wnd_proc(const EguiApp *app, uint32_t umsg, uintptr_t wparam, intptr_t lparam);
+
+/**
+ * Shows or hides the UI.
+ */
+void egui_d3d11_set_visible(const EguiApp *app, bool visible);
+
+/**
+ * Sets callback receiving failures, called before the function that failed returns
+ * its error value. Null removes the previous callback.
+ */
+void egui_d3d11_set_error_callback(EguiErrorCallback callback);
+
+/**
+ * Colors are `0xRRGGBBAA`, coordinates are in points.
+ */
+void egui_d3d11_painter_line(EguiPainter *painter,
+                             float x0,
+                             float y0,
+                             float x1,
+                             float y1,
+                             float width,
+                             uint32_t rgba);
+
+void egui_d3d11_painter_rect(EguiPainter *painter,
+                             float x,
+                             float y,
+                             float w,
+                             float h,
+                             float rounding,
+                             float width,
+                             uint32_t rgba);
+
+void egui_d3d11_painter_rect_filled(EguiPainter *painter,
+                                    float x,
+                                    float y,
+                                    float w,
+                                    float h,
+                                    float rounding,
+                                    uint32_t rgba);
+
+void egui_d3d11_painter_circle(EguiPainter *painter,
+                               float x,
+                               float y,
+                               float radius,
+                               float width,
+                               uint32_t rgba);
+
+void egui_d3d11_painter_circle_filled(EguiPainter *painter,
+                                      float x,
+                                      float y,
+                                      float radius,
+                                      uint32_t rgba);
+
+/**
+ * Draws zero terminated UTF-8 `text` with its top left corner at `x`, `y`.
+ */
+void egui_d3d11_painter_text(EguiPainter *painter,
+                             float x,
+                             float y,
+                             const char *text,
+                             float size,
+                             uint32_t rgba);
+
+#endif /* EGUI_D3D11_H */
@@ -1,46 +1,103 @@
-use egui::{Context, FullOutput, Pos2};
-use parking_lot::{Mutex, MutexGuard};
+use egui::{Context, CursorIcon, Event, FullOutput, Pos2, RawInput, Rect, Style, TextureId};
 use std::{
+    cell::Cell,
+    collections::HashMap,
     intrinsics::transmute,
     mem::{size_of, zeroed},
     ptr::null_mut as null,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
+#[cfg(target_pointer_width = "32")]
+use windows::Win32::UI::WindowsAndMessaging::GetWindowLongA;
+#[cfg(target_pointer_width = "64")]
+use windows::Win32::UI::WindowsAndMessaging::GetWindowLongPtrA;
 use windows::{
-    core::HRESULT,
+    core::{Interface, HRESULT},
     Win32::{
-        Foundation::{HWND, LPARAM, RECT, WPARAM},
+        Foundation::{E_HANDLE, HWND, LPARAM, RECT, WPARAM},
         Graphics::{
             Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST,
             Direct3D11::{
-                ID3D11Device, ID3D11DeviceContext, ID3D11InputLayout, ID3D11RenderTargetView,
-                ID3D11SamplerState, ID3D11Texture2D, D3D11_APPEND_ALIGNED_ELEMENT,
-                D3D11_BLEND_DESC, D3D11_BLEND_INV_SRC_ALPHA, D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD,
+                ID3D11BlendState, ID3D11Buffer, ID3D11DepthStencilView, ID3D11Device,
+                ID3D11DeviceContext, ID3D11InputLayout, ID3D11RasterizerState,
+                ID3D11RenderTargetView, ID3D11SamplerState, ID3D11ShaderResourceView,
+                ID3D11Texture2D, D3D11_APPEND_ALIGNED_ELEMENT, D3D11_BLEND_DESC,
+                D3D11_BLEND_INV_SRC_ALPHA, D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD,
                 D3D11_BLEND_SRC_ALPHA, D3D11_COLOR_WRITE_ENABLE_ALL, D3D11_COMPARISON_ALWAYS,
                 D3D11_CULL_NONE, D3D11_FILL_SOLID, D3D11_FILTER_MIN_MAG_MIP_LINEAR,
                 D3D11_INPUT_ELEMENT_DESC, D3D11_INPUT_PER_VERTEX_DATA, D3D11_RASTERIZER_DESC,
-                D3D11_RENDER_TARGET_BLEND_DESC, D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_BORDER,
-                D3D11_VIEWPORT,
+                D3D11_RENDER_TARGET_BLEND_DESC, D3D11_SAMPLER_DESC, D3D11_TEXTURE2D_DESC,
+                D3D11_TEXTURE_ADDRESS_BORDER, D3D11_TEXTURE_ADDRESS_CLAMP, D3D11_VIEWPORT,
             },
             Dxgi::{
                 Common::{
                     DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32_FLOAT, DXGI_FORMAT_R32_UINT,
-                    DXGI_FORMAT_R8_UINT,
+                    DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8_UINT,
                 },
                 IDXGISwapChain,
             },
         },
-        UI::WindowsAndMessaging::GetClientRect,
+        UI::WindowsAndMessaging::{
+            GetClientRect, GWLP_WNDPROC, HTCLIENT, WM_KEYDOWN, WM_MOUSEMOVE, WM_SETCURSOR,
+            WM_SYSKEYDOWN,
+        },
     },
 };
 
+#[cfg(feature = "record")]
+use crate::record::{InputRecorder, InputReplayer};
 use crate::{
-    backup::BackupState,
-    input::{InputCollector, InputResult},
-    mesh::{convert_meshes, GpuMesh, GpuVertex, MeshBuffers},
+    backup::{BackupState, ClearStages},
+    builder::DirectX11AppBuilder,
+    clipboard::Win32Clipboard,
+    constants::{FrameConstantBuffer, FrameConstants, FRAME_CONSTANTS_SLOT},
+    cursor::{paint_software_cursor, CursorClip, CursorState, CursorVisibility},
+    debug::{MemoryUsage, RenderStats},
+    depth::DepthBuffer,
+    gamepad::GamepadButtons,
+    input::{ExtraButtons, FunctionKeys, InputCollector, InputPolicy, InputResult, WantsInput},
+    keybind::{Keybind, KeybindManager},
+    layers::{Cadence, ContextId, ContextLayers},
+    mesh::{convert_meshes, has_area, GpuMesh, GpuVertex},
+    overlay::{OverlayLayer, OverlayPainter, OverlayShapes},
+    panel::TexturePanel,
+    platform::PlatformOutputHandler,
+    pool::BufferPool,
     shader::CompiledShaders,
-    texture::TextureAllocator,
+    stream::GeometryStream,
+    sync::{Mutex, MutexGuard},
+    texture::{is_downlevel, max_texture_side, TextureAllocator},
+    time::PerformanceCounter,
+    widgets::FrameHistory,
 };
 
+/// Where in the chain of `Present` hooks the UI is drawn.
+///
+/// Capture tools like OBS game capture copy the backbuffer inside their own `Present` hook,
+/// so the UI appears in recordings only if it is drawn before their hook runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentOrder {
+    /// Draws inside of [`DirectX11App::present`], before the rest of the chain.
+    /// The UI shows up in game capture.
+    Immediate,
+    /// Draws inside of [`DirectX11App::present_deferred`], called from a second hook
+    /// positioned after the other hooks. The UI is hidden from game capture.
+    Deferred,
+}
+
+impl Default for PresentOrder {
+    fn default() -> Self {
+        Self::Immediate
+    }
+}
+
+/// Render target view and viewport the UI is drawn into instead of the back buffer.
+type RenderTarget = (ID3D11RenderTargetView, D3D11_VIEWPORT);
+
 /// Heart and soul of this integration.
 /// Main methods you are going to use are:
 /// * [`Self::present`] - Should be called inside of hook are before present.
@@ -48,14 +105,56 @@ use crate::{
 /// * [`Self::wnd_proc`] - Should be called on each `WndProc`.
 pub struct DirectX11App<T = ()> {
     ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
-    render_view: Mutex<ID3D11RenderTargetView>,
+    /// Released for the duration of swapchain's `ResizeBuffers`.
+    render_view: Mutex<Option<ID3D11RenderTargetView>>,
+    /// Target set for the duration of [`DirectX11App::present_to`].
+    target_override: Mutex<Option<RenderTarget>>,
     input_collector: InputCollector,
     input_layout: ID3D11InputLayout,
     tex_alloc: TextureAllocator,
     sampler: ID3D11SamplerState,
+    constants: FrameConstantBuffer,
+    blend_state: ID3D11BlendState,
+    raster_state: ID3D11RasterizerState,
+    clear_stages: ClearStages,
+    /// Set once static pipeline state is bound in an isolated device context state.
+    pipeline_bound: AtomicBool,
+    present_order: PresentOrder,
+    deferred_frame: AtomicBool,
+    presenting: AtomicBool,
+    /// Minimum time between runs of the ui closure in seconds, see [`DirectX11AppBuilder::ui_rate_limit`].
+    ui_interval: Option<f64>,
+    last_ui_run: Mutex<Option<f64>>,
+    /// Input of frames that only re-rendered cached meshes.
+    pending_input: Mutex<Option<RawInput>>,
+    /// Converted meshes of the last ui run and the number of culled ones, redrawn as they are.
+    cached_meshes: Mutex<(Vec<GpuMesh>, usize)>,
     shaders: CompiledShaders,
     backup: BackupState,
+    stats: Mutex<RenderStats>,
+    cursor: Mutex<CursorState>,
+    software_cursor: bool,
+    cursor_visibility: Mutex<CursorVisibility>,
+    manage_cursor: bool,
+    cursor_clip: Mutex<CursorClip>,
+    release_cursor_clip: bool,
+    visible: AtomicBool,
+    wants_pointer: AtomicBool,
+    wants_keyboard: AtomicBool,
+    /// Cloned out before use, so `InputPolicy::Custom` can replace the policy.
+    input_policy: Mutex<Arc<InputPolicy>>,
+    keybinds: Mutex<KeybindManager>,
+    depth: Mutex<Option<DepthBuffer>>,
+    frame_history: Mutex<FrameHistory>,
+    overlay: Mutex<OverlayShapes>,
+    buffer_pool: Mutex<BufferPool>,
+    platform_handler: Mutex<Option<Box<dyn PlatformOutputHandler>>>,
+    layers: Mutex<ContextLayers>,
+    panels: Mutex<Vec<TexturePanel>>,
+    render_queue: Mutex<Vec<RenderCommand>>,
     ctx: Mutex<Context>,
+    /// Style queued by [`Self::set_style`], applied before the ui closure runs.
+    pending_style: Mutex<Option<Arc<Style>>>,
     state: Mutex<T>,
     hwnd: HWND,
 }
@@ -63,6 +162,10 @@ pub struct DirectX11App<T = ()> {
 impl<T> DirectX11App<T> {
     #[inline]
     fn get_screen_size(&self) -> Pos2 {
+        if let Some((_, viewport)) = &*self.target_override.lock() {
+            return Pos2::new(viewport.Width, viewport.Height);
+        }
+
         let mut rect = RECT::default();
         unsafe {
             GetClientRect(self.hwnd, &mut rect);
@@ -113,25 +216,38 @@ impl<T> DirectX11App<T> {
     ];
 
     fn create_input_layout(shaders: &CompiledShaders, device: &ID3D11Device) -> ID3D11InputLayout {
+        // Feature level 9_x has no integer vertex formats, mode byte is read as unorm.
+        let mut elements = Self::LAYOUT_ELEMENTS;
+        if shaders.downlevel {
+            elements[3].Format = DXGI_FORMAT_R8G8B8A8_UNORM;
+        }
+
         unsafe {
             expect!(
                 device.CreateInputLayout(
-                    Self::LAYOUT_ELEMENTS.as_ptr(),
-                    Self::LAYOUT_ELEMENTS.len() as _,
+                    elements.as_ptr(),
+                    elements.len() as _,
                     shaders.get_vertex_bytecode() as _,
                     shaders.get_vertex_bytecode_len()
                 ),
+                CreateInputLayout,
                 "Failed to create input layout."
             )
         }
     }
 
     fn create_sampler_state(device: &ID3D11Device) -> ID3D11SamplerState {
+        // Border addressing isn't available on every 9_x device.
+        let address = if is_downlevel(device) {
+            D3D11_TEXTURE_ADDRESS_CLAMP
+        } else {
+            D3D11_TEXTURE_ADDRESS_BORDER
+        };
         let sampler_desc = D3D11_SAMPLER_DESC {
             Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
-            AddressU: D3D11_TEXTURE_ADDRESS_BORDER,
-            AddressV: D3D11_TEXTURE_ADDRESS_BORDER,
-            AddressW: D3D11_TEXTURE_ADDRESS_BORDER,
+            AddressU: address,
+            AddressV: address,
+            AddressW: address,
             MipLODBias: 0.,
             MaxAnisotropy: 1,
             ComparisonFunc: D3D11_COMPARISON_ALWAYS,
@@ -143,35 +259,13 @@ impl<T> DirectX11App<T> {
         unsafe {
             expect!(
                 device.CreateSamplerState(&sampler_desc),
+                CreateSamplerState,
                 "Failed to create sampler state"
             )
         }
     }
 
-    /// Converts texture coords to directx coords which looks like this.
-    /// (-1, 1) ============ (1 , 1)
-    /// ||                        ||
-    /// ||         (0, 0)         ||
-    /// ||                        ||
-    /// (-1,-1) ============ (1 ,-1)
-    fn normalize_meshes(&self, meshes: &mut [GpuMesh]) {
-        let mut screen_half = self.get_screen_size();
-        screen_half.x /= 2.;
-        screen_half.y /= 2.;
-
-        meshes
-            .iter_mut()
-            .flat_map(|m| &mut m.vertices)
-            .for_each(|v| {
-                v.pos.x -= screen_half.x;
-                v.pos.y -= screen_half.y;
-
-                v.pos.x /= screen_half.x;
-                v.pos.y /= -screen_half.y;
-            })
-    }
-
-    fn set_blend_state(&self, device: &ID3D11Device, context: &ID3D11DeviceContext) {
+    fn create_blend_state(device: &ID3D11Device) -> ID3D11BlendState {
         unsafe {
             let mut targets: [D3D11_RENDER_TARGET_BLEND_DESC; 8] = zeroed();
             targets[0].BlendEnable = true.into();
@@ -189,15 +283,20 @@ impl<T> DirectX11App<T> {
                 RenderTarget: targets,
             };
 
-            let state = expect!(
+            expect!(
                 device.CreateBlendState(&blend_desc),
+                CreateBlendState,
                 "Failed to create blend state."
-            );
-            context.OMSetBlendState(&state, [0., 0., 0., 0.].as_ptr(), 0xffffffff);
+            )
         }
     }
 
-    fn set_viewports(&self, context: &ID3D11DeviceContext) {
+    fn set_viewports(&self, context: &ID3D11DeviceContext, target: Option<&RenderTarget>) {
+        if let Some((_, viewport)) = target {
+            unsafe { context.RSSetViewports(1, viewport) };
+            return;
+        }
+
         let size = self.get_screen_size();
         let viewport = D3D11_VIEWPORT {
             TopLeftX: 0.,
@@ -213,7 +312,7 @@ impl<T> DirectX11App<T> {
         }
     }
 
-    fn set_raster_state(&self, device: &ID3D11Device, context: &ID3D11DeviceContext) {
+    fn create_raster_state(device: &ID3D11Device) -> ID3D11RasterizerState {
         let raster_desc = D3D11_RASTERIZER_DESC {
             FillMode: D3D11_FILL_SOLID,
             CullMode: D3D11_CULL_NONE,
@@ -228,78 +327,177 @@ impl<T> DirectX11App<T> {
         };
 
         unsafe {
-            let raster_state = expect!(
+            expect!(
                 device.CreateRasterizerState(&raster_desc),
+                CreateRasterizerState,
                 "Failed to create rasterizer descriptor"
-            );
+            )
+        }
+    }
 
-            context.RSSetState(&raster_state);
+    /// Unbinds shader stages selected with [`DirectX11AppBuilder::clear_stages`].
+    fn clear_stages(&self, ctx: &ID3D11DeviceContext) {
+        unsafe {
+            if self.clear_stages.geometry {
+                ctx.GSSetShader(None, null(), 0);
+            }
+            if self.clear_stages.hull {
+                ctx.HSSetShader(None, null(), 0);
+            }
+            if self.clear_stages.domain {
+                ctx.DSSetShader(None, null(), 0);
+            }
+            if self.clear_stages.compute {
+                ctx.CSSetShader(None, null(), 0);
+            }
         }
     }
 
     fn render_meshes(
         &self,
-        mut meshes: Vec<GpuMesh>,
+        meshes: &[GpuMesh],
+        culled: usize,
+        pixels_per_point: f32,
+        target: Option<&RenderTarget>,
         device: &ID3D11Device,
         ctx: &ID3D11DeviceContext,
     ) {
-        self.backup.save(ctx);
+        profile_scope!("render_meshes");
 
-        self.normalize_meshes(&mut meshes);
-        self.set_viewports(ctx);
-        self.set_blend_state(device, ctx);
-        self.set_raster_state(device, ctx);
+        let stats = &mut *self.stats.lock();
+        stats.begin_frame();
+        stats.culled_meshes = culled;
 
-        let view_lock = &mut *self.render_view.lock();
+        // Nothing would be drawn, game's state doesn't have to be touched at all.
+        if meshes.is_empty() {
+            return;
+        }
+
+        let screen_size = match target {
+            Some((_, viewport)) => Pos2::new(viewport.Width, viewport.Height),
+            None => self.get_screen_size(),
+        };
+        let view_lock = self.render_view.lock();
+        let (view, offset) = match (target, &*view_lock) {
+            (Some((view, viewport)), _) => (view, Pos2::new(viewport.TopLeftX, viewport.TopLeftY)),
+            (None, Some(view)) => (view, Pos2::ZERO),
+            (None, None) => return,
+        };
+
+        let backup_start = Instant::now();
+        self.backup.save(ctx);
+        stats.backup_cost += backup_start.elapsed();
 
         unsafe {
             #[cfg(feature = "clear")]
-            ctx.ClearRenderTargetView(view_lock.clone(), [0.2, 0.3, 0.9, 1.].as_ptr());
+            ctx.ClearRenderTargetView(view.clone(), [0.2, 0.3, 0.9, 1.].as_ptr());
+
+            // Client area can change without `ResizeBuffers`, viewport is set every frame.
+            self.set_viewports(ctx, target);
+
+            // Separate context state keeps everything bound last frame,
+            // so static state only has to be set again after it changes.
+            let isolated = self.backup.is_isolated();
+            if !isolated || !self.pipeline_bound.swap(true, Ordering::SeqCst) {
+                ctx.OMSetBlendState(&self.blend_state, [0., 0., 0., 0.].as_ptr(), 0xffffffff);
+                ctx.RSSetState(&self.raster_state);
 
-            ctx.OMSetRenderTargets(1, transmute(view_lock), None);
-            ctx.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
-            ctx.IASetInputLayout(&self.input_layout);
+                ctx.OMSetRenderTargets(1, transmute(view), None);
+                ctx.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+                ctx.IASetInputLayout(&self.input_layout);
 
-            ctx.VSSetShader(&self.shaders.vertex, null(), 0);
-            ctx.PSSetShader(&self.shaders.pixel, null(), 0);
-            ctx.PSSetSamplers(0, 1, transmute(&self.sampler));
-            ctx.GSSetShader(None, null(), 0);
+                ctx.VSSetShader(&self.shaders.vertex, null(), 0);
+                ctx.PSSetShader(&self.shaders.pixel, null(), 0);
+                ctx.PSSetSamplers(0, 1, transmute(&self.sampler));
 
+                let constants = Some(self.constants.buffer().clone());
+                ctx.VSSetConstantBuffers(FRAME_CONSTANTS_SLOT, 1, &constants);
+                ctx.PSSetConstantBuffers(FRAME_CONSTANTS_SLOT, 1, &constants);
+                self.clear_stages(ctx);
+            }
+            self.constants
+                .write(ctx, [screen_size.x, screen_size.y], pixels_per_point);
+
+            let frame = self.tex_alloc.frame();
             let tex_lock = self.tex_alloc.allocated();
+            let pool = &mut *self.buffer_pool.lock();
+            let mut stream = GeometryStream::new(pool, device, meshes);
 
-            for mesh in &meshes {
-                let buffers = MeshBuffers::new(device, mesh);
+            ctx.IASetVertexBuffers(
+                0,
+                1,
+                &Some(stream.vertex_buffer().clone()),
+                &(size_of::<GpuVertex>() as _),
+                &0,
+            );
+            ctx.IASetIndexBuffer(stream.index_buffer(), DXGI_FORMAT_R32_UINT, 0);
 
-                ctx.IASetVertexBuffers(
-                    0,
-                    1,
-                    &Some(buffers.vertex),
-                    &(size_of::<GpuVertex>() as _),
-                    &0,
-                );
-                ctx.IASetIndexBuffer(&buffers.index, DXGI_FORMAT_R32_UINT, 0);
+            // Keyed mutexes of shared textures are acquired once per frame, at their first mesh.
+            let mut acquired = HashMap::new();
 
-                if let Some(tex) = tex_lock.get(&mesh.tex_id) {
-                    ctx.PSSetShaderResources(0, 1, transmute(tex.resource()));
-                } else {
-                    unreachable!()
+            for mesh in meshes {
+                let tex = match tex_lock.get(&mesh.tex_id) {
+                    Some(tex) => tex,
+                    None => {
+                        stats.push_error(format!("Missing texture {:?}.", mesh.tex_id));
+                        continue;
+                    }
+                };
+
+                tex.touch(frame);
+
+                // Shared texture is being written by its producer.
+                if !*acquired.entry(mesh.tex_id).or_insert_with(|| tex.acquire()) {
+                    continue;
                 }
+                ctx.PSSetShaderResources(0, 1, transmute(tex.resource()));
+
+                let streamed = stream.push(ctx, mesh, screen_size);
 
                 ctx.RSSetScissorRects(
                     1,
                     &RECT {
-                        left: (mesh.rect.min.x) as _,
-                        top: (mesh.rect.min.y) as _,
-                        right: (mesh.rect.max.x) as _,
-                        bottom: (mesh.rect.max.y) as _,
+                        left: (mesh.rect.min.x + offset.x) as _,
+                        top: (mesh.rect.min.y + offset.y) as _,
+                        right: (mesh.rect.max.x + offset.x) as _,
+                        bottom: (mesh.rect.max.y + offset.y) as _,
                     },
                 );
 
-                ctx.DrawIndexed(mesh.indices.len() as _, 0, 0);
+                ctx.DrawIndexed(
+                    mesh.indices.len() as _,
+                    streamed.start_index,
+                    streamed.base_vertex,
+                );
+
+                stats.draw_calls += 1;
+                stats.vertices += mesh.vertices.len();
+                stats.indices += mesh.indices.len();
+                stats.buffer_bytes += mesh.byte_size();
+            }
+
+            for (id, _) in acquired.into_iter().filter(|(_, acquired)| *acquired) {
+                if let Some(tex) = tex_lock.get(&id) {
+                    tex.release();
+                }
             }
+
+            pool.recycle();
         }
 
+        stats.meshes = meshes.len();
+
+        let backup_start = Instant::now();
         self.backup.restore(ctx);
+        stats.backup_cost += backup_start.elapsed();
+    }
+
+    fn update_backbuffer_stats(&self, swap_chain: &IDXGISwapChain) {
+        if let Ok(desc) = unsafe { swap_chain.GetDesc() } {
+            let stats = &mut *self.stats.lock();
+            stats.backbuffer_format = desc.BufferDesc.Format;
+            stats.backbuffer_size = [desc.BufferDesc.Width, desc.BufferDesc.Height];
+        }
     }
 }
 
@@ -328,6 +526,293 @@ impl<T> DirectX11App<T> {
         self.ctx.lock()
     }
 
+    /// Shows or hides the overlay. Hidden overlay doesn't run the ui closure and doesn't render.
+    #[inline]
+    pub fn set_visible(&self, visible: bool) {
+        self.visible.store(visible, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible.load(Ordering::SeqCst)
+    }
+
+    /// Replaces style of the context, applied from the next frame.
+    /// Can be called from the ui closure, unlike `context().set_style`.
+    #[inline]
+    pub fn set_style(&self, style: impl Into<Arc<Style>>) {
+        *self.pending_style.lock() = Some(style.into());
+    }
+
+    /// Returns whether egui currently owns pointer or keyboard input,
+    /// so game side code can ignore input captured by the overlay.
+    /// Always empty while the overlay is hidden.
+    #[inline]
+    pub fn wants_input(&self) -> WantsInput {
+        WantsInput {
+            pointer: self.wants_pointer.load(Ordering::SeqCst),
+            keyboard: self.wants_keyboard.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Returns state of side mouse buttons for the current frame.
+    /// egui has no buttons besides primary, secondary and middle ones so they're reported here.
+    #[inline]
+    pub fn extra_buttons(&self) -> ExtraButtons {
+        self.input_collector.extra_buttons()
+    }
+
+    /// Returns state of F1 to F24 keys for the current frame, egui has no `Key` for them.
+    /// Their messages are still classified as [`crate::MessageKind::Key`].
+    #[inline]
+    pub fn function_keys(&self) -> FunctionKeys {
+        self.input_collector.function_keys()
+    }
+
+    /// Sets state of gamepad buttons used for navigating the overlay, should be updated every frame.
+    /// Ignored when the builder was configured to poll XInput.
+    #[inline]
+    pub fn set_gamepad(&self, buttons: Option<GamepadButtons>) {
+        self.input_collector.set_gamepad(buttons);
+    }
+
+    /// Sets handler receiving platform output every frame, replacing the previous one.
+    #[inline]
+    pub fn set_platform_output_handler(&self, handler: Option<Box<dyn PlatformOutputHandler>>) {
+        *self.platform_handler.lock() = handler;
+    }
+
+    /// Adds another egui context with its own ui closure, drawn below the app's own context
+    /// for negative `order` and above it otherwise. Useful for keeping a cheap HUD updated
+    /// every frame while a heavy menu only updates on input, see [`Cadence`].
+    /// Input is routed between contexts, see [`Self::layer_wants_input`].
+    pub fn add_context(
+        &self,
+        order: i32,
+        cadence: Cadence,
+        ui: impl FnMut(&Context) + 'static,
+    ) -> ContextId {
+        self.layers.lock().add(order, cadence, Box::new(ui))
+    }
+
+    /// Removes context added with [`Self::add_context`] and frees its textures.
+    pub fn remove_context(&self, id: ContextId) -> bool {
+        let removed = self.layers.lock().remove(id);
+        if removed {
+            self.tex_alloc.allocated().retain(|tex, _| !id.owns(tex));
+        }
+        removed
+    }
+
+    /// Returns context added with [`Self::add_context`], e.g. to change its style.
+    #[inline]
+    pub fn layer_context(&self, id: ContextId) -> Option<Context> {
+        self.layers.lock().context(id)
+    }
+
+    /// Returns input captured by a single context, [`ContextId::MAIN`] for the app's own one.
+    /// Events are delivered from the topmost context down and stop at the first context
+    /// capturing them, so e.g. a popup with keyboard focus keeps keys from passive HUD below.
+    #[inline]
+    pub fn layer_wants_input(&self, id: ContextId) -> Option<WantsInput> {
+        self.layers.lock().wants(id)
+    }
+
+    /// Makes context added with [`Self::add_context`] run its ui closure on the next frame.
+    #[inline]
+    pub fn request_update(&self, id: ContextId) {
+        self.layers.lock().request_update(id);
+    }
+
+    /// Adds ui rendered every frame into a transparent texture of `size` pixels instead of
+    /// the screen, to be mapped onto in-world surfaces. The texture is created on the next
+    /// present, its view is returned by [`Self::panel_texture`].
+    pub fn add_texture_panel(
+        &self,
+        size: [u32; 2],
+        ui: impl FnMut(&Context) + 'static,
+    ) -> ContextId {
+        let panel = TexturePanel::new(size, Box::new(ui));
+        let id = panel.id;
+        self.panels.lock().push(panel);
+        id
+    }
+
+    /// Removes panel added with [`Self::add_texture_panel`] and frees its textures.
+    pub fn remove_texture_panel(&self, id: ContextId) -> bool {
+        let panels = &mut *self.panels.lock();
+        let len = panels.len();
+        panels.retain(|panel| panel.id != id);
+
+        let removed = panels.len() != len;
+        if removed {
+            self.tex_alloc.allocated().retain(|tex, _| !id.owns(tex));
+        }
+        removed
+    }
+
+    /// Returns shader resource view of the panel's texture, `None` before its first present.
+    /// The texture is persistent, so the view can be kept and bound by the game each frame.
+    #[inline]
+    pub fn panel_texture(&self, id: ContextId) -> Option<ID3D11ShaderResourceView> {
+        self.panels
+            .lock()
+            .iter()
+            .find(|panel| panel.id == id)
+            .and_then(TexturePanel::resource)
+    }
+
+    /// Returns context of panel added with [`Self::add_texture_panel`].
+    #[inline]
+    pub fn panel_context(&self, id: ContextId) -> Option<Context> {
+        self.panels
+            .lock()
+            .iter()
+            .find(|panel| panel.id == id)
+            .map(TexturePanel::context)
+    }
+
+    /// Queues input for a panel, in its pixel coordinates. Panels don't receive window
+    /// input, as only the game knows where on the surface the pointer is.
+    pub fn push_panel_events(&self, id: ContextId, events: impl IntoIterator<Item = Event>) {
+        if let Some(panel) = self.panels.lock().iter_mut().find(|panel| panel.id == id) {
+            panel.push_events(events);
+        }
+    }
+
+    fn draw_panels(&self, time: Option<f64>, device: &ID3D11Device, context: &ID3D11DeviceContext) {
+        let panels = &mut *self.panels.lock();
+        if panels.is_empty() {
+            return;
+        }
+        profile_scope!("draw_panels");
+
+        for panel in panels {
+            let (clipped, delta) = panel.run(time);
+            self.tex_alloc.resolve_delta(delta, device, context);
+
+            let (view, viewport) = panel.begin(device, context);
+            let (meshes, culled) = convert_meshes(
+                clipped,
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(viewport.Width, viewport.Height)),
+            );
+
+            // Static state bound in an isolated context state targets the frame's render target.
+            self.pipeline_bound.store(false, Ordering::SeqCst);
+            let target = (view, viewport);
+            self.render_meshes(&meshes, culled, 1., Some(&target), device, context);
+        }
+
+        // Rebinds the frame's render target for the main ui drawn after panels.
+        self.pipeline_bound.store(false, Ordering::SeqCst);
+    }
+
+    /// Queues closure executed with the swapchain's device and immediate context at the start
+    /// of the next present, before the UI is drawn. Lets other threads create or release
+    /// GPU resources without racing the hook, closures run in the order they were queued.
+    pub fn run_on_render_thread(
+        &self,
+        command: impl FnOnce(&ID3D11Device, &ID3D11DeviceContext) + Send + 'static,
+    ) {
+        self.render_queue.lock().push(Box::new(command));
+    }
+
+    /// Returns statistics of the last rendered frame.
+    pub fn stats(&self) -> RenderStats {
+        self.stats.lock().clone()
+    }
+
+    /// Queues shapes drawn on a layer below or above all egui windows during the next present.
+    /// Can be called from any thread and multiple times per frame, shapes are shown only once.
+    pub fn draw_overlay<R>(
+        &self,
+        layer: OverlayLayer,
+        draw: impl FnOnce(&mut OverlayPainter) -> R,
+    ) -> R {
+        draw(self.overlay.lock().layer(layer))
+    }
+
+    /// Returns uniforms written for the last draw, see [`FrameConstants`].
+    #[inline]
+    pub fn frame_constants(&self) -> FrameConstants {
+        self.constants.last()
+    }
+
+    /// Returns buffer the UI binds at [`FRAME_CONSTANTS_SLOT`], for binding it in own passes.
+    /// Its contents are only updated while the UI is drawn.
+    #[inline]
+    pub fn frame_constants_buffer(&self) -> ID3D11Buffer {
+        self.constants.buffer().clone()
+    }
+
+    /// Returns durations of recently presented frames.
+    pub fn frame_history(&self) -> FrameHistory {
+        self.frame_history.lock().clone()
+    }
+
+    /// Returns video memory currently used by textures and mesh buffers.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let (texture_count, texture_bytes) = {
+            let lock = self.tex_alloc.allocated();
+            (lock.len(), lock.values().map(|t| t.byte_size()).sum())
+        };
+
+        MemoryUsage {
+            buffer_bytes: self.buffer_pool.lock().byte_size(),
+            texture_count,
+            texture_bytes,
+        }
+    }
+
+    /// Returns allocator owning all textures, used for registering user textures.
+    #[inline]
+    pub fn textures(&self) -> &TextureAllocator {
+        &self.tex_alloc
+    }
+
+    /// Registers game's depth buffer so it can be sampled by custom rendering code,
+    /// e.g. to depth-test 3D overlay drawings against world geometry. `None` removes it.
+    /// Returns `false` if the buffer can't be read by shaders, it has to be created with
+    /// a typeless format and `D3D11_BIND_SHADER_RESOURCE`.
+    pub fn set_depth_buffer(&self, view: Option<&ID3D11DepthStencilView>) -> bool {
+        let depth = match view {
+            Some(view) => match DepthBuffer::new(view) {
+                Some(depth) => Some(depth),
+                None => return false,
+            },
+            None => None,
+        };
+
+        *self.depth.lock() = depth;
+        true
+    }
+
+    /// Returns shader resource view reading depth of the buffer registered with
+    /// [`Self::set_depth_buffer`] together with its size.
+    #[inline]
+    pub fn depth_buffer(&self) -> Option<(ID3D11ShaderResourceView, [usize; 2])> {
+        self.depth
+            .lock()
+            .as_ref()
+            .map(|depth| (depth.resource.clone(), depth.size))
+    }
+
+    /// Returns ids and sizes of all allocated textures.
+    pub(crate) fn texture_list(&self) -> Vec<(TextureId, [usize; 2])> {
+        let mut list = self
+            .tex_alloc
+            .allocated()
+            .iter()
+            .map(|(id, tex)| (*id, tex.size()))
+            .collect::<Vec<_>>();
+
+        list.sort_by_key(|(id, _)| match id {
+            TextureId::Managed(i) => (0, *i),
+            TextureId::User(i) => (1, *i),
+        });
+        list
+    }
+
     /// Creates new app with state initialized from closule call.
     #[inline]
     pub fn new_with(
@@ -339,63 +824,336 @@ impl<T> DirectX11App<T> {
     }
 
     /// Creates new app with explicit state value.
+    /// Use [`DirectX11AppBuilder`] to configure the app further.
+    #[inline]
     pub fn new_with_state(
         ui: impl FnMut(&Context, &mut T) + 'static,
         swap_chain: &IDXGISwapChain,
         state: T,
+    ) -> Self {
+        DirectX11AppBuilder::new(ui, state).build(swap_chain)
+    }
+
+    pub(crate) fn from_builder(
+        builder: DirectX11AppBuilder<T>,
+        swap_chain: &IDXGISwapChain,
     ) -> Self {
         unsafe {
-            let hwnd = expect!(
-                swap_chain.GetDesc(),
-                "Failed to get swapchain's descriptor."
-            )
-            .OutputWindow;
+            let hwnd = match builder.window {
+                Some(hwnd) => hwnd,
+                None => {
+                    expect!(
+                        swap_chain.GetDesc(),
+                        GetSwapChainDesc,
+                        "Failed to get swapchain's descriptor."
+                    )
+                    .OutputWindow
+                }
+            };
 
             let (device, _) = get_device_context(swap_chain);
 
             if hwnd.is_invalid() {
-                if !cfg!(feature = "no-msgs") {
-                    panic!("Invalid output window descriptor.");
-                } else {
-                    unreachable!()
-                }
+                crate::error::fail(
+                    crate::ErrorCode::InvalidOutputWindow,
+                    E_HANDLE,
+                    if cfg!(feature = "no-msgs") {
+                        ""
+                    } else {
+                        "Invalid output window descriptor."
+                    },
+                );
             }
 
             let back_buffer: ID3D11Texture2D = expect!(
                 swap_chain.GetBuffer(0),
+                GetBackBuffer,
                 "Failed to get swapchain's back buffer"
             );
 
             let render_view = expect!(
                 device.CreateRenderTargetView(&back_buffer, null()),
+                CreateRenderTargetView,
                 "Failed to create render target view."
             );
 
             let shaders = CompiledShaders::new(&device);
 
-            Self {
+            // Fonts are built during the first `begin_frame`, setting them now doesn't race
+            // the ui closure.
+            let ctx = Context::default();
+            if let Some(style) = builder.style {
+                ctx.set_style(style);
+            }
+            builder.fonts.apply(&ctx);
+
+            let app = Self {
                 input_layout: Self::create_input_layout(&shaders, &device),
                 sampler: Self::create_sampler_state(&device),
-                input_collector: InputCollector::new(hwnd),
-                render_view: Mutex::new(render_view),
-                ctx: Mutex::new(Context::default()),
+                constants: FrameConstantBuffer::new(&device),
+                blend_state: Self::create_blend_state(&device),
+                raster_state: Self::create_raster_state(&device),
+                clear_stages: builder.clear_stages,
+                pipeline_bound: AtomicBool::new(false),
+                present_order: builder.present_order,
+                ui_interval: builder.ui_rate_limit.map(|rate| 1. / rate.max(1.) as f64),
+                last_ui_run: Mutex::new(None),
+                pending_input: Mutex::new(None),
+                cached_meshes: Mutex::new((vec![], 0)),
+                deferred_frame: AtomicBool::new(false),
+                presenting: AtomicBool::new(false),
+                input_collector: InputCollector::new(
+                    hwnd,
+                    builder
+                        .clipboard
+                        .unwrap_or_else(|| Box::new(Win32Clipboard::new(hwnd))),
+                    builder
+                        .time_source
+                        .unwrap_or_else(|| Box::new(PerformanceCounter::new())),
+                    builder.input_options,
+                ),
+                render_view: Mutex::new(Some(render_view)),
+                target_override: Mutex::new(None),
+                ctx: Mutex::new(ctx),
+                pending_style: Mutex::new(None),
                 tex_alloc: TextureAllocator::default(),
-                state: Mutex::new(state),
-                backup: BackupState::default(),
-                ui: Box::new(ui),
+                state: Mutex::new(builder.state),
+                backup: BackupState::new(
+                    builder.backup_level,
+                    builder.backup_slots,
+                    builder.clear_stages,
+                    builder.swap_context_state,
+                ),
+                stats: Mutex::new(RenderStats::default()),
+                cursor: Mutex::new(CursorState::default()),
+                software_cursor: builder.software_cursor,
+                cursor_visibility: Mutex::new(CursorVisibility::default()),
+                manage_cursor: builder.manage_cursor,
+                cursor_clip: Mutex::new(CursorClip::default()),
+                release_cursor_clip: builder.release_cursor_clip,
+                visible: AtomicBool::new(true),
+                wants_pointer: AtomicBool::new(false),
+                wants_keyboard: AtomicBool::new(false),
+                input_policy: Mutex::new(Arc::new(builder.input_policy)),
+                keybinds: Mutex::new(KeybindManager::default()),
+                depth: Mutex::new(None),
+                frame_history: Mutex::new(FrameHistory::default()),
+                overlay: Mutex::new(OverlayShapes::default()),
+                buffer_pool: Mutex::new(BufferPool::default()),
+                platform_handler: Mutex::new(builder.platform_handler),
+                layers: Mutex::new(ContextLayers::default()),
+                panels: Mutex::new(vec![]),
+                render_queue: Mutex::new(vec![]),
+                ui: builder.ui,
                 shaders,
                 hwnd,
-            }
+            };
+
+            app.update_backbuffer_stats(swap_chain);
+            app
         }
     }
 
     /// Present call. Should be called once per original present call, before or inside of hook.
+    /// With [`PresentOrder::Deferred`] only marks the frame, which is then drawn
+    /// by [`Self::present_deferred`].
+    ///
+    /// Nested calls, e.g. from another overlay presenting inside of its own hook, are ignored.
     pub fn present(&self, swap_chain: &IDXGISwapChain, _sync_interval: u32, _flags: u32) {
-        let (device, context) = get_device_context(swap_chain);
+        let _guard = match ReentrancyGuard::enter(&self.presenting) {
+            Some(guard) => guard,
+            None => return,
+        };
+
+        match self.present_order {
+            PresentOrder::Immediate => {
+                let (device, context) = get_device_context(swap_chain);
+                self.draw_frame(&device, &context);
+            }
+            PresentOrder::Deferred => self.deferred_frame.store(true, Ordering::SeqCst),
+        }
+    }
+
+    /// Draws frame marked by [`Self::present`] with [`PresentOrder::Deferred`].
+    /// Should be called from a second hook that runs after capture tools, e.g. a `Present`
+    /// hook installed below theirs. Does nothing if no frame is pending.
+    pub fn present_deferred(&self, swap_chain: &IDXGISwapChain) {
+        let _guard = match ReentrancyGuard::enter(&self.presenting) {
+            Some(guard) => guard,
+            None => return,
+        };
+
+        if self.deferred_frame.swap(false, Ordering::SeqCst) {
+            let (device, context) = get_device_context(swap_chain);
+            self.draw_frame(&device, &context);
+        }
+    }
+
+    /// Runs the ui and renders it into `view` instead of the swapchain's back buffer, e.g. into
+    /// an offscreen or intermediate HDR target. egui's screen is sized to `viewport`, while
+    /// pointer positions keep referring to the window. Should be called instead of
+    /// [`Self::present`] for frames drawn elsewhere.
+    pub fn present_to(&self, view: &ID3D11RenderTargetView, viewport: D3D11_VIEWPORT) {
+        let _guard = match ReentrancyGuard::enter(&self.presenting) {
+            Some(guard) => guard,
+            None => return,
+        };
+
+        let (device, context) = unsafe {
+            let mut device = None;
+            view.GetDevice(&mut device);
+            let device: ID3D11Device =
+                expect!(device, GetDevice, "Failed to get render target's device.");
+
+            let mut context = None;
+            device.GetImmediateContext(&mut context);
+            let context = expect!(
+                context,
+                GetImmediateContext,
+                "Failed to get device's immediate context."
+            );
+            (device, context)
+        };
+
+        self.draw_to(&device, &context, view, viewport);
+    }
+
+    /// Runs the ui and renders it into `view` using device and context supplied by the caller,
+    /// for engines drawing the UI themselves rather than hooking a game's swapchain.
+    /// Saves querying them every frame and works for present paths without a swapchain.
+    /// The viewport covers the whole texture behind `view`.
+    pub fn present_with(
+        &self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        view: &ID3D11RenderTargetView,
+    ) {
+        let _guard = match ReentrancyGuard::enter(&self.presenting) {
+            Some(guard) => guard,
+            None => return,
+        };
+
+        let size = unsafe {
+            let mut resource = None;
+            view.GetResource(&mut resource);
+            match resource.and_then(|res| res.cast::<ID3D11Texture2D>().ok()) {
+                Some(texture) => {
+                    let mut desc = D3D11_TEXTURE2D_DESC::default();
+                    texture.GetDesc(&mut desc);
+                    [desc.Width as f32, desc.Height as f32]
+                }
+                // Views of other resources are assumed to cover the window.
+                None => {
+                    let size = self.get_screen_size();
+                    [size.x, size.y]
+                }
+            }
+        };
+
+        let viewport = D3D11_VIEWPORT {
+            TopLeftX: 0.,
+            TopLeftY: 0.,
+            Width: size[0],
+            Height: size[1],
+            MinDepth: 0.,
+            MaxDepth: 1.,
+        };
+        self.draw_to(device, context, view, viewport);
+    }
+
+    fn draw_to(
+        &self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+        view: &ID3D11RenderTargetView,
+        viewport: D3D11_VIEWPORT,
+    ) {
+        // Static state bound in an isolated context state targets the back buffer.
+        self.pipeline_bound.store(false, Ordering::SeqCst);
+        *self.target_override.lock() = Some((view.clone(), viewport));
+        self.draw_frame(device, context);
+        *self.target_override.lock() = None;
+        self.pipeline_bound.store(false, Ordering::SeqCst);
+    }
+
+    fn draw_frame(&self, device: &ID3D11Device, context: &ID3D11DeviceContext) {
+        #[cfg(feature = "profiling")]
+        puffin::GlobalProfiler::lock().new_frame();
+        profile_scope!("present");
+
+        // Taken out first, so commands can queue more commands for the next present.
+        let commands = std::mem::take(&mut *self.render_queue.lock());
+        for command in commands {
+            command(device, context);
+        }
+
+        if !self.is_visible() {
+            // Input that happened while overlay was hidden shouldn't be applied later.
+            drop(self.input_collector.collect_input());
+            self.overlay.lock().clear();
+            self.pending_input.lock().take();
+            *self.cached_meshes.lock() = (vec![], 0);
+            self.cursor_clip.lock().restore();
+            self.cursor.lock().update(CursorIcon::Default, false);
+            self.wants_pointer.store(false, Ordering::SeqCst);
+            self.wants_keyboard.store(false, Ordering::SeqCst);
+            return;
+        }
+
+        // Minimized windows and windows in the middle of a resize report a degenerate client
+        // area, the ui isn't run at all rather than laid out for a zero sized screen.
+        if !has_area(self.get_screen_size().to_vec2()) {
+            drop(self.input_collector.collect_input());
+            self.stats.lock().begin_frame();
+            return;
+        }
+
+        self.constants.next_frame();
+
+        {
+            profile_scope!("apply_pending_uploads");
+            self.tex_alloc.apply_pending(device, context);
+        }
 
         let ctx_lock = &mut *self.ctx.lock();
 
-        let input = self.input_collector.collect_input();
+        // Applied here rather than in the setters, which the ui closure calls with the context locked.
+        if let Some(style) = self.pending_style.lock().take() {
+            ctx_lock.set_style(style);
+        }
+
+        let mut input = {
+            profile_scope!("collect_input");
+            self.input_collector.collect_input()
+        };
+        // Lets egui size its font atlas for the device instead of relying on downscaling.
+        input.max_texture_side = Some(max_texture_side(device));
+        if self.target_override.lock().is_some() {
+            input.screen_rect = Some(Rect::from_min_max(Pos2::ZERO, self.get_screen_size()));
+        }
+
+        if let Some(time) = input.time {
+            self.frame_history.lock().record(time);
+        }
+
+        self.draw_panels(input.time, device, context);
+
+        let input = match self.pace_input(input) {
+            Some(input) => input,
+            None => {
+                profile_scope!("render_cached");
+                // Shapes queued for this frame would pile up until the next ui run.
+                self.overlay.lock().clear();
+
+                let cached = self.cached_meshes.lock();
+                let ppp = ctx_lock.pixels_per_point();
+                let target = self.target_override.lock().clone();
+                self.render_meshes(&cached.0, cached.1, ppp, target.as_ref(), device, context);
+                self.tex_alloc.end_frame();
+                return;
+            }
+        };
+        let input = self.layers.lock().route(input);
 
         // This should be fine as present can't be called from different threads by
         // a person with enough intelect.
@@ -405,17 +1163,154 @@ impl<T> DirectX11App<T> {
             platform_output,
             textures_delta,
             ..
-        } = ctx_lock.run(input, |u| unsafe { (*ui)(u, &mut *self.state.lock()) });
+        } = {
+            profile_scope!("run_ui");
+            let cursor_icon = self.cursor.lock().icon();
+            ctx_lock.run(input, |u| unsafe {
+                (*ui)(u, &mut *self.state.lock());
+                self.overlay.lock().paint(u);
+
+                if self.software_cursor {
+                    paint_software_cursor(u, cursor_icon);
+                }
+            })
+        };
+
+        let (copy_handled, cursor_handled) = match &mut *self.platform_handler.lock() {
+            Some(handler) => {
+                handler.output(&platform_output);
+                if let Some(url) = &platform_output.open_url {
+                    handler.open_url(url);
+                }
+                (
+                    !platform_output.copied_text.is_empty()
+                        && handler.copied_text(&platform_output.copied_text),
+                    handler.cursor_icon(platform_output.cursor_icon),
+                )
+            }
+            None => (false, false),
+        };
+
+        if !platform_output.copied_text.is_empty()
+            && !copy_handled
+            && !self
+                .input_collector
+                .clipboard()
+                .set_text(&platform_output.copied_text)
+        {
+            self.stats
+                .lock()
+                .push_error("Failed to copy text into clipboard.".into());
+        }
+
+        let layers = &mut *self.layers.lock();
+        let layers_delta = {
+            profile_scope!("run_layers");
+            layers.update()
+        };
 
-        if !platform_output.copied_text.is_empty() {
-            // @TODO: Do clipboard pasting.
+        let over_ui = ctx_lock.is_pointer_over_area() || ctx_lock.is_using_pointer();
+        layers.set_main_wants(WantsInput {
+            pointer: over_ui || ctx_lock.wants_pointer_input(),
+            keyboard: ctx_lock.wants_keyboard_input(),
+        });
+        let layers_wants = layers.any_wants();
+        let over_ui = over_ui || layers_wants.pointer;
+
+        self.wants_pointer
+            .store(over_ui || ctx_lock.wants_pointer_input(), Ordering::SeqCst);
+        self.wants_keyboard.store(
+            ctx_lock.wants_keyboard_input() || layers_wants.keyboard,
+            Ordering::SeqCst,
+        );
+
+        if !cursor_handled {
+            self.cursor
+                .lock()
+                .update(platform_output.cursor_icon, over_ui);
+        }
+
+        if self.release_cursor_clip {
+            let clip = &mut *self.cursor_clip.lock();
+            if self.wants_pointer.load(Ordering::SeqCst) {
+                clip.release();
+            } else {
+                clip.restore();
+            }
+        }
+
+        let (meshes, culled) = {
+            profile_scope!("tessellate");
+            let (mut clipped, above) = layers.meshes();
+            clipped.extend(ctx_lock.tessellate(shapes));
+            clipped.extend(above);
+
+            convert_meshes(
+                clipped,
+                Rect::from_min_max(Pos2::ZERO, self.get_screen_size()),
+            )
+        };
+
+        {
+            profile_scope!("upload_textures");
+            self.tex_alloc
+                .resolve_delta(textures_delta, device, context);
+            self.tex_alloc.resolve_delta(layers_delta, device, context);
         }
 
-        let meshes = convert_meshes(ctx_lock.tessellate(shapes));
-        self.tex_alloc
-            .resolve_delta(textures_delta, &device, &context);
+        let ppp = ctx_lock.pixels_per_point();
+        let target = self.target_override.lock().clone();
+        self.render_meshes(&meshes, culled, ppp, target.as_ref(), device, context);
+        self.tex_alloc.end_frame();
 
-        self.render_meshes(meshes, &device, &context);
+        if self.ui_interval.is_some() {
+            *self.cached_meshes.lock() = (meshes, culled);
+        }
+
+        // Marked as secondary frame to not interfere with game's own frame marks.
+        #[cfg(feature = "tracy")]
+        tracy_client::finish_continuous_frame!("egui-d3d11");
+    }
+
+    /// Returns input the ui closure should run with this frame, or `None` if it isn't due yet
+    /// and the cached meshes should be drawn instead. Skipped input is merged into the next run,
+    /// anything but pointer movement runs the ui closure immediately.
+    fn pace_input(&self, input: RawInput) -> Option<RawInput> {
+        let interval = match self.ui_interval {
+            Some(interval) => interval,
+            None => return Some(input),
+        };
+
+        let pending = &mut *self.pending_input.lock();
+        let input = match pending.take() {
+            Some(mut older) => {
+                older.events.extend(input.events);
+                older.dropped_files.extend(input.dropped_files);
+                RawInput {
+                    events: older.events,
+                    dropped_files: older.dropped_files,
+                    ..input
+                }
+            }
+            None => input,
+        };
+
+        let time = input.time.unwrap_or_default();
+        let last_run = &mut *self.last_ui_run.lock();
+        let due = last_run.map_or(true, |last| time - last >= interval);
+        let urgent = !input.dropped_files.is_empty()
+            || input
+                .events
+                .iter()
+                .any(|e| !matches!(e, Event::PointerMoved(_)));
+
+        if due || urgent {
+            *last_run = Some(time);
+            Some(input)
+        } else {
+            *pending = Some(input);
+            None
+        }
     }
 
     /// Call when resizing buffers.
@@ -428,49 +1323,321 @@ impl<T> DirectX11App<T> {
     ) -> HRESULT {
         unsafe {
             let view_lock = &mut *self.render_view.lock();
-            std::ptr::drop_in_place(view_lock);
+            self.backup.release_targets();
+            drop(view_lock.take());
 
             let result = original();
 
             let backbuffer: ID3D11Texture2D = expect!(
                 swap_chain.GetBuffer(0),
+                GetBackBuffer,
                 "Failed to get swapchain's backbuffer."
             );
 
-            let device: ID3D11Device =
-                expect!(swap_chain.GetDevice(), "Failed to get swapchain's device.");
+            let device: ID3D11Device = expect!(
+                swap_chain.GetDevice(),
+                GetDevice,
+                "Failed to get swapchain's device."
+            );
 
             let new_view = expect!(
                 device.CreateRenderTargetView(&backbuffer, null()),
+                CreateRenderTargetView,
                 "Failed to create render target view."
             );
 
-            *view_lock = new_view;
+            *view_lock = Some(new_view);
+            self.pipeline_bound.store(false, Ordering::SeqCst);
+
+            self.update_backbuffer_stats(swap_chain);
             result
         }
     }
 
+    /// Starts writing all collected input events into file at `path`.
+    /// Replaces previous recording if there was one.
+    #[cfg(feature = "record")]
+    pub fn start_recording(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.input_collector
+            .set_recorder(Some(InputRecorder::create(path)?));
+        Ok(())
+    }
+
+    /// Stops recording and flushes the file.
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn stop_recording(&self) {
+        self.input_collector.set_recorder(None);
+    }
+
+    /// Returns `true` if input is being recorded.
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.input_collector.is_recording()
+    }
+
+    /// Starts feeding events from recording at `path` instead of the real input.
+    /// Replay stops automatically once all frames are consumed.
+    #[cfg(feature = "record")]
+    pub fn start_replay(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.input_collector
+            .set_replayer(Some(InputReplayer::open(path)?));
+        Ok(())
+    }
+
+    /// Stops replay and switches back to the real input.
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn stop_replay(&self) {
+        self.input_collector.set_replayer(None);
+    }
+
+    /// Returns `true` if recorded input is being replayed.
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn is_replaying(&self) -> bool {
+        self.input_collector.is_replaying()
+    }
+
     /// Call on each `WndProc` occurence.
-    /// Returns `true` if message was recognized and dispatched by input handler,
-    /// `false` otherwise.
+    /// Reports how the message was handled. Unless [`InputResult::should_forward`] returns `true`
+    /// the original procedure shouldn't be called and `WndProc` should return `LRESULT(1)`,
+    /// so clicks and keys don't reach the game while interacting with the UI.
     #[inline]
     pub fn wnd_proc(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> InputResult {
-        self.input_collector.process(umsg, wparam.0, lparam.0)
+        // Overlays hooking the window after us can route the same message through
+        // our hook again while it's handled, it must not be collected twice.
+        let _guard = match WndProcGuard::enter() {
+            Some(guard) => guard,
+            None => return InputResult::PassedThrough,
+        };
+
+        self.handle_message(umsg, wparam, lparam)
+    }
+
+    /// Returns `true` if window procedure of the game's window is no longer `ours`,
+    /// e.g. because Steam or Discord overlay hooked it after us. Their hook still calls
+    /// ours, but can be used to decide whether to reinstall the hook.
+    pub fn is_wnd_proc_replaced(&self, ours: usize) -> bool {
+        unsafe {
+            #[cfg(target_pointer_width = "64")]
+            let current = GetWindowLongPtrA(self.hwnd, GWLP_WNDPROC) as usize;
+            #[cfg(target_pointer_width = "32")]
+            let current = GetWindowLongA(self.hwnd, GWLP_WNDPROC) as usize;
+            current != ours
+        }
+    }
+
+    fn handle_message(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> InputResult {
+        if self.apply_cursor(umsg, lparam) {
+            return InputResult::Consumed;
+        }
+
+        let wants = self.wants_input();
+        let result = self.input_collector.process(
+            umsg,
+            wparam.0,
+            lparam.0,
+            &self.input_policy(),
+            self.is_visible(),
+            wants,
+        );
+
+        if let Some(action) = self.find_keybind(umsg, wparam, lparam, wants) {
+            action();
+            return InputResult::Consumed;
+        }
+
+        result
+    }
+
+    #[inline]
+    fn input_policy(&self) -> Arc<InputPolicy> {
+        self.input_policy.lock().clone()
+    }
+
+    /// Changes which messages are forwarded to the game.
+    #[inline]
+    pub fn set_input_policy(&self, policy: InputPolicy) {
+        *self.input_policy.lock() = Arc::new(policy);
+    }
+
+    /// Returns manager of keybinds handled in [`Self::wnd_proc`].
+    /// Lock must not be held while calling `wnd_proc`.
+    #[inline]
+    pub fn keybinds(&self) -> MutexGuard<KeybindManager> {
+        self.keybinds.lock()
+    }
+
+    fn find_keybind(
+        &self,
+        umsg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+        wants: WantsInput,
+    ) -> Option<Arc<dyn Fn()>> {
+        // Bit 30 is set for auto-repeated key presses.
+        if !matches!(umsg, WM_KEYDOWN | WM_SYSKEYDOWN) || lparam.0 & (1 << 30) != 0 {
+            return None;
+        }
+
+        // Typing into focused text field shouldn't trigger actions.
+        if self.is_visible() && wants.keyboard {
+            return None;
+        }
+
+        let keybind = Keybind::from_modifiers(wparam.0 as u16, self.input_collector.modifiers());
+        self.keybinds.lock().find(keybind)
+    }
+
+    /// Returns `true` if the message was used to set egui's cursor.
+    fn apply_cursor(&self, umsg: u32, lparam: LPARAM) -> bool {
+        let force_cursor = self.manage_cursor && self.is_visible();
+        if self.manage_cursor {
+            let visibility = &mut *self.cursor_visibility.lock();
+            if !force_cursor {
+                visibility.restore();
+            } else if umsg == WM_SETCURSOR || umsg == WM_MOUSEMOVE {
+                visibility.show();
+            }
+        }
+
+        umsg == WM_SETCURSOR
+            && (lparam.0 & 0xFFFF) as u32 == HTCLIENT
+            && self.cursor.lock().apply(force_cursor)
+    }
+}
+
+/// Closure queued with [`DirectX11App::run_on_render_thread`].
+type RenderCommand = Box<dyn FnOnce(&ID3D11Device, &ID3D11DeviceContext) + Send>;
+
+/// Clears flag set by [`Self::enter`] once dropped.
+struct ReentrancyGuard<'a>(&'a AtomicBool);
+
+impl<'a> ReentrancyGuard<'a> {
+    /// Returns `None` if the flag is already set.
+    #[inline]
+    fn enter(flag: &'a AtomicBool) -> Option<Self> {
+        (!flag.swap(true, Ordering::SeqCst)).then(|| Self(flag))
+    }
+}
+
+impl Drop for ReentrancyGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+thread_local! {
+    /// Set while [`DirectX11App::wnd_proc`] runs on the thread.
+    static IN_WND_PROC: Cell<bool> = Cell::new(false);
+}
+
+/// Same as [`ReentrancyGuard`] for `wnd_proc`, which may run on several threads at once,
+/// e.g. for the window thread and for messages synthesized by `ExternalOverlay`.
+struct WndProcGuard;
+
+impl WndProcGuard {
+    /// Returns `None` if `wnd_proc` is already running on this thread.
+    #[inline]
+    fn enter() -> Option<Self> {
+        (!IN_WND_PROC.with(|flag| flag.replace(true))).then(|| Self)
+    }
+}
+
+impl Drop for WndProcGuard {
+    #[inline]
+    fn drop(&mut self) {
+        IN_WND_PROC.with(|flag| flag.set(false));
     }
 }
 
 #[inline]
 fn get_device_context(swap_chain: &IDXGISwapChain) -> (ID3D11Device, ID3D11DeviceContext) {
     unsafe {
-        let device: ID3D11Device =
-            expect!(swap_chain.GetDevice(), "Failed to get swapchain's device");
+        let device: ID3D11Device = expect!(
+            swap_chain.GetDevice(),
+            GetDevice,
+            "Failed to get swapchain's device"
+        );
 
         let mut context = None;
         device.GetImmediateContext(&mut context);
 
         (
             device,
-            expect!(context, "Failed to get device's immediate context."),
+            expect!(
+                context,
+                GetImmediateContext,
+                "Failed to get device's immediate context."
+            ),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestWindow;
+    use windows::Win32::{
+        Foundation::S_OK,
+        Graphics::Dxgi::Common::DXGI_FORMAT_UNKNOWN,
+        UI::WindowsAndMessaging::{SetWindowPos, SWP_NOMOVE, SWP_NOZORDER},
+    };
+
+    fn resize(app: &DirectX11App, window: &TestWindow, width: i32, height: i32) -> HRESULT {
+        unsafe {
+            SetWindowPos(
+                window.hwnd,
+                HWND(0),
+                0,
+                0,
+                width,
+                height,
+                SWP_NOMOVE | SWP_NOZORDER,
+            );
+        }
+
+        app.resize_buffers(&window.swap_chain, || unsafe {
+            match window
+                .swap_chain
+                .ResizeBuffers(0, 0, 0, DXGI_FORMAT_UNKNOWN, 0)
+            {
+                Ok(()) => S_OK,
+                Err(e) => e.code(),
+            }
+        })
+    }
+
+    #[test]
+    fn present_after_zero_sized_resize() {
+        let window = TestWindow::new(64, 64);
+        let app = DirectX11App::new_with_default(
+            |ctx: &Context, _: &mut ()| {
+                egui::CentralPanel::default().show(ctx, |ui| ui.label("resize"));
+            },
+            &window.swap_chain,
+        );
+
+        app.present(&window.swap_chain, 0, 0);
+        assert!(app.stats().draw_calls > 0);
+
+        for [width, height] in [[0, 0], [0, 64], [64, 0], [1, 0]] {
+            assert_eq!(
+                resize(&app, &window, width, height),
+                S_OK,
+                "resize to {}x{}",
+                width,
+                height
+            );
+            app.present(&window.swap_chain, 0, 0);
+            assert_eq!(app.stats().draw_calls, 0, "drawn at {}x{}", width, height);
+        }
+
+        assert_eq!(resize(&app, &window, 64, 64), S_OK);
+        app.present(&window.swap_chain, 0, 0);
+        assert!(app.stats().draw_calls > 0);
+    }
+}
@@ -1,42 +1,281 @@
-use std::{cell::RefCell, mem::zeroed};
-use windows::Win32::{
-    Foundation::RECT,
-    Graphics::{
-        Direct3D::D3D_PRIMITIVE_TOPOLOGY,
-        Direct3D11::{
-            ID3D11BlendState, ID3D11Buffer, ID3D11ClassInstance, ID3D11DepthStencilState,
-            ID3D11DeviceContext, ID3D11GeometryShader, ID3D11InputLayout, ID3D11PixelShader,
-            ID3D11RasterizerState, ID3D11SamplerState, ID3D11ShaderResourceView,
-            ID3D11VertexShader, D3D11_VIEWPORT,
-            D3D11_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE,
+use std::{
+    cell::RefCell,
+    ptr::{null, null_mut},
+};
+use windows::{
+    core::Interface,
+    Win32::{
+        Foundation::RECT,
+        Graphics::{
+            Direct3D::D3D_PRIMITIVE_TOPOLOGY,
+            Direct3D11::{
+                ID3D11BlendState, ID3D11Buffer, ID3D11ClassInstance, ID3D11ComputeShader,
+                ID3D11DepthStencilState, ID3D11DepthStencilView, ID3D11Device, ID3D11Device1,
+                ID3D11DeviceContext, ID3D11DeviceContext1, ID3D11DomainShader,
+                ID3D11GeometryShader, ID3D11HullShader, ID3D11InputLayout, ID3D11PixelShader,
+                ID3D11RasterizerState, ID3D11RenderTargetView, ID3D11SamplerState,
+                ID3D11ShaderResourceView, ID3D11UnorderedAccessView, ID3D11VertexShader,
+                ID3DDeviceContextState, D3D11_1_CREATE_DEVICE_CONTEXT_STATE_SINGLETHREADED,
+                D3D11_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT,
+                D3D11_COMMONSHADER_INPUT_RESOURCE_SLOT_COUNT,
+                D3D11_COMMONSHADER_SAMPLER_SLOT_COUNT, D3D11_CREATE_DEVICE_SINGLETHREADED,
+                D3D11_IA_VERTEX_INPUT_RESOURCE_SLOT_COUNT, D3D11_PS_CS_UAV_REGISTER_COUNT,
+                D3D11_SDK_VERSION, D3D11_SIMULTANEOUS_RENDER_TARGET_COUNT, D3D11_VIEWPORT,
+                D3D11_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE,
+            },
+            Dxgi::Common::DXGI_FORMAT,
         },
-        Dxgi::Common::DXGI_FORMAT,
     },
 };
 
+/// How much of the game's pipeline state is saved before rendering and restored after.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackupLevel {
+    /// Nothing is saved, the game has to rebind everything it needs after the present.
+    None,
+    /// Only state the renderer overwrites: first slot of each resource it binds,
+    /// vertex, geometry and pixel shaders, input assembler and output merger.
+    /// Stages selected with [`ClearStages`] are saved as well.
+    Minimal,
+    /// All stages and slots configured with [`BackupSlots`].
+    Full,
+}
+
+impl Default for BackupLevel {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
+/// Number of resource slots of each shader stage saved before rendering and restored after.
+/// Slots above the counts are left as they are, which is only safe if the game doesn't bind
+/// anything there or rebinds it every frame. All slots are saved by default.
+#[derive(Clone, Copy, Debug)]
+pub struct BackupSlots {
+    pub shader_resources: u32,
+    pub samplers: u32,
+    pub constant_buffers: u32,
+    /// Input assembler vertex buffer slots, with their strides and offsets.
+    pub vertex_buffers: u32,
+    /// Unordered access view slots of the compute stage.
+    pub unordered_access_views: u32,
+}
+
+impl Default for BackupSlots {
+    fn default() -> Self {
+        Self {
+            shader_resources: D3D11_COMMONSHADER_INPUT_RESOURCE_SLOT_COUNT,
+            samplers: D3D11_COMMONSHADER_SAMPLER_SLOT_COUNT,
+            constant_buffers: D3D11_COMMONSHADER_CONSTANT_BUFFER_API_SLOT_COUNT,
+            vertex_buffers: D3D11_IA_VERTEX_INPUT_RESOURCE_SLOT_COUNT,
+            unordered_access_views: D3D11_PS_CS_UAV_REGISTER_COUNT,
+        }
+    }
+}
+
+impl BackupSlots {
+    /// Clamps counts to the number of slots supported by D3D11.
+    fn clamped(self) -> Self {
+        let max = Self::default();
+        Self {
+            shader_resources: self.shader_resources.min(max.shader_resources),
+            samplers: self.samplers.min(max.samplers),
+            constant_buffers: self.constant_buffers.min(max.constant_buffers),
+            vertex_buffers: self.vertex_buffers.min(max.vertex_buffers),
+            unordered_access_views: self.unordered_access_views.min(max.unordered_access_views),
+        }
+    }
+}
+
+/// Shader stages the renderer unbinds before drawing, so shaders left bound by the game
+/// don't run on the UI geometry. Only the geometry stage is cleared by default,
+/// tessellation and compute stages only need it if the game leaves them bound.
+#[derive(Clone, Copy, Debug)]
+pub struct ClearStages {
+    pub geometry: bool,
+    pub hull: bool,
+    pub domain: bool,
+    pub compute: bool,
+}
+
+impl Default for ClearStages {
+    fn default() -> Self {
+        Self {
+            geometry: true,
+            hull: false,
+            domain: false,
+            compute: false,
+        }
+    }
+}
+
 /// Structe used to backup all data from directx context.
 /// Thanks ImGui.
-#[derive(Default)]
-pub struct BackupState(RefCell<InnerState>);
+pub struct BackupState {
+    inner: RefCell<InnerState>,
+    /// `None` until the first save, then `Some(None)` if the device doesn't support it.
+    swap: RefCell<Option<Option<ContextStateSwap>>>,
+    swap_enabled: bool,
+}
 
 impl BackupState {
+    pub fn new(
+        level: BackupLevel,
+        slots: BackupSlots,
+        clear: ClearStages,
+        swap_context_state: bool,
+    ) -> Self {
+        let slots = match level {
+            BackupLevel::None => BackupSlots {
+                shader_resources: 0,
+                samplers: 0,
+                constant_buffers: 0,
+                vertex_buffers: 0,
+                unordered_access_views: 0,
+            },
+            BackupLevel::Minimal => BackupSlots {
+                shader_resources: 1,
+                samplers: 1,
+                constant_buffers: 1,
+                vertex_buffers: 1,
+                // Compute stage isn't saved at this level.
+                unordered_access_views: 0,
+            },
+            BackupLevel::Full => slots.clamped(),
+        };
+
+        // Cleared tessellation and compute stages would be lost otherwise.
+        let save_unused = match level {
+            BackupLevel::None => false,
+            BackupLevel::Minimal => clear.hull || clear.domain || clear.compute,
+            BackupLevel::Full => true,
+        };
+
+        Self {
+            inner: RefCell::new(InnerState::new(level, slots, save_unused)),
+            swap: RefCell::new(None),
+            swap_enabled: swap_context_state && level != BackupLevel::None,
+        }
+    }
+
     #[inline]
     pub fn save(&self, context: &ID3D11DeviceContext) {
+        if self.swap_enabled {
+            let swap = &mut *self.swap.borrow_mut();
+            if let Some(swap) = swap.get_or_insert_with(|| ContextStateSwap::new(context)) {
+                swap.enter();
+                return;
+            }
+        }
+
         unsafe {
-            self.0.borrow_mut().save(context);
+            self.inner.borrow_mut().save(context);
         }
     }
 
+    /// Returns `true` if rendering happens in a separate device context state,
+    /// whose bindings persist between frames and aren't touched by the game.
+    #[inline]
+    pub fn is_isolated(&self) -> bool {
+        matches!(&*self.swap.borrow(), Some(Some(_)))
+    }
+
     #[inline]
     pub fn restore(&self, context: &ID3D11DeviceContext) {
+        if let Some(Some(swap)) = &mut *self.swap.borrow_mut() {
+            swap.leave();
+            return;
+        }
+
+        unsafe {
+            self.inner.borrow_mut().restore(context);
+        }
+    }
+
+    /// Unbinds render targets left bound in the separate context state,
+    /// they'd keep swapchain's buffers referenced and make `ResizeBuffers` fail.
+    pub fn release_targets(&self) {
+        if let Some(Some(swap)) = &mut *self.swap.borrow_mut() {
+            swap.enter();
+            unsafe {
+                swap.context.OMSetRenderTargets(0, null(), None);
+            }
+            swap.leave();
+        }
+    }
+}
+
+/// Switches D3D11.1 context to a separate, clean state object for the duration of rendering.
+/// Swapping back restores every binding of the game at once, which is both complete
+/// and cheaper than querying each stage.
+struct ContextStateSwap {
+    context: ID3D11DeviceContext1,
+    clean: ID3DDeviceContextState,
+    previous: Option<ID3DDeviceContextState>,
+}
+
+impl ContextStateSwap {
+    /// Returns `None` on devices without D3D11.1 support.
+    fn new(context: &ID3D11DeviceContext) -> Option<Self> {
+        unsafe {
+            let context = context.cast::<ID3D11DeviceContext1>().ok()?;
+
+            let mut device = None;
+            context.GetDevice(&mut device);
+            let device = device?.cast::<ID3D11Device1>().ok()?;
+
+            // State has to match threading model of the device it's used with.
+            let flags = if device.GetCreationFlags() & D3D11_CREATE_DEVICE_SINGLETHREADED.0 != 0 {
+                D3D11_1_CREATE_DEVICE_CONTEXT_STATE_SINGLETHREADED.0 as u32
+            } else {
+                0
+            };
+            let level = device.GetFeatureLevel();
+
+            let mut clean = None;
+            device
+                .CreateDeviceContextState(
+                    flags,
+                    &level,
+                    1,
+                    D3D11_SDK_VERSION,
+                    &ID3D11Device::IID,
+                    null_mut(),
+                    &mut clean,
+                )
+                .ok()?;
+
+            Some(Self {
+                context,
+                clean: clean?,
+                previous: None,
+            })
+        }
+    }
+
+    #[inline]
+    fn enter(&mut self) {
         unsafe {
-            self.0.borrow_mut().restore(context);
+            self.context
+                .SwapDeviceContextState(&self.clean, &mut self.previous);
+        }
+    }
+
+    #[inline]
+    fn leave(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            unsafe {
+                self.context.SwapDeviceContextState(previous, null_mut());
+            }
         }
     }
 }
 
-#[derive(Default)]
 struct InnerState {
+    level: BackupLevel,
+    /// Hull, domain and compute stages are saved too.
+    save_unused: bool,
+
     scissor_rects: [RECT; D3D11_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE as _],
     scissor_count: u32,
 
@@ -52,39 +291,149 @@ struct InnerState {
     depth_stencil_state: Option<ID3D11DepthStencilState>,
     stencil_ref: u32,
 
-    pixel_shader_resource: Option<ID3D11ShaderResourceView>,
-
-    sampler: Option<ID3D11SamplerState>,
+    render_targets: [Option<ID3D11RenderTargetView>; D3D11_SIMULTANEOUS_RENDER_TARGET_COUNT as _],
+    depth_stencil_view: Option<ID3D11DepthStencilView>,
 
     vertex_shader: Option<ID3D11VertexShader>,
     vertex_shader_instances: ClassInstances,
     vertex_shader_instances_count: u32,
+    vertex_resources: StageResources,
 
     geometry_shader: Option<ID3D11GeometryShader>,
     geometry_shader_instances: ClassInstances,
     geomentry_shader_instances_count: u32,
+    geometry_resources: StageResources,
 
     pixel_shader: Option<ID3D11PixelShader>,
     pixel_shader_instances: ClassInstances,
     pixel_shader_instances_count: u32,
+    pixel_resources: StageResources,
+
+    hull_shader: Option<ID3D11HullShader>,
+    hull_shader_instances: ClassInstances,
+    hull_shader_instances_count: u32,
+    hull_resources: StageResources,
+
+    domain_shader: Option<ID3D11DomainShader>,
+    domain_shader_instances: ClassInstances,
+    domain_shader_instances_count: u32,
+    domain_resources: StageResources,
+
+    compute_shader: Option<ID3D11ComputeShader>,
+    compute_shader_instances: ClassInstances,
+    compute_shader_instances_count: u32,
+    compute_resources: StageResources,
+    compute_uavs: Vec<Option<ID3D11UnorderedAccessView>>,
 
-    constant_buffer: Option<ID3D11Buffer>,
     primitive_topology: D3D_PRIMITIVE_TOPOLOGY,
 
     index_buffer: Option<ID3D11Buffer>,
     index_buffer_format: DXGI_FORMAT,
     index_buffer_offest: u32,
 
-    vertex_buffer: Option<ID3D11Buffer>,
-    vertex_buffer_strides: u32,
-    vertex_buffer_offsets: u32,
+    vertex_buffers: Vec<Option<ID3D11Buffer>>,
+    vertex_buffer_strides: Vec<u32>,
+    vertex_buffer_offsets: Vec<u32>,
 
     input_layout: Option<ID3D11InputLayout>,
 }
 
+/// Saves resources bound to the configured slots of a stage.
+macro_rules! save_stage {
+    ($ctx:expr, $res:expr, $get_srv:ident, $get_samplers:ident, $get_cbs:ident) => {{
+        let res = &mut $res;
+        $ctx.$get_srv(
+            0,
+            res.shader_resources.len() as _,
+            res.shader_resources.as_mut_ptr(),
+        );
+        $ctx.$get_samplers(0, res.samplers.len() as _, res.samplers.as_mut_ptr());
+        $ctx.$get_cbs(
+            0,
+            res.constant_buffers.len() as _,
+            res.constant_buffers.as_mut_ptr(),
+        );
+    }};
+}
+
+/// Restores resources saved with `save_stage!` and releases the saved references.
+macro_rules! restore_stage {
+    ($ctx:expr, $res:expr, $set_srv:ident, $set_samplers:ident, $set_cbs:ident) => {{
+        let res = &mut $res;
+        $ctx.$set_srv(
+            0,
+            res.shader_resources.len() as _,
+            res.shader_resources.as_ptr(),
+        );
+        $ctx.$set_samplers(0, res.samplers.len() as _, res.samplers.as_ptr());
+        $ctx.$set_cbs(
+            0,
+            res.constant_buffers.len() as _,
+            res.constant_buffers.as_ptr(),
+        );
+        res.release();
+    }};
+}
+
 impl InnerState {
+    fn new(level: BackupLevel, slots: BackupSlots, save_unused: bool) -> Self {
+        Self {
+            level,
+            save_unused,
+            scissor_rects: Default::default(),
+            scissor_count: 0,
+            viewports: Default::default(),
+            viewport_count: 0,
+            raster_state: None,
+            blend_state: None,
+            blend_factor: [0.; 4],
+            blend_mask: 0,
+            depth_stencil_state: None,
+            stencil_ref: 0,
+            render_targets: Default::default(),
+            depth_stencil_view: None,
+            vertex_shader: None,
+            vertex_shader_instances: ClassInstances::default(),
+            vertex_shader_instances_count: 0,
+            vertex_resources: StageResources::new(slots),
+            geometry_shader: None,
+            geometry_shader_instances: ClassInstances::default(),
+            geomentry_shader_instances_count: 0,
+            geometry_resources: StageResources::new(slots),
+            pixel_shader: None,
+            pixel_shader_instances: ClassInstances::default(),
+            pixel_shader_instances_count: 0,
+            pixel_resources: StageResources::new(slots),
+            hull_shader: None,
+            hull_shader_instances: ClassInstances::default(),
+            hull_shader_instances_count: 0,
+            hull_resources: StageResources::new(slots),
+            domain_shader: None,
+            domain_shader_instances: ClassInstances::default(),
+            domain_shader_instances_count: 0,
+            domain_resources: StageResources::new(slots),
+            compute_shader: None,
+            compute_shader_instances: ClassInstances::default(),
+            compute_shader_instances_count: 0,
+            compute_resources: StageResources::new(slots),
+            compute_uavs: vec![None; slots.unordered_access_views as _],
+            primitive_topology: D3D_PRIMITIVE_TOPOLOGY::default(),
+            index_buffer: None,
+            index_buffer_format: DXGI_FORMAT::default(),
+            index_buffer_offest: 0,
+            vertex_buffers: vec![None; slots.vertex_buffers as _],
+            vertex_buffer_strides: vec![0; slots.vertex_buffers as _],
+            vertex_buffer_offsets: vec![0; slots.vertex_buffers as _],
+            input_layout: None,
+        }
+    }
+
     #[inline]
     pub unsafe fn save(&mut self, ctx: &ID3D11DeviceContext) {
+        if self.level == BackupLevel::None {
+            return;
+        }
+
         ctx.RSGetScissorRects(&mut self.scissor_count, self.scissor_rects.as_mut_ptr());
         ctx.RSGetViewports(&mut self.viewport_count, self.viewports.as_mut_ptr());
         ctx.RSGetState(&mut self.raster_state);
@@ -94,8 +443,11 @@ impl InnerState {
             &mut self.blend_mask,
         );
         ctx.OMGetDepthStencilState(&mut self.depth_stencil_state, &mut self.stencil_ref);
-        ctx.PSGetShaderResources(0, 1, &mut self.pixel_shader_resource);
-        ctx.PSGetSamplers(0, 1, &mut self.sampler);
+        ctx.OMGetRenderTargets(
+            self.render_targets.len() as _,
+            self.render_targets.as_mut_ptr(),
+            &mut self.depth_stencil_view,
+        );
         self.pixel_shader_instances_count = 256;
         self.vertex_shader_instances_count = 256;
         self.geomentry_shader_instances_count = 256;
@@ -105,18 +457,44 @@ impl InnerState {
             self.pixel_shader_instances.as_mut_ptr(),
             &mut self.pixel_shader_instances_count,
         );
+        save_stage!(
+            ctx,
+            self.pixel_resources,
+            PSGetShaderResources,
+            PSGetSamplers,
+            PSGetConstantBuffers
+        );
+
         ctx.VSGetShader(
             &mut self.vertex_shader,
             self.vertex_shader_instances.as_mut_ptr(),
             &mut self.vertex_shader_instances_count,
         );
+        save_stage!(
+            ctx,
+            self.vertex_resources,
+            VSGetShaderResources,
+            VSGetSamplers,
+            VSGetConstantBuffers
+        );
+
         ctx.GSGetShader(
             &mut self.geometry_shader,
             self.geometry_shader_instances.as_mut_ptr(),
             &mut self.geomentry_shader_instances_count,
         );
+        save_stage!(
+            ctx,
+            self.geometry_resources,
+            GSGetShaderResources,
+            GSGetSamplers,
+            GSGetConstantBuffers
+        );
+
+        if self.save_unused {
+            self.save_unused_stages(ctx);
+        }
 
-        ctx.VSGetConstantBuffers(0, 1, &mut self.constant_buffer);
         ctx.IAGetPrimitiveTopology(&mut self.primitive_topology);
         ctx.IAGetIndexBuffer(
             &mut self.index_buffer,
@@ -125,16 +503,20 @@ impl InnerState {
         );
         ctx.IAGetVertexBuffers(
             0,
-            1,
-            &mut self.vertex_buffer,
-            &mut self.vertex_buffer_strides,
-            &mut self.vertex_buffer_offsets,
+            self.vertex_buffers.len() as _,
+            self.vertex_buffers.as_mut_ptr(),
+            self.vertex_buffer_strides.as_mut_ptr(),
+            self.vertex_buffer_offsets.as_mut_ptr(),
         );
         ctx.IAGetInputLayout(&mut self.input_layout);
     }
 
     #[inline]
     pub unsafe fn restore(&mut self, ctx: &ID3D11DeviceContext) {
+        if self.level == BackupLevel::None {
+            return;
+        }
+
         ctx.RSSetScissorRects(self.scissor_count, self.scissor_rects.as_ptr());
         ctx.RSSetViewports(self.viewport_count, self.viewports.as_ptr());
         ctx.RSSetState(self.raster_state.take());
@@ -144,14 +526,26 @@ impl InnerState {
             self.blend_mask,
         );
         ctx.OMSetDepthStencilState(self.depth_stencil_state.take(), self.stencil_ref);
-        ctx.PSSetShaderResources(0, 1, &self.pixel_shader_resource.take());
-        ctx.PSSetSamplers(0, 1, &self.sampler.take());
+        ctx.OMSetRenderTargets(
+            self.render_targets.len() as _,
+            self.render_targets.as_ptr(),
+            self.depth_stencil_view.take(),
+        );
+        self.render_targets.iter_mut().for_each(|rt| *rt = None);
+
         ctx.PSSetShader(
             self.pixel_shader.take(),
             self.pixel_shader_instances.as_ptr(),
             self.pixel_shader_instances_count,
         );
         self.pixel_shader_instances.release();
+        restore_stage!(
+            ctx,
+            self.pixel_resources,
+            PSSetShaderResources,
+            PSSetSamplers,
+            PSSetConstantBuffers
+        );
 
         ctx.VSSetShader(
             self.vertex_shader.take(),
@@ -159,6 +553,13 @@ impl InnerState {
             self.vertex_shader_instances_count,
         );
         self.vertex_shader_instances.release();
+        restore_stage!(
+            ctx,
+            self.vertex_resources,
+            VSSetShaderResources,
+            VSSetSamplers,
+            VSSetConstantBuffers
+        );
 
         ctx.GSSetShader(
             self.geometry_shader.take(),
@@ -166,8 +567,18 @@ impl InnerState {
             self.geomentry_shader_instances_count,
         );
         self.geometry_shader_instances.release();
+        restore_stage!(
+            ctx,
+            self.geometry_resources,
+            GSSetShaderResources,
+            GSSetSamplers,
+            GSSetConstantBuffers
+        );
+
+        if self.save_unused {
+            self.restore_unused_stages(ctx);
+        }
 
-        ctx.VSSetConstantBuffers(0, 1, &self.constant_buffer);
         ctx.IASetPrimitiveTopology(self.primitive_topology);
         ctx.IASetIndexBuffer(
             self.index_buffer.take(),
@@ -176,36 +587,314 @@ impl InnerState {
         );
         ctx.IASetVertexBuffers(
             0,
-            1,
-            &self.vertex_buffer.take(),
-            &self.vertex_buffer_strides,
-            &self.vertex_buffer_offsets,
+            self.vertex_buffers.len() as _,
+            self.vertex_buffers.as_ptr(),
+            self.vertex_buffer_strides.as_ptr(),
+            self.vertex_buffer_offsets.as_ptr(),
         );
+        self.vertex_buffers.iter_mut().for_each(|vb| *vb = None);
         ctx.IASetInputLayout(self.input_layout.take());
     }
+
+    /// Saves stages the renderer doesn't use, they're only restored in case
+    /// the game expects them to survive the present.
+    unsafe fn save_unused_stages(&mut self, ctx: &ID3D11DeviceContext) {
+        self.hull_shader_instances_count = 256;
+        self.domain_shader_instances_count = 256;
+        self.compute_shader_instances_count = 256;
+
+        ctx.HSGetShader(
+            &mut self.hull_shader,
+            self.hull_shader_instances.as_mut_ptr(),
+            &mut self.hull_shader_instances_count,
+        );
+        save_stage!(
+            ctx,
+            self.hull_resources,
+            HSGetShaderResources,
+            HSGetSamplers,
+            HSGetConstantBuffers
+        );
+
+        ctx.DSGetShader(
+            &mut self.domain_shader,
+            self.domain_shader_instances.as_mut_ptr(),
+            &mut self.domain_shader_instances_count,
+        );
+        save_stage!(
+            ctx,
+            self.domain_resources,
+            DSGetShaderResources,
+            DSGetSamplers,
+            DSGetConstantBuffers
+        );
+
+        ctx.CSGetShader(
+            &mut self.compute_shader,
+            self.compute_shader_instances.as_mut_ptr(),
+            &mut self.compute_shader_instances_count,
+        );
+        save_stage!(
+            ctx,
+            self.compute_resources,
+            CSGetShaderResources,
+            CSGetSamplers,
+            CSGetConstantBuffers
+        );
+        ctx.CSGetUnorderedAccessViews(
+            0,
+            self.compute_uavs.len() as _,
+            self.compute_uavs.as_mut_ptr(),
+        );
+    }
+
+    unsafe fn restore_unused_stages(&mut self, ctx: &ID3D11DeviceContext) {
+        ctx.HSSetShader(
+            self.hull_shader.take(),
+            self.hull_shader_instances.as_ptr(),
+            self.hull_shader_instances_count,
+        );
+        self.hull_shader_instances.release();
+        restore_stage!(
+            ctx,
+            self.hull_resources,
+            HSSetShaderResources,
+            HSSetSamplers,
+            HSSetConstantBuffers
+        );
+
+        ctx.DSSetShader(
+            self.domain_shader.take(),
+            self.domain_shader_instances.as_ptr(),
+            self.domain_shader_instances_count,
+        );
+        self.domain_shader_instances.release();
+        restore_stage!(
+            ctx,
+            self.domain_resources,
+            DSSetShaderResources,
+            DSSetSamplers,
+            DSSetConstantBuffers
+        );
+
+        ctx.CSSetShader(
+            self.compute_shader.take(),
+            self.compute_shader_instances.as_ptr(),
+            self.compute_shader_instances_count,
+        );
+        self.compute_shader_instances.release();
+        restore_stage!(
+            ctx,
+            self.compute_resources,
+            CSSetShaderResources,
+            CSSetSamplers,
+            CSSetConstantBuffers
+        );
+        // -1 keeps current counters of append/consume buffers.
+        let counters = [u32::MAX; D3D11_PS_CS_UAV_REGISTER_COUNT as usize];
+        ctx.CSSetUnorderedAccessViews(
+            0,
+            self.compute_uavs.len() as _,
+            self.compute_uavs.as_ptr(),
+            counters.as_ptr(),
+        );
+        self.compute_uavs.iter_mut().for_each(|uav| *uav = None);
+    }
+}
+
+/// Resources bound to slots of a single shader stage.
+struct StageResources {
+    shader_resources: Vec<Option<ID3D11ShaderResourceView>>,
+    samplers: Vec<Option<ID3D11SamplerState>>,
+    constant_buffers: Vec<Option<ID3D11Buffer>>,
 }
 
-struct ClassInstances([Option<ID3D11ClassInstance>; 256]);
+impl StageResources {
+    fn new(slots: BackupSlots) -> Self {
+        Self {
+            shader_resources: vec![None; slots.shader_resources as _],
+            samplers: vec![None; slots.samplers as _],
+            constant_buffers: vec![None; slots.constant_buffers as _],
+        }
+    }
+
+    /// Drops saved references so resources can be freed by the game.
+    #[inline]
+    fn release(&mut self) {
+        self.shader_resources.iter_mut().for_each(|r| *r = None);
+        self.samplers.iter_mut().for_each(|s| *s = None);
+        self.constant_buffers.iter_mut().for_each(|b| *b = None);
+    }
+}
+
+/// Class instances returned by `*GetShader`, each one holds a reference released on [`Self::release`].
+struct ClassInstances(Vec<Option<ID3D11ClassInstance>>);
 
 impl ClassInstances {
     #[inline]
     pub fn as_ptr(&self) -> *const Option<ID3D11ClassInstance> {
-        &self.0[0]
+        self.0.as_ptr()
     }
 
     #[inline]
     pub fn as_mut_ptr(&mut self) -> *mut Option<ID3D11ClassInstance> {
-        &mut self.0[0]
+        self.0.as_mut_ptr()
     }
 
+    /// Drops the saved references, the game keeps its own.
     #[inline]
     pub fn release(&mut self) {
-        self.0.iter().for_each(drop);
+        self.0.iter_mut().for_each(|instance| *instance = None);
     }
 }
 
 impl Default for ClassInstances {
     fn default() -> Self {
-        unsafe { zeroed() }
+        Self(vec![None; 256])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        shader::compile_source,
+        testing::{ref_count, warp_device},
+    };
+    use windows::Win32::Graphics::Direct3D11::{
+        ID3D11ClassLinkage, D3D11_BIND_UNORDERED_ACCESS, D3D11_BUFFER_DESC,
+        D3D11_RESOURCE_MISC_BUFFER_STRUCTURED, D3D11_USAGE_DEFAULT,
+    };
+
+    const LINKED_SHADER: &str = r#"
+interface ITint {
+  float4 tint();
+};
+
+class Red : ITint {
+  float4 tint() { return float4(1.0, 0.0, 0.0, 1.0); }
+};
+
+ITint tint;
+
+float4 ps_main() : SV_TARGET {
+  return tint.tint();
+}
+"#;
+
+    /// Saving and restoring a pixel shader with a bound class instance must leave
+    /// the instance's reference count where the game had it.
+    fn check_class_instance_refcount(level: BackupLevel) {
+        let (device, ctx) = warp_device();
+
+        unsafe {
+            let linkage: ID3D11ClassLinkage = device.CreateClassLinkage().unwrap();
+            let blob = compile_source(LINKED_SHADER, c_str!("ps_main"), c_str!("ps_5_0"));
+            let shader = device
+                .CreatePixelShader(blob.GetBufferPointer(), blob.GetBufferSize(), &linkage)
+                .unwrap();
+            let instance = linkage
+                .CreateClassInstance(c_str!("Red"), 0, 0, 0, 0)
+                .unwrap();
+
+            ctx.PSSetShader(&shader, &Some(instance.clone()), 1);
+            let before = ref_count(&instance);
+
+            let backup =
+                BackupState::new(level, BackupSlots::default(), ClearStages::default(), false);
+            for _ in 0..64 {
+                backup.save(&ctx);
+                backup.restore(&ctx);
+            }
+
+            assert_eq!(ref_count(&instance), before);
+
+            // The game's binding is restored, not dropped.
+            let mut bound = None;
+            let mut instances = [None, None];
+            let mut count = instances.len() as u32;
+            ctx.PSGetShader(&mut bound, instances.as_mut_ptr(), &mut count);
+            assert_eq!(count, 1);
+            assert_eq!(instances[0].as_ref(), Some(&instance));
+        }
+    }
+
+    #[test]
+    fn full_backup_keeps_class_instance_refcount() {
+        check_class_instance_refcount(BackupLevel::Full);
+    }
+
+    #[test]
+    fn minimal_backup_keeps_class_instance_refcount() {
+        check_class_instance_refcount(BackupLevel::Minimal);
+    }
+
+    #[test]
+    fn full_backup_restores_all_compute_uavs() {
+        let (device, ctx) = warp_device();
+
+        unsafe {
+            let desc = D3D11_BUFFER_DESC {
+                ByteWidth: 64,
+                Usage: D3D11_USAGE_DEFAULT,
+                BindFlags: D3D11_BIND_UNORDERED_ACCESS.0,
+                CPUAccessFlags: 0,
+                MiscFlags: D3D11_RESOURCE_MISC_BUFFER_STRUCTURED.0,
+                StructureByteStride: 4,
+            };
+            let buffer = device.CreateBuffer(&desc, std::ptr::null()).unwrap();
+            let uav = device
+                .CreateUnorderedAccessView(&buffer, std::ptr::null())
+                .unwrap();
+
+            let last = D3D11_PS_CS_UAV_REGISTER_COUNT - 1;
+            ctx.CSSetUnorderedAccessViews(last, 1, &Some(uav.clone()), &u32::MAX);
+
+            let backup = BackupState::new(
+                BackupLevel::Full,
+                BackupSlots::default(),
+                ClearStages::default(),
+                false,
+            );
+            backup.save(&ctx);
+            ctx.CSSetUnorderedAccessViews(last, 1, &None, &u32::MAX);
+            backup.restore(&ctx);
+
+            let mut bound = None;
+            ctx.CSGetUnorderedAccessViews(last, 1, &mut bound);
+            assert_eq!(bound.as_ref(), Some(&uav));
+        }
+    }
+
+    #[test]
+    fn minimal_backup_restores_cleared_compute_shader() {
+        let (device, ctx) = warp_device();
+
+        unsafe {
+            let blob = compile_source(
+                "[numthreads(1, 1, 1)] void cs_main() {}",
+                c_str!("cs_main"),
+                c_str!("cs_5_0"),
+            );
+            let shader = device
+                .CreateComputeShader(blob.GetBufferPointer(), blob.GetBufferSize(), None)
+                .unwrap();
+            ctx.CSSetShader(&shader, null(), 0);
+
+            let clear = ClearStages {
+                compute: true,
+                ..Default::default()
+            };
+            let backup =
+                BackupState::new(BackupLevel::Minimal, BackupSlots::default(), clear, false);
+            backup.save(&ctx);
+            ctx.CSSetShader(None, null(), 0);
+            backup.restore(&ctx);
+
+            let mut bound = None;
+            let mut count = 0;
+            ctx.CSGetShader(&mut bound, null_mut(), &mut count);
+            assert_eq!(bound.as_ref(), Some(&shader));
+        }
     }
 }
@@ -0,0 +1,311 @@
+use egui::{Context, FontData, FontDefinitions, FontFamily, Style};
+use windows::Win32::{Foundation::HWND, Graphics::Dxgi::IDXGISwapChain};
+
+use crate::{
+    fonts::{load_system_font, FontOptions},
+    input::InputOptions,
+    BackupLevel, BackupSlots, ClearStages, ClipboardProvider, DirectX11App, InputPolicy,
+    PlatformOutputHandler, PresentOrder, Theme, TimeSource,
+};
+
+/// Allows to configure [`DirectX11App`] before creating it.
+pub struct DirectX11AppBuilder<T = ()> {
+    pub(crate) ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
+    pub(crate) state: T,
+    pub(crate) clipboard: Option<Box<dyn ClipboardProvider>>,
+    pub(crate) time_source: Option<Box<dyn TimeSource>>,
+    pub(crate) software_cursor: bool,
+    pub(crate) manage_cursor: bool,
+    pub(crate) release_cursor_clip: bool,
+    pub(crate) input_policy: InputPolicy,
+    pub(crate) input_options: InputOptions,
+    pub(crate) backup_level: BackupLevel,
+    pub(crate) backup_slots: BackupSlots,
+    pub(crate) swap_context_state: bool,
+    pub(crate) clear_stages: ClearStages,
+    pub(crate) window: Option<HWND>,
+    pub(crate) present_order: PresentOrder,
+    pub(crate) fonts: FontOptions,
+    pub(crate) style: Option<Style>,
+    pub(crate) platform_handler: Option<Box<dyn PlatformOutputHandler>>,
+    pub(crate) ui_rate_limit: Option<f32>,
+}
+
+impl<T> DirectX11AppBuilder<T>
+where
+    T: Default,
+{
+    /// Creates new builder with state set to default value.
+    #[inline]
+    pub fn with_default(ui: impl FnMut(&Context, &mut T) + 'static) -> Self {
+        Self::new(ui, T::default())
+    }
+}
+
+impl<T> DirectX11AppBuilder<T> {
+    /// Creates new builder with explicit state value.
+    pub fn new(ui: impl FnMut(&Context, &mut T) + 'static, state: T) -> Self {
+        Self {
+            ui: Box::new(ui),
+            clipboard: None,
+            time_source: None,
+            software_cursor: false,
+            manage_cursor: false,
+            release_cursor_clip: true,
+            input_policy: InputPolicy::default(),
+            input_options: InputOptions::default(),
+            backup_level: BackupLevel::default(),
+            backup_slots: BackupSlots::default(),
+            swap_context_state: true,
+            clear_stages: ClearStages::default(),
+            window: None,
+            present_order: PresentOrder::default(),
+            fonts: FontOptions::default(),
+            style: None,
+            platform_handler: None,
+            ui_rate_limit: None,
+            state,
+        }
+    }
+
+    /// Sets custom clipboard backend. Win32 clipboard is used by default.
+    #[inline]
+    pub fn clipboard(mut self, clipboard: impl ClipboardProvider + 'static) -> Self {
+        self.clipboard = Some(Box::new(clipboard));
+        self
+    }
+
+    /// Sets clock used for input timestamps. `QueryPerformanceCounter` is used by default.
+    #[inline]
+    pub fn time_source(mut self, source: impl TimeSource + 'static) -> Self {
+        self.time_source = Some(Box::new(source));
+        self
+    }
+
+    /// Draws cursor with egui when pointer is inside of the window.
+    /// Useful for games that hide the hardware cursor. Disabled by default.
+    #[inline]
+    pub fn software_cursor(mut self, enabled: bool) -> Self {
+        self.software_cursor = enabled;
+        self
+    }
+
+    /// Forces hardware cursor to be visible while the overlay is visible
+    /// and restores game's cursor state when it gets hidden. Disabled by default.
+    #[inline]
+    pub fn manage_cursor(mut self, enabled: bool) -> Self {
+        self.manage_cursor = enabled;
+        self
+    }
+
+    /// Releases cursor confined by the game with `ClipCursor` while egui wants pointer input
+    /// and restores the confinement afterwards. Enabled by default.
+    #[inline]
+    pub fn release_cursor_clip(mut self, enabled: bool) -> Self {
+        self.release_cursor_clip = enabled;
+        self
+    }
+
+    /// Sets which messages are forwarded to the game, see [`DirectX11App::wnd_proc`].
+    #[inline]
+    pub fn input_policy(mut self, policy: InputPolicy) -> Self {
+        self.input_policy = policy;
+        self
+    }
+
+    /// Multiplies scroll distance derived from system's wheel settings. `1.0` by default.
+    #[inline]
+    pub fn scroll_speed(mut self, speed: f32) -> Self {
+        self.input_options.scroll_speed = speed;
+        self
+    }
+
+    /// Moves egui pointer with relative deltas from `WM_INPUT` instead of `WM_MOUSEMOVE`.
+    /// Meant for games that lock the cursor and read raw mouse input, the game has to
+    /// register the mouse with `RegisterRawInputDevices` itself. `WM_INPUT` still reaches
+    /// the game while the pointer is over egui, as only `DefWindowProc` frees its buffer.
+    /// Disabled by default.
+    #[inline]
+    pub fn raw_input(mut self, enabled: bool) -> Self {
+        self.input_options.raw_input = enabled;
+        self
+    }
+
+    /// Accepts files dropped onto the game window with `DragAcceptFiles`,
+    /// they're reported in [`egui::RawInput::dropped_files`]. `WM_DROPFILES` is never
+    /// forwarded to the game then, regardless of [`InputPolicy`]. Disabled by default.
+    #[inline]
+    pub fn accept_files(mut self, enabled: bool) -> Self {
+        self.input_options.accept_files = enabled;
+        self
+    }
+
+    /// Registers OLE drop target on the game window which, unlike [`Self::accept_files`],
+    /// reports [`egui::RawInput::hovered_files`] during the drag and accepts dropped text and URLs.
+    /// App has to be created on the window's thread and the game can't have its own target.
+    /// Disabled by default.
+    #[inline]
+    pub fn drop_target(mut self, enabled: bool) -> Self {
+        self.input_options.drop_target = enabled;
+        self
+    }
+
+    /// Polls XInput controller at `index` every frame and translates its d-pad, `A` and `B`
+    /// into focus navigation. Use [`DirectX11App::set_gamepad`] for other input libraries.
+    /// Disabled by default.
+    #[inline]
+    pub fn xinput(mut self, index: Option<u32>) -> Self {
+        self.input_options.xinput = index;
+        self
+    }
+
+    /// Types characters of alt+key combinations (`WM_SYSCHAR`) into focused text fields.
+    /// When disabled they're only reported as key presses with alt modifier. Disabled by default.
+    #[inline]
+    pub fn syschar_as_text(mut self, enabled: bool) -> Self {
+        self.input_options.syschar_as_text = enabled;
+        self
+    }
+
+    /// Sets how much of the game's pipeline state is saved before rendering and restored
+    /// afterwards, lower levels are cheaper but rely on the game rebinding its state.
+    /// [`BackupLevel::Full`] by default.
+    #[inline]
+    pub fn backup_level(mut self, level: BackupLevel) -> Self {
+        self.backup_level = level;
+        self
+    }
+
+    /// Sets how many resource slots of each shader stage and vertex buffer slots
+    /// are saved with [`BackupLevel::Full`]. All slots by default.
+    #[inline]
+    pub fn backup_slots(mut self, slots: BackupSlots) -> Self {
+        self.backup_slots = slots;
+        self
+    }
+
+    /// On D3D11.1 devices, renders with a separate device context state swapped in with
+    /// `SwapDeviceContextState` instead of saving and restoring the game's state manually.
+    /// Falls back to [`Self::backup_level`] when unsupported. Enabled by default.
+    #[inline]
+    pub fn swap_context_state(mut self, enabled: bool) -> Self {
+        self.swap_context_state = enabled;
+        self
+    }
+
+    /// Sets which shader stages are unbound before rendering.
+    /// Only the geometry shader is cleared by default.
+    #[inline]
+    pub fn clear_stages(mut self, stages: ClearStages) -> Self {
+        self.clear_stages = stages;
+        self
+    }
+
+    /// Sets window the app reads input and screen size from.
+    /// Swapchain's output window is used by default, this is needed for swapchains without one,
+    /// e.g. created for composition.
+    #[inline]
+    pub fn window(mut self, hwnd: HWND) -> Self {
+        self.window = Some(hwnd);
+        self
+    }
+
+    /// Sets whether the UI is drawn before or after other `Present` hooks,
+    /// which decides if it shows up in game capture.
+    /// [`PresentOrder::Immediate`] by default.
+    #[inline]
+    pub fn present_order(mut self, order: PresentOrder) -> Self {
+        self.present_order = order;
+        self
+    }
+
+    /// Sets fonts installed before the first frame, replacing fonts added with
+    /// [`Self::font`] or [`Self::system_font`] so far. egui's default fonts by default.
+    #[inline]
+    pub fn fonts(mut self, fonts: FontDefinitions) -> Self {
+        self.fonts.definitions = Some(fonts);
+        self
+    }
+
+    /// Adds TTF/OTF font, e.g. loaded with `include_bytes!`, with the highest priority
+    /// for `family`. egui's default fonts stay as a fallback.
+    #[inline]
+    pub fn font(mut self, name: impl Into<String>, data: FontData, family: FontFamily) -> Self {
+        self.fonts.push(name.into(), data, family);
+        self
+    }
+
+    /// Adds installed font, see [`crate::load_system_font`], with the highest priority
+    /// for `family`. Fonts that aren't installed are skipped.
+    pub fn system_font(mut self, name: &str, family: FontFamily) -> Self {
+        if let Some(data) = load_system_font(name) {
+            self.fonts.push(name.to_owned(), data, family);
+        }
+        self
+    }
+
+    /// Appends installed Windows fonts for CJK, Indic scripts and emoji after all other fonts,
+    /// so such text doesn't render as boxes. They're only rasterized when used, but loaded
+    /// into memory, about 30 MB in total. Disabled by default.
+    #[inline]
+    pub fn fallback_fonts(mut self, enabled: bool) -> Self {
+        self.fonts.fallbacks = enabled;
+        self
+    }
+
+    /// Multiplies sizes of all text styles. `1.0` by default.
+    #[inline]
+    pub fn text_scale(mut self, scale: f32) -> Self {
+        self.fonts.text_scale = Some(scale);
+        self
+    }
+
+    /// Sets style applied before the first frame, see [`DirectX11App::set_style`]
+    /// for changing it later. egui's default style by default.
+    #[inline]
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Sets style to one of the presets, shorthand for [`Self::style`].
+    #[inline]
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.style = Some(theme.style());
+        self
+    }
+
+    /// Sets handler receiving platform output every frame, see [`PlatformOutputHandler`].
+    /// None by default.
+    #[inline]
+    pub fn platform_output_handler(
+        mut self,
+        handler: impl PlatformOutputHandler + 'static,
+    ) -> Self {
+        self.platform_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Limits how many times per second the ui closure runs, frames in between draw the last
+    /// output again. Input is still collected every frame, clicks and keys run the ui
+    /// immediately, only pointer movement waits for the next run. Meant for games running
+    /// at hundreds of frames per second. Unlimited by default.
+    #[inline]
+    pub fn ui_rate_limit(mut self, rate: Option<f32>) -> Self {
+        self.ui_rate_limit = rate;
+        self
+    }
+
+    /// Creates the app.
+    #[inline]
+    pub fn build(self, swap_chain: &IDXGISwapChain) -> DirectX11App<T> {
+        DirectX11App::from_builder(self, swap_chain)
+    }
+
+    /// Creates the app rendering into an [`crate::ExternalOverlay`] window over `target`.
+    #[cfg(feature = "external")]
+    #[inline]
+    pub fn build_external(self, target: HWND) -> crate::ExternalOverlay<T> {
+        crate::ExternalOverlay::new(target, self)
+    }
+}
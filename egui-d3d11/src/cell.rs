@@ -0,0 +1,186 @@
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ops::Deref,
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+
+use crate::DirectX11App;
+
+const EMPTY: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+const TEARING_DOWN: u8 = 3;
+
+/// Holder of the app meant to be a `static`, replacing `static mut APP: Option<_>`.
+/// The app is created once by whichever hook runs first and shared by the present
+/// and wndproc hooks, which the app already synchronizes internally.
+/// ```ignore
+/// static APP: AppCell = AppCell::new();
+///
+/// unsafe extern "stdcall" fn hk_present(swap_chain: IDXGISwapChain, sync: u32, flags: u32) -> HRESULT {
+///     APP.init(|| DirectX11App::new_with_default(ui, &swap_chain));
+///     APP.get().present(&swap_chain, sync, flags);
+///     ...
+/// }
+/// ```
+pub struct AppCell<T = ()> {
+    state: AtomicU8,
+    /// Number of live [`AppGuard`]s, teardown waits for it to reach zero.
+    readers: AtomicUsize,
+    app: UnsafeCell<MaybeUninit<DirectX11App<T>>>,
+}
+
+// `DirectX11App` is used from the present and wndproc hooks at the same time by design,
+// the cell only has to make sure it's initialized and dropped exactly once. Closures owned
+// by the app aren't `Send`, which is why `init` puts the responsibility on the caller.
+unsafe impl<T: Send> Sync for AppCell<T> {}
+unsafe impl<T: Send> Send for AppCell<T> {}
+
+impl<T> AppCell<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            readers: AtomicUsize::new(0),
+            app: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Creates the app with `f` unless it's already there or being created by another thread.
+    /// Returns `true` if this call created it. If `f` panics, the cell stays empty.
+    ///
+    /// # Safety
+    /// The ui closure and other callbacks of the app are called from whichever thread
+    /// uses the cell, usually the render and window threads of the game, without being
+    /// `Send`. Caller must make sure they don't touch thread-bound state, such as `Rc`s
+    /// or thread locals, shared with the thread that created them.
+    pub unsafe fn init(&self, f: impl FnOnce() -> DirectX11App<T>) -> bool {
+        if self
+            .state
+            .compare_exchange(EMPTY, INITIALIZING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return false;
+        }
+
+        let reset = ResetOnUnwind(&self.state);
+        (*self.app.get()).write(f());
+        std::mem::forget(reset);
+
+        self.state.store(READY, Ordering::SeqCst);
+        true
+    }
+
+    /// Returns the app, `None` before [`Self::init`] finished or during [`Self::teardown`].
+    pub fn try_get(&self) -> Option<AppGuard<'_, T>> {
+        // Counted before checking the state, so teardown can't drop the app under the guard.
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        if self.state.load(Ordering::SeqCst) != READY {
+            self.readers.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        Some(AppGuard { cell: self })
+    }
+
+    /// Returns the app, panics if it's not initialized.
+    #[inline]
+    pub fn get(&self) -> AppGuard<'_, T> {
+        match self.try_get() {
+            Some(app) => app,
+            None => panic!("AppCell accessed before initialization."),
+        }
+    }
+
+    #[inline]
+    pub fn is_initialized(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == READY
+    }
+
+    /// Drops the app once all guards are gone, e.g. before unhooking and unloading the module.
+    /// New guards can't be created meanwhile, the cell can be initialized again afterwards.
+    /// Returns `false` if there was no app. Never call it while holding a guard,
+    /// such as from the ui closure, as it would wait forever.
+    pub fn teardown(&self) -> bool {
+        if self
+            .state
+            .compare_exchange(READY, TEARING_DOWN, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return false;
+        }
+
+        while self.readers.load(Ordering::SeqCst) != 0 {
+            std::thread::yield_now();
+        }
+
+        unsafe { (*self.app.get()).assume_init_drop() };
+        self.state.store(EMPTY, Ordering::SeqCst);
+        true
+    }
+}
+
+impl<T> Default for AppCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for AppCell<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            unsafe { self.app.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+/// Puts the cell back to empty if the app constructor panics.
+struct ResetOnUnwind<'a>(&'a AtomicU8);
+
+impl Drop for ResetOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.store(EMPTY, Ordering::SeqCst);
+    }
+}
+
+/// Access to the app in an [`AppCell`], keeps it from being torn down while alive.
+pub struct AppGuard<'a, T> {
+    cell: &'a AppCell<T>,
+}
+
+impl<T> Deref for AppGuard<'_, T> {
+    type Target = DirectX11App<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { (*self.cell.app.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Drop for AppGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.cell.readers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn panicking_init_leaves_cell_empty() {
+        let cell = AppCell::<()>::new();
+
+        for _ in 0..2 {
+            // Second attempt panics as well only if the state was reset.
+            let result = catch_unwind(AssertUnwindSafe(|| unsafe {
+                cell.init(|| -> DirectX11App { panic!("constructor failed") })
+            }));
+            assert!(result.is_err());
+            assert!(!cell.is_initialized());
+            assert!(cell.try_get().is_none());
+        }
+    }
+}
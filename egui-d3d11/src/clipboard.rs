@@ -0,0 +1,104 @@
+use egui::ColorImage;
+use windows::Win32::{
+    Foundation::{HANDLE, HWND},
+    System::{
+        DataExchange::{
+            CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+        },
+        Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+        SystemServices::CF_UNICODETEXT,
+    },
+};
+
+/// Backend used for copying and pasting.
+/// Useful when game sandboxes or hooks the clipboard functions.
+pub trait ClipboardProvider {
+    fn get_text(&self) -> Option<String>;
+
+    /// Returns `true` if text was successfully placed into clipboard.
+    fn set_text(&self, text: &str) -> bool;
+
+    fn get_image(&self) -> Option<ColorImage> {
+        None
+    }
+
+    fn set_image(&self, _image: &ColorImage) -> bool {
+        false
+    }
+}
+
+/// Default clipboard backed by Win32 clipboard functions.
+pub struct Win32Clipboard {
+    hwnd: HWND,
+}
+
+impl Win32Clipboard {
+    /// Window is required to become clipboard's owner when setting data.
+    #[inline]
+    pub fn new(hwnd: HWND) -> Self {
+        Self { hwnd }
+    }
+}
+
+impl ClipboardProvider for Win32Clipboard {
+    fn get_text(&self) -> Option<String> {
+        unsafe {
+            if !OpenClipboard(self.hwnd).as_bool() {
+                return None;
+            }
+
+            let handle = GetClipboardData(CF_UNICODETEXT.0);
+            let data = if handle.is_invalid() {
+                None
+            } else {
+                let ptr = GlobalLock(handle.0) as *const u16;
+                if ptr.is_null() {
+                    None
+                } else {
+                    let mut len = 0;
+                    while *ptr.add(len) != 0 {
+                        len += 1;
+                    }
+
+                    let text = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
+                    GlobalUnlock(handle.0);
+                    Some(text)
+                }
+            };
+
+            CloseClipboard();
+            data
+        }
+    }
+
+    fn set_text(&self, text: &str) -> bool {
+        let wide = text.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+
+        unsafe {
+            if !OpenClipboard(self.hwnd).as_bool() {
+                return false;
+            }
+
+            let mut result = false;
+            if EmptyClipboard().as_bool() {
+                let mem = GlobalAlloc(GMEM_MOVEABLE, wide.len() * 2);
+                let ptr = GlobalLock(mem) as *mut u16;
+
+                if !ptr.is_null() {
+                    ptr.copy_from_nonoverlapping(wide.as_ptr(), wide.len());
+                    GlobalUnlock(mem);
+
+                    // On success clipboard takes ownership of the memory.
+                    result = !SetClipboardData(CF_UNICODETEXT.0, HANDLE(mem)).is_invalid();
+                }
+
+                if !result && mem != 0 {
+                    GlobalFree(mem);
+                }
+            }
+
+            CloseClipboard();
+            result
+        }
+    }
+}
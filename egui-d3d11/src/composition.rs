@@ -0,0 +1,178 @@
+use windows::{
+    core::Interface,
+    Win32::{
+        Foundation::{HWND, RECT},
+        Graphics::{
+            DirectComposition::{
+                DCompositionCreateDevice, IDCompositionDevice, IDCompositionTarget,
+                IDCompositionVisual,
+            },
+            Dxgi::{
+                Common::{
+                    DXGI_ALPHA_MODE_PREMULTIPLIED, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_SAMPLE_DESC,
+                },
+                IDXGIAdapter, IDXGIDevice, IDXGIFactory2, IDXGISwapChain, DXGI_SCALING_STRETCH,
+                DXGI_SWAP_CHAIN_DESC1, DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+                DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            },
+        },
+        UI::WindowsAndMessaging::GetClientRect,
+    },
+};
+
+use crate::{
+    swapchain::{create_device, OverlaySwapChain},
+    DirectX11App, DirectX11AppBuilder,
+};
+
+/// Renders egui into a DirectComposition visual placed over the game's window,
+/// instead of drawing into the game's backbuffer.
+///
+/// The UI has its own device and swapchain with per-pixel alpha, so it is presented
+/// independently of the game's frames and never touches the game's pipeline state.
+/// Input still has to be forwarded from the game's `WndProc` with [`DirectX11App::wnd_proc`].
+pub struct CompositionOverlay<T = ()> {
+    app: DirectX11App<T>,
+    target: OverlaySwapChain,
+    // Visual is only shown while the device, target and visual are alive.
+    _composition: (
+        IDCompositionDevice,
+        IDCompositionTarget,
+        IDCompositionVisual,
+    ),
+    hwnd: HWND,
+    size: (u32, u32),
+}
+
+impl<T> CompositionOverlay<T> {
+    /// Creates composition target over `hwnd` and the app drawing into it.
+    pub fn new(hwnd: HWND, builder: DirectX11AppBuilder<T>) -> Self {
+        unsafe {
+            let (_, device, context) = create_device(None);
+            let size = client_size(hwnd);
+
+            let dxgi_device: IDXGIDevice = expect!(
+                device.cast(),
+                QueryDxgiDevice,
+                "Device isn't a DXGI device."
+            );
+            let adapter: IDXGIAdapter = expect!(
+                dxgi_device.GetAdapter(),
+                GetDxgiAdapter,
+                "Failed to get device's adapter."
+            );
+            let factory: IDXGIFactory2 = expect!(
+                adapter.GetParent(),
+                GetDxgiFactory,
+                "DXGI 1.2 factory is unavailable."
+            );
+
+            let desc = DXGI_SWAP_CHAIN_DESC1 {
+                Width: size.0,
+                Height: size.1,
+                Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+                BufferCount: 2,
+                Scaling: DXGI_SCALING_STRETCH,
+                SwapEffect: DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+                AlphaMode: DXGI_ALPHA_MODE_PREMULTIPLIED,
+                Flags: 0,
+                Stereo: false.into(),
+            };
+
+            let swap_chain = expect!(
+                factory.CreateSwapChainForComposition(&device, &desc, None),
+                CreateSwapChain,
+                "Failed to create composition swapchain."
+            );
+
+            let mut composition: Option<IDCompositionDevice> = None;
+            expect!(
+                DCompositionCreateDevice(
+                    &dxgi_device,
+                    &IDCompositionDevice::IID,
+                    &mut composition as *mut _ as _,
+                ),
+                CreateComposition,
+                "Failed to create composition device."
+            );
+            let composition = expect!(
+                composition,
+                CreateComposition,
+                "Composition device wasn't created."
+            );
+            let target = expect!(
+                composition.CreateTargetForHwnd(hwnd, true),
+                CreateCompositionTarget,
+                "Failed to create composition target."
+            );
+            let visual = expect!(
+                composition.CreateVisual(),
+                CreateCompositionVisual,
+                "Failed to create composition visual."
+            );
+            expect!(
+                visual.SetContent(&swap_chain),
+                SetCompositionContent,
+                "Failed to set visual's content."
+            );
+            expect!(
+                target.SetRoot(&visual),
+                SetCompositionRoot,
+                "Failed to set target's root."
+            );
+            expect!(
+                composition.Commit(),
+                CommitComposition,
+                "Failed to commit composition."
+            );
+
+            let swap_chain: IDXGISwapChain = expect!(
+                swap_chain.cast(),
+                QuerySwapChain,
+                "Swapchain isn't IDXGISwapChain."
+            );
+            let app = builder.window(hwnd).build(&swap_chain);
+
+            Self {
+                app,
+                target: OverlaySwapChain::new(swap_chain, device, context),
+                _composition: (composition, target, visual),
+                hwnd,
+                size,
+            }
+        }
+    }
+
+    #[inline]
+    pub fn app(&self) -> &DirectX11App<T> {
+        &self.app
+    }
+
+    /// Renders and presents a frame, resizing the swapchain to the window's client area.
+    /// Can be called at any rate, independently of the game's presents.
+    pub fn render(&mut self) {
+        unsafe {
+            let size = client_size(self.hwnd);
+            if size != self.size {
+                self.size = size;
+                self.target.resize(&self.app, size.0, size.1);
+            }
+
+            self.target.present(&self.app);
+        }
+    }
+}
+
+unsafe fn client_size(hwnd: HWND) -> (u32, u32) {
+    let mut rect = RECT::default();
+    GetClientRect(hwnd, &mut rect);
+    (
+        (rect.right - rect.left).max(1) as _,
+        (rect.bottom - rect.top).max(1) as _,
+    )
+}
@@ -0,0 +1,116 @@
+use std::{
+    mem::size_of,
+    ptr::null,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Instant,
+};
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11Buffer, ID3D11Device, ID3D11DeviceContext, D3D11_BIND_CONSTANT_BUFFER, D3D11_BUFFER_DESC,
+    D3D11_CPU_ACCESS_WRITE, D3D11_MAP_WRITE_DISCARD, D3D11_USAGE_DYNAMIC,
+};
+
+use crate::sync::Mutex;
+
+/// Register of the constant buffer holding [`FrameConstants`], bound to both vertex and
+/// pixel shader stages while the UI is drawn. Slot `0` is restored even with
+/// [`crate::BackupLevel::Minimal`].
+pub const FRAME_CONSTANTS_SLOT: u32 = 0;
+
+/// Common uniforms for effect shaders, updated before every draw of the UI.
+/// Declared in HLSL as:
+/// ```hlsl
+/// cbuffer FrameConstants : register(b0) {
+///   float time;
+///   float pixels_per_point;
+///   float2 resolution;
+///   uint frame;
+/// };
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameConstants {
+    /// Seconds since the app was created.
+    pub time: f32,
+    pub pixels_per_point: f32,
+    /// Size of the current render target in pixels, e.g. of a texture panel while it's drawn.
+    pub resolution: [f32; 2],
+    /// Number of frames presented so far, starting from zero.
+    pub frame: u32,
+    // Constant buffers are sized in multiples of 16 bytes.
+    _padding: [u32; 3],
+}
+
+pub(crate) struct FrameConstantBuffer {
+    buffer: ID3D11Buffer,
+    start: Instant,
+    frame: AtomicU32,
+    last: Mutex<FrameConstants>,
+}
+
+impl FrameConstantBuffer {
+    pub fn new(device: &ID3D11Device) -> Self {
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: size_of::<FrameConstants>() as _,
+            Usage: D3D11_USAGE_DYNAMIC,
+            BindFlags: D3D11_BIND_CONSTANT_BUFFER.0,
+            CPUAccessFlags: D3D11_CPU_ACCESS_WRITE.0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+
+        let buffer = unsafe {
+            expect!(
+                device.CreateBuffer(&desc, null()),
+                CreateBuffer,
+                "Failed to create frame constant buffer"
+            )
+        };
+
+        Self {
+            buffer,
+            start: Instant::now(),
+            frame: AtomicU32::new(0),
+            last: Mutex::new(FrameConstants::default()),
+        }
+    }
+
+    #[inline]
+    pub fn buffer(&self) -> &ID3D11Buffer {
+        &self.buffer
+    }
+
+    /// Returns values written by the last draw.
+    #[inline]
+    pub fn last(&self) -> FrameConstants {
+        *self.last.lock()
+    }
+
+    /// Advances frame index, called once per present.
+    #[inline]
+    pub fn next_frame(&self) {
+        self.frame.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn write(&self, ctx: &ID3D11DeviceContext, resolution: [f32; 2], pixels_per_point: f32) {
+        let constants = FrameConstants {
+            time: self.start.elapsed().as_secs_f32(),
+            pixels_per_point,
+            resolution,
+            // Starts from zero, as the index is advanced at the start of each present.
+            frame: self.frame.load(Ordering::Relaxed).wrapping_sub(1),
+            _padding: [0; 3],
+        };
+
+        unsafe {
+            let mapped = expect!(
+                ctx.Map(&self.buffer, 0, D3D11_MAP_WRITE_DISCARD, 0),
+                MapBuffer,
+                "Failed to map frame constant buffer"
+            );
+            *(mapped.pData as *mut FrameConstants) = constants;
+            ctx.Unmap(&self.buffer, 0);
+        }
+
+        *self.last.lock() = constants;
+    }
+}
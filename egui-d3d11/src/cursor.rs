@@ -0,0 +1,304 @@
+use egui::{Color32, Context, CursorIcon, Id, LayerId, Order, Painter, Pos2, Shape, Stroke, Vec2};
+use windows::Win32::{
+    Foundation::{HINSTANCE, PWSTR, RECT},
+    UI::WindowsAndMessaging::{
+        ClipCursor, GetClipCursor, GetCursor, GetCursorInfo, LoadCursorW, SetCursor, ShowCursor,
+        CURSORINFO, CURSOR_SHOWING, HCURSOR, IDC_APPSTARTING, IDC_ARROW, IDC_CROSS, IDC_HAND,
+        IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE,
+        IDC_SIZEWE, IDC_WAIT,
+    },
+};
+
+/// Cursor requested by egui during the last frame.
+pub struct CursorState {
+    icon: CursorIcon,
+    over_ui: bool,
+}
+
+impl Default for CursorState {
+    fn default() -> Self {
+        Self {
+            icon: CursorIcon::Default,
+            over_ui: false,
+        }
+    }
+}
+
+impl CursorState {
+    #[inline]
+    pub fn update(&mut self, icon: CursorIcon, over_ui: bool) {
+        self.icon = icon;
+        self.over_ui = over_ui;
+    }
+
+    #[inline]
+    pub fn icon(&self) -> CursorIcon {
+        self.icon
+    }
+
+    /// Sets the cursor if pointer is above egui or `force` is set.
+    /// Should be called only while handling `WM_SETCURSOR` with `HTCLIENT` hit test.
+    /// Returns `true` if cursor was set and message shouldn't be passed to the game.
+    pub fn apply(&self, force: bool) -> bool {
+        if !self.over_ui && !force {
+            return false;
+        }
+
+        unsafe {
+            SetCursor(load_cursor(self.icon));
+        }
+        true
+    }
+}
+
+/// Keeps hardware cursor visible while the overlay is shown.
+/// Display counter of the game is remembered so its cursor state can be restored afterwards.
+#[derive(Default)]
+pub struct CursorVisibility {
+    /// Display counter before the cursor was first forced visible.
+    original: Option<i32>,
+    previous: Option<HCURSOR>,
+}
+
+impl CursorVisibility {
+    /// Should be called from the window's thread as display counter is per thread.
+    /// Cheap when cursor is already visible, so games hiding it every frame are handled.
+    pub fn show(&mut self) {
+        unsafe {
+            let mut info = CURSORINFO {
+                cbSize: std::mem::size_of::<CURSORINFO>() as _,
+                ..Default::default()
+            };
+
+            if GetCursorInfo(&mut info).as_bool() && info.flags.0 & CURSOR_SHOWING.0 != 0 {
+                return;
+            }
+
+            if self.previous.is_none() {
+                self.previous = Some(GetCursor());
+            }
+
+            let mut count = ShowCursor(true);
+            self.original.get_or_insert(count - 1);
+            while count < 0 {
+                count = ShowCursor(true);
+            }
+        }
+    }
+
+    /// Steps display counter back to its value before [`Self::show`] first forced the cursor
+    /// visible, increments replayed every frame for games hiding the cursor aren't undone.
+    pub fn restore(&mut self) {
+        unsafe {
+            if let Some(original) = self.original.take() {
+                let mut count = ShowCursor(false);
+                if count < original {
+                    // Game has hidden the cursor further on its own, the decrement is undone.
+                    ShowCursor(true);
+                } else {
+                    while count > original {
+                        count = ShowCursor(false);
+                    }
+                }
+            }
+
+            if let Some(cursor) = self.previous.take() {
+                SetCursor(cursor);
+            }
+        }
+    }
+}
+
+/// Temporarily removes cursor confinement set by the game with `ClipCursor`.
+#[derive(Default)]
+pub struct CursorClip {
+    /// Clip rect of the game.
+    saved: Option<RECT>,
+    /// Clip rect reported by the system after the release.
+    released: RECT,
+}
+
+impl CursorClip {
+    /// Releases the cursor. Should be called every frame as games tend to reapply the clip.
+    pub fn release(&mut self) {
+        unsafe {
+            let mut current = RECT::default();
+            if !GetClipCursor(&mut current).as_bool() {
+                return;
+            }
+
+            // Either first release or game has clipped the cursor again.
+            if self.saved.is_none() || !rect_eq(&current, &self.released) {
+                self.saved = Some(current);
+                ClipCursor(std::ptr::null());
+                GetClipCursor(&mut self.released);
+            }
+        }
+    }
+
+    /// Restores game's clip rect if cursor was released.
+    pub fn restore(&mut self) {
+        if let Some(rect) = self.saved.take() {
+            unsafe {
+                ClipCursor(&rect);
+            }
+        }
+    }
+}
+
+#[inline]
+fn rect_eq(a: &RECT, b: &RECT) -> bool {
+    a.left == b.left && a.top == b.top && a.right == b.right && a.bottom == b.bottom
+}
+
+/// Returns system cursor matching egui's icon, `CursorIcon::None` hides the cursor.
+pub fn load_cursor(icon: CursorIcon) -> HCURSOR {
+    let id = match icon {
+        CursorIcon::None => return HCURSOR::default(),
+        CursorIcon::Default
+        | CursorIcon::ContextMenu
+        | CursorIcon::Alias
+        | CursorIcon::Copy
+        | CursorIcon::ZoomIn
+        | CursorIcon::ZoomOut => IDC_ARROW,
+        CursorIcon::Help => IDC_HELP,
+        CursorIcon::PointingHand | CursorIcon::Grab => IDC_HAND,
+        CursorIcon::Progress => IDC_APPSTARTING,
+        CursorIcon::Wait => IDC_WAIT,
+        CursorIcon::Cell | CursorIcon::Crosshair => IDC_CROSS,
+        CursorIcon::Text | CursorIcon::VerticalText => IDC_IBEAM,
+        CursorIcon::Move | CursorIcon::AllScroll | CursorIcon::Grabbing => IDC_SIZEALL,
+        CursorIcon::NoDrop | CursorIcon::NotAllowed => IDC_NO,
+        CursorIcon::ResizeHorizontal => IDC_SIZEWE,
+        CursorIcon::ResizeNeSw => IDC_SIZENESW,
+        CursorIcon::ResizeNwSe => IDC_SIZENWSE,
+        CursorIcon::ResizeVertical => IDC_SIZENS,
+    };
+
+    unsafe { LoadCursorW(HINSTANCE::default(), PWSTR(id.0)) }
+}
+
+/// Draws cursor with egui itself, used when the game hides the hardware one.
+pub fn paint_software_cursor(ctx: &Context, icon: CursorIcon) {
+    let pos = match ctx.input().pointer.hover_pos() {
+        Some(pos) => pos,
+        None => return,
+    };
+
+    let painter = ctx.layer_painter(LayerId::new(Order::Debug, Id::new("egui_d3d11_cursor")));
+    let fill = Color32::WHITE;
+    let stroke = Stroke::new(1., Color32::BLACK);
+
+    match icon {
+        CursorIcon::None => {}
+        CursorIcon::Text | CursorIcon::VerticalText => {
+            let outline = Stroke::new(3., Color32::BLACK);
+            let line = Stroke::new(1., fill);
+            for stroke in [outline, line] {
+                painter.line_segment([pos + Vec2::new(0., -8.), pos + Vec2::new(0., 8.)], stroke);
+                painter.line_segment(
+                    [pos + Vec2::new(-3., -8.), pos + Vec2::new(3., -8.)],
+                    stroke,
+                );
+                painter.line_segment([pos + Vec2::new(-3., 8.), pos + Vec2::new(3., 8.)], stroke);
+            }
+        }
+        CursorIcon::Cell | CursorIcon::Crosshair => {
+            cross(&painter, pos, 8., false);
+        }
+        CursorIcon::Move | CursorIcon::AllScroll | CursorIcon::Grabbing => {
+            cross(&painter, pos, 9., true);
+        }
+        CursorIcon::ResizeHorizontal => double_arrow(&painter, pos, Vec2::new(1., 0.)),
+        CursorIcon::ResizeVertical => double_arrow(&painter, pos, Vec2::new(0., 1.)),
+        CursorIcon::ResizeNwSe => double_arrow(&painter, pos, Vec2::new(1., 1.).normalized()),
+        CursorIcon::ResizeNeSw => double_arrow(&painter, pos, Vec2::new(1., -1.).normalized()),
+        CursorIcon::Wait | CursorIcon::Progress => {
+            painter.circle(
+                pos,
+                7.,
+                Color32::TRANSPARENT,
+                Stroke::new(3., Color32::BLACK),
+            );
+            painter.circle(pos, 7., Color32::TRANSPARENT, Stroke::new(1.5, fill));
+        }
+        CursorIcon::NoDrop | CursorIcon::NotAllowed => {
+            let red = Stroke::new(2., Color32::RED);
+            painter.circle(pos, 7., Color32::TRANSPARENT, red);
+            painter.line_segment([pos + Vec2::new(-5., -5.), pos + Vec2::new(5., 5.)], red);
+        }
+        CursorIcon::PointingHand | CursorIcon::Grab => {
+            painter.rect(
+                egui::Rect::from_min_size(pos + Vec2::new(-1.5, 0.), Vec2::new(4., 9.)),
+                2.,
+                fill,
+                stroke,
+            );
+            painter.rect(
+                egui::Rect::from_min_size(pos + Vec2::new(-3., 7.), Vec2::new(10., 9.)),
+                3.,
+                fill,
+                stroke,
+            );
+        }
+        _ => arrow(&painter, pos, fill, stroke),
+    }
+}
+
+fn arrow(painter: &Painter, pos: Pos2, fill: Color32, stroke: Stroke) {
+    let offset = |x: f32, y: f32| pos + Vec2::new(x, y);
+
+    // Tail goes first so the head's outline is drawn above it.
+    painter.add(Shape::convex_polygon(
+        vec![
+            offset(4., 11.),
+            offset(7., 10.),
+            offset(10., 17.),
+            offset(7., 18.),
+        ],
+        fill,
+        stroke,
+    ));
+    painter.add(Shape::convex_polygon(
+        vec![offset(0., 0.), offset(12., 12.), offset(0., 16.)],
+        fill,
+        stroke,
+    ));
+}
+
+fn cross(painter: &Painter, pos: Pos2, size: f32, arrows: bool) {
+    for dir in [Vec2::new(1., 0.), Vec2::new(0., 1.)] {
+        if arrows {
+            double_arrow(painter, pos, dir);
+        } else {
+            painter.line_segment(
+                [pos - dir * size, pos + dir * size],
+                Stroke::new(3., Color32::BLACK),
+            );
+            painter.line_segment(
+                [pos - dir * size, pos + dir * size],
+                Stroke::new(1., Color32::WHITE),
+            );
+        }
+    }
+}
+
+fn double_arrow(painter: &Painter, pos: Pos2, dir: Vec2) {
+    const LEN: f32 = 9.;
+    const HEAD: f32 = 4.;
+
+    let side = dir.rot90();
+    for stroke in [
+        Stroke::new(3., Color32::BLACK),
+        Stroke::new(1., Color32::WHITE),
+    ] {
+        painter.line_segment([pos - dir * LEN, pos + dir * LEN], stroke);
+
+        for sign in [-1., 1.] {
+            let tip = pos + dir * LEN * sign;
+            let back = tip - dir * HEAD * sign;
+            painter.line_segment([tip, back + side * HEAD], stroke);
+            painter.line_segment([tip, back - side * HEAD], stroke);
+        }
+    }
+}
@@ -0,0 +1,683 @@
+use egui::{
+    epaint::ImageDelta, Context, FullOutput, ImageData, Pos2, Rect, TextureId, TexturesDelta,
+};
+use std::{
+    collections::HashMap,
+    mem::{size_of, zeroed},
+    ptr::{null, null_mut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use windows::{
+    core::HRESULT,
+    Win32::{
+        Foundation::{HWND, LPARAM, RECT, WPARAM},
+        Graphics::{
+            Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST,
+            Direct3D10::{
+                D3D10CreateStateBlock, D3D10StateBlockMaskEnableAll, ID3D10BlendState,
+                ID3D10Buffer, ID3D10Device, ID3D10InputLayout, ID3D10PixelShader,
+                ID3D10RasterizerState, ID3D10RenderTargetView, ID3D10SamplerState,
+                ID3D10ShaderResourceView, ID3D10StateBlock, ID3D10Texture2D, ID3D10VertexShader,
+                D3D10_APPEND_ALIGNED_ELEMENT, D3D10_BIND_INDEX_BUFFER, D3D10_BIND_SHADER_RESOURCE,
+                D3D10_BIND_VERTEX_BUFFER, D3D10_BLEND_DESC, D3D10_BLEND_INV_SRC_ALPHA,
+                D3D10_BLEND_ONE, D3D10_BLEND_OP_ADD, D3D10_BLEND_SRC_ALPHA, D3D10_BOX,
+                D3D10_BUFFER_DESC, D3D10_COLOR_WRITE_ENABLE_ALL, D3D10_COMPARISON_ALWAYS,
+                D3D10_CPU_ACCESS_WRITE, D3D10_CULL_NONE, D3D10_FILL_SOLID,
+                D3D10_FILTER_MIN_MAG_MIP_LINEAR, D3D10_INPUT_ELEMENT_DESC,
+                D3D10_INPUT_PER_VERTEX_DATA, D3D10_MAP_WRITE_DISCARD, D3D10_RASTERIZER_DESC,
+                D3D10_SAMPLER_DESC, D3D10_SUBRESOURCE_DATA, D3D10_TEXTURE2D_DESC,
+                D3D10_TEXTURE_ADDRESS_BORDER, D3D10_USAGE_DEFAULT, D3D10_USAGE_DYNAMIC,
+                D3D10_VIEWPORT,
+            },
+            Dxgi::{
+                Common::{
+                    DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32G32_FLOAT, DXGI_FORMAT_R32_UINT,
+                    DXGI_FORMAT_R8_UINT, DXGI_SAMPLE_DESC,
+                },
+                IDXGISwapChain,
+            },
+        },
+        UI::WindowsAndMessaging::GetClientRect,
+    },
+};
+
+use crate::{
+    input::{InputCollector, InputOptions},
+    mesh::{convert_meshes, GpuMesh, GpuVertex},
+    shader::{compile_source, SHADER_SOURCE},
+    sync::Mutex,
+    texture::{get_image_data, get_image_format, has_tight_pixels},
+    InputPolicy, InputResult, PerformanceCounter, WantsInput, Win32Clipboard,
+};
+
+const LAYOUT_ELEMENTS: [D3D10_INPUT_ELEMENT_DESC; 4] = [
+    D3D10_INPUT_ELEMENT_DESC {
+        SemanticName: c_str!("POSITION"),
+        SemanticIndex: 0,
+        Format: DXGI_FORMAT_R32G32_FLOAT,
+        InputSlot: 0,
+        AlignedByteOffset: 0,
+        InputSlotClass: D3D10_INPUT_PER_VERTEX_DATA,
+        InstanceDataStepRate: 0,
+    },
+    D3D10_INPUT_ELEMENT_DESC {
+        SemanticName: c_str!("TEXCOORD"),
+        SemanticIndex: 0,
+        Format: DXGI_FORMAT_R32G32_FLOAT,
+        InputSlot: 0,
+        AlignedByteOffset: D3D10_APPEND_ALIGNED_ELEMENT,
+        InputSlotClass: D3D10_INPUT_PER_VERTEX_DATA,
+        InstanceDataStepRate: 0,
+    },
+    D3D10_INPUT_ELEMENT_DESC {
+        SemanticName: c_str!("COLOR"),
+        SemanticIndex: 0,
+        Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+        InputSlot: 0,
+        AlignedByteOffset: D3D10_APPEND_ALIGNED_ELEMENT,
+        InputSlotClass: D3D10_INPUT_PER_VERTEX_DATA,
+        InstanceDataStepRate: 0,
+    },
+    D3D10_INPUT_ELEMENT_DESC {
+        SemanticName: c_str!("MODE"),
+        SemanticIndex: 0,
+        Format: DXGI_FORMAT_R8_UINT,
+        InputSlot: 0,
+        AlignedByteOffset: D3D10_APPEND_ALIGNED_ELEMENT,
+        InputSlotClass: D3D10_INPUT_PER_VERTEX_DATA,
+        InstanceDataStepRate: 0,
+    },
+];
+
+/// Texture of the D3D10 backend, only kept alive while egui uses it.
+struct Texture10 {
+    texture: ID3D10Texture2D,
+    resource: ID3D10ShaderResourceView,
+}
+
+/// Dynamic buffer recreated only when a frame needs more space than it has.
+#[derive(Default)]
+struct GrowableBuffer {
+    buffer: Option<ID3D10Buffer>,
+    capacity: usize,
+}
+
+impl GrowableBuffer {
+    unsafe fn write<T>(
+        &mut self,
+        device: &ID3D10Device,
+        bind: u32,
+        data: impl ExactSizeIterator<Item = T>,
+    ) -> &ID3D10Buffer {
+        let len = data.len() * size_of::<T>();
+        if self.capacity < len {
+            self.buffer = None;
+        }
+
+        let capacity = &mut self.capacity;
+        let buffer = &*self.buffer.get_or_insert_with(|| {
+            *capacity = len.next_power_of_two().max(256);
+            let desc = D3D10_BUFFER_DESC {
+                ByteWidth: *capacity as _,
+                Usage: D3D10_USAGE_DYNAMIC,
+                BindFlags: bind,
+                CPUAccessFlags: D3D10_CPU_ACCESS_WRITE.0 as _,
+                MiscFlags: 0,
+            };
+            expect!(
+                device.CreateBuffer(&desc, null()),
+                CreateBuffer,
+                "Failed to create buffer."
+            )
+        });
+        let mut dst = null_mut();
+        expect!(
+            buffer.Map(D3D10_MAP_WRITE_DISCARD, 0, &mut dst),
+            MapBuffer,
+            "Failed to map buffer."
+        );
+        let dst = dst as *mut T;
+        let count = data.len();
+        for (i, item) in data.take(count).enumerate() {
+            dst.add(i).write_unaligned(item);
+        }
+        buffer.Unmap();
+
+        buffer
+    }
+}
+
+/// Renders egui into swapchains created with `ID3D10Device`, used by some older games.
+/// Shares input handling and mesh preparation with [`crate::DirectX11App`], but only provides
+/// the basics of it: presenting, resizing and input.
+pub struct DirectX10App<T = ()> {
+    ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
+    ctx: Mutex<Context>,
+    state: Mutex<T>,
+    input_collector: InputCollector,
+    /// Cloned out before use, so `InputPolicy::Custom` can replace the policy.
+    input_policy: Mutex<Arc<InputPolicy>>,
+    render_view: Mutex<Option<ID3D10RenderTargetView>>,
+    input_layout: ID3D10InputLayout,
+    vertex_shader: ID3D10VertexShader,
+    pixel_shader: ID3D10PixelShader,
+    blend_state: ID3D10BlendState,
+    raster_state: ID3D10RasterizerState,
+    sampler: ID3D10SamplerState,
+    state_block: ID3D10StateBlock,
+    textures: Mutex<HashMap<TextureId, Texture10>>,
+    vertex_buffer: Mutex<GrowableBuffer>,
+    index_buffer: Mutex<GrowableBuffer>,
+    visible: AtomicBool,
+    wants_pointer: AtomicBool,
+    wants_keyboard: AtomicBool,
+    hwnd: HWND,
+}
+
+impl<T> DirectX10App<T>
+where
+    T: Default,
+{
+    /// Creates new app with state set to default value.
+    #[inline]
+    pub fn new_with_default(
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        swap_chain: &IDXGISwapChain,
+    ) -> Self {
+        Self::new_with_state(ui, swap_chain, T::default())
+    }
+}
+
+impl<T> DirectX10App<T> {
+    /// Creates new app with explicit state value.
+    pub fn new_with_state(
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        swap_chain: &IDXGISwapChain,
+        state: T,
+    ) -> Self {
+        unsafe {
+            let hwnd = expect!(
+                swap_chain.GetDesc(),
+                GetSwapChainDesc,
+                "Failed to get swapchain's descriptor."
+            )
+            .OutputWindow;
+            let device: ID3D10Device = expect!(
+                swap_chain.GetDevice(),
+                GetDevice,
+                "Failed to get swapchain's device."
+            );
+
+            // Embedded bytecode targets shader model 5, D3D10 needs 4.
+            let vertex_blob = compile_source(SHADER_SOURCE, c_str!("vs_main"), c_str!("vs_4_0"));
+            let pixel_blob = compile_source(SHADER_SOURCE, c_str!("ps_main"), c_str!("ps_4_0"));
+
+            let vertex_shader = expect!(
+                device.CreateVertexShader(
+                    vertex_blob.GetBufferPointer(),
+                    vertex_blob.GetBufferSize()
+                ),
+                CreateVertexShader,
+                "Failed to create vertex shader."
+            );
+            let pixel_shader = expect!(
+                device.CreatePixelShader(pixel_blob.GetBufferPointer(), pixel_blob.GetBufferSize()),
+                CreatePixelShader,
+                "Failed to create pixel shader."
+            );
+            let input_layout = expect!(
+                device.CreateInputLayout(
+                    LAYOUT_ELEMENTS.as_ptr(),
+                    LAYOUT_ELEMENTS.len() as _,
+                    vertex_blob.GetBufferPointer(),
+                    vertex_blob.GetBufferSize(),
+                ),
+                CreateInputLayout,
+                "Failed to create input layout."
+            );
+
+            let mask = expect!(
+                D3D10StateBlockMaskEnableAll(),
+                CreateStateBlock,
+                "Failed to fill state block mask."
+            );
+            let state_block = expect!(
+                D3D10CreateStateBlock(&device, &mask),
+                CreateStateBlock,
+                "Failed to create state block."
+            );
+
+            let app = Self {
+                ui: Box::new(ui),
+                ctx: Mutex::new(Context::default()),
+                state: Mutex::new(state),
+                input_collector: InputCollector::new(
+                    hwnd,
+                    Box::new(Win32Clipboard::new(hwnd)),
+                    Box::new(PerformanceCounter::new()),
+                    InputOptions::default(),
+                ),
+                input_policy: Mutex::new(Arc::new(InputPolicy::default())),
+                render_view: Mutex::new(None),
+                input_layout,
+                vertex_shader,
+                pixel_shader,
+                blend_state: create_blend_state(&device),
+                raster_state: create_raster_state(&device),
+                sampler: create_sampler_state(&device),
+                state_block,
+                textures: Mutex::new(HashMap::new()),
+                vertex_buffer: Mutex::new(GrowableBuffer::default()),
+                index_buffer: Mutex::new(GrowableBuffer::default()),
+                visible: AtomicBool::new(true),
+                wants_pointer: AtomicBool::new(false),
+                wants_keyboard: AtomicBool::new(false),
+                hwnd,
+            };
+
+            *app.render_view.lock() = Some(create_render_view(swap_chain, &device));
+            app
+        }
+    }
+
+    /// Present call. Should be called once per original present call, before or inside of hook.
+    pub fn present(&self, swap_chain: &IDXGISwapChain) {
+        if !self.visible.load(Ordering::SeqCst) {
+            drop(self.input_collector.collect_input());
+            self.wants_pointer.store(false, Ordering::SeqCst);
+            self.wants_keyboard.store(false, Ordering::SeqCst);
+            return;
+        }
+
+        let device: ID3D10Device = unsafe {
+            expect!(
+                swap_chain.GetDevice(),
+                GetDevice,
+                "Failed to get swapchain's device."
+            )
+        };
+        let ctx_lock = &mut *self.ctx.lock();
+
+        // Same as with D3D11, present isn't called from multiple threads.
+        let ui = self.ui.as_ref() as *const _ as *mut dyn FnMut(&Context, &mut T);
+        let FullOutput {
+            shapes,
+            platform_output,
+            textures_delta,
+            ..
+        } = ctx_lock.run(self.input_collector.collect_input(), |u| unsafe {
+            (*ui)(u, &mut *self.state.lock());
+        });
+
+        if !platform_output.copied_text.is_empty() {
+            self.input_collector
+                .clipboard()
+                .set_text(&platform_output.copied_text);
+        }
+
+        self.wants_pointer.store(
+            ctx_lock.is_pointer_over_area() || ctx_lock.wants_pointer_input(),
+            Ordering::SeqCst,
+        );
+        self.wants_keyboard
+            .store(ctx_lock.wants_keyboard_input(), Ordering::SeqCst);
+
+        let screen = self.get_screen_size();
+        let (meshes, _) = convert_meshes(
+            ctx_lock.tessellate(shapes),
+            Rect::from_min_max(Pos2::ZERO, screen),
+        );
+
+        self.update_textures(textures_delta, &device);
+        if !meshes.is_empty() {
+            unsafe {
+                self.render_meshes(&meshes, screen, &device);
+            }
+        }
+    }
+
+    /// Should be called **INSTEAD** of swapchain's `ResizeBuffers`.
+    pub fn resize_buffers(
+        &self,
+        swap_chain: &IDXGISwapChain,
+        original: impl FnOnce() -> HRESULT,
+    ) -> HRESULT {
+        let view_lock = &mut *self.render_view.lock();
+        drop(view_lock.take());
+
+        let result = original();
+
+        unsafe {
+            let device: ID3D10Device = expect!(
+                swap_chain.GetDevice(),
+                GetDevice,
+                "Failed to get swapchain's device."
+            );
+            *view_lock = Some(create_render_view(swap_chain, &device));
+        }
+        result
+    }
+
+    /// Call on each `WndProc` occurence.
+    /// Reports how the message was handled, same as [`crate::DirectX11App::wnd_proc`].
+    pub fn wnd_proc(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> InputResult {
+        self.input_collector.process(
+            umsg,
+            wparam.0,
+            lparam.0,
+            &self.input_policy(),
+            self.is_visible(),
+            self.wants_input(),
+        )
+    }
+
+    #[inline]
+    fn input_policy(&self) -> Arc<InputPolicy> {
+        self.input_policy.lock().clone()
+    }
+
+    /// Changes which messages are forwarded to the game.
+    #[inline]
+    pub fn set_input_policy(&self, policy: InputPolicy) {
+        *self.input_policy.lock() = Arc::new(policy);
+    }
+
+    /// Returns what egui wanted during the last frame.
+    #[inline]
+    pub fn wants_input(&self) -> WantsInput {
+        WantsInput {
+            pointer: self.wants_pointer.load(Ordering::SeqCst),
+            keyboard: self.wants_keyboard.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Shows or hides the UI, input isn't collected while hidden.
+    #[inline]
+    pub fn set_visible(&self, visible: bool) {
+        self.visible.store(visible, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn get_screen_size(&self) -> Pos2 {
+        let mut rect = RECT::default();
+        unsafe {
+            GetClientRect(self.hwnd, &mut rect);
+        }
+        Pos2::new(
+            (rect.right - rect.left) as f32,
+            (rect.bottom - rect.top) as f32,
+        )
+    }
+
+    fn update_textures(&self, delta: TexturesDelta, device: &ID3D10Device) {
+        let textures = &mut *self.textures.lock();
+        for (id, ImageDelta { image, pos }) in delta.set {
+            if !has_tight_pixels(&image) {
+                continue;
+            }
+
+            unsafe {
+                match (pos, textures.get(&id)) {
+                    (Some([x, y]), Some(tex)) => {
+                        let region = D3D10_BOX {
+                            left: x as _,
+                            top: y as _,
+                            front: 0,
+                            right: (x + image.width()) as _,
+                            bottom: (y + image.height()) as _,
+                            back: 1,
+                        };
+                        device.UpdateSubresource(
+                            &tex.texture,
+                            0,
+                            &region,
+                            get_image_data(&image),
+                            (image.width() * image.bytes_per_pixel()) as _,
+                            0,
+                        );
+                    }
+                    (Some(_), None) => {}
+                    (None, _) => {
+                        textures.insert(id, create_texture(&image, device));
+                    }
+                }
+            }
+        }
+
+        for id in delta.free {
+            textures.remove(&id);
+        }
+    }
+
+    unsafe fn render_meshes(&self, meshes: &[GpuMesh], screen: Pos2, device: &ID3D10Device) {
+        expect!(
+            self.state_block.Capture(),
+            CaptureState,
+            "Failed to capture state."
+        );
+
+        let vertices = meshes.iter().flat_map(|m| m.gpu_vertices(screen));
+        let vertex_count = meshes.iter().map(|m| m.vertices.len()).sum();
+        let vertex_buffer = self
+            .vertex_buffer
+            .lock()
+            .write(
+                device,
+                D3D10_BIND_VERTEX_BUFFER.0 as _,
+                ExactIter(vertices, vertex_count),
+            )
+            .clone();
+
+        let indices = meshes.iter().flat_map(|m| m.indices.iter().copied());
+        let index_count = meshes.iter().map(|m| m.indices.len()).sum();
+        let index_buffer = self
+            .index_buffer
+            .lock()
+            .write(
+                device,
+                D3D10_BIND_INDEX_BUFFER.0 as _,
+                ExactIter(indices, index_count),
+            )
+            .clone();
+
+        let viewport = D3D10_VIEWPORT {
+            TopLeftX: 0,
+            TopLeftY: 0,
+            Width: screen.x as _,
+            Height: screen.y as _,
+            MinDepth: 0.,
+            MaxDepth: 1.,
+        };
+        device.RSSetViewports(1, &viewport);
+        device.RSSetState(&self.raster_state);
+        device.OMSetBlendState(&self.blend_state, [0.; 4].as_ptr(), 0xffffffff);
+        device.OMSetRenderTargets(1, &*self.render_view.lock(), None);
+
+        device.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        device.IASetInputLayout(&self.input_layout);
+        device.IASetVertexBuffers(
+            0,
+            1,
+            &Some(vertex_buffer),
+            &(size_of::<GpuVertex>() as _),
+            &0,
+        );
+        device.IASetIndexBuffer(&index_buffer, DXGI_FORMAT_R32_UINT, 0);
+
+        device.VSSetShader(&self.vertex_shader);
+        device.GSSetShader(None);
+        device.PSSetShader(&self.pixel_shader);
+        device.PSSetSamplers(0, 1, &Some(self.sampler.clone()));
+
+        let textures = self.textures.lock();
+        let (mut base_vertex, mut start_index) = (0, 0);
+        for mesh in meshes {
+            if let Some(tex) = textures.get(&mesh.tex_id) {
+                device.PSSetShaderResources(0, 1, &Some(tex.resource.clone()));
+
+                let scissor = RECT {
+                    left: mesh.rect.left() as _,
+                    top: mesh.rect.top() as _,
+                    right: mesh.rect.right() as _,
+                    bottom: mesh.rect.bottom() as _,
+                };
+                device.RSSetScissorRects(1, &scissor);
+                device.DrawIndexed(mesh.indices.len() as _, start_index, base_vertex);
+            }
+
+            base_vertex += mesh.vertices.len() as i32;
+            start_index += mesh.indices.len() as u32;
+        }
+
+        expect!(
+            self.state_block.Apply(),
+            ApplyState,
+            "Failed to apply state."
+        );
+    }
+}
+
+/// Iterator over all vertices or indices of a frame, with the length known upfront.
+struct ExactIter<I>(I, usize);
+
+impl<I: Iterator> Iterator for ExactIter<I> {
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.1, Some(self.1))
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for ExactIter<I> {}
+
+unsafe fn create_render_view(
+    swap_chain: &IDXGISwapChain,
+    device: &ID3D10Device,
+) -> ID3D10RenderTargetView {
+    let back_buffer: ID3D10Texture2D = expect!(
+        swap_chain.GetBuffer(0),
+        GetBackBuffer,
+        "Failed to get swapchain's back buffer."
+    );
+    expect!(
+        device.CreateRenderTargetView(&back_buffer, null()),
+        CreateRenderTargetView,
+        "Failed to create render target view."
+    )
+}
+
+unsafe fn create_texture(image: &ImageData, device: &ID3D10Device) -> Texture10 {
+    let desc = D3D10_TEXTURE2D_DESC {
+        Width: image.width() as _,
+        Height: image.height() as _,
+        MipLevels: 1,
+        ArraySize: 1,
+        Format: get_image_format(image),
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Usage: D3D10_USAGE_DEFAULT,
+        BindFlags: D3D10_BIND_SHADER_RESOURCE.0 as _,
+        CPUAccessFlags: 0,
+        MiscFlags: 0,
+    };
+    let init = D3D10_SUBRESOURCE_DATA {
+        pSysMem: get_image_data(image),
+        SysMemPitch: (image.width() * image.bytes_per_pixel()) as _,
+        SysMemSlicePitch: 0,
+    };
+
+    let texture = expect!(
+        device.CreateTexture2D(&desc, &init),
+        CreateTexture,
+        "Failed to create 2D texture."
+    );
+    let resource = expect!(
+        device.CreateShaderResourceView(&texture, null()),
+        CreateShaderResourceView,
+        "Failed to create shader resource view."
+    );
+
+    Texture10 { texture, resource }
+}
+
+fn create_blend_state(device: &ID3D10Device) -> ID3D10BlendState {
+    let mut desc: D3D10_BLEND_DESC = unsafe { zeroed() };
+    desc.BlendEnable[0] = true.into();
+    desc.SrcBlend = D3D10_BLEND_SRC_ALPHA;
+    desc.DestBlend = D3D10_BLEND_INV_SRC_ALPHA;
+    desc.BlendOp = D3D10_BLEND_OP_ADD;
+    desc.SrcBlendAlpha = D3D10_BLEND_ONE;
+    desc.DestBlendAlpha = D3D10_BLEND_INV_SRC_ALPHA;
+    desc.BlendOpAlpha = D3D10_BLEND_OP_ADD;
+    desc.RenderTargetWriteMask[0] = D3D10_COLOR_WRITE_ENABLE_ALL.0 as _;
+
+    unsafe {
+        expect!(
+            device.CreateBlendState(&desc),
+            CreateBlendState,
+            "Failed to create blend state."
+        )
+    }
+}
+
+fn create_raster_state(device: &ID3D10Device) -> ID3D10RasterizerState {
+    let desc = D3D10_RASTERIZER_DESC {
+        FillMode: D3D10_FILL_SOLID,
+        CullMode: D3D10_CULL_NONE,
+        FrontCounterClockwise: false.into(),
+        DepthBias: 0,
+        DepthBiasClamp: 0.,
+        SlopeScaledDepthBias: 0.,
+        DepthClipEnable: false.into(),
+        ScissorEnable: true.into(),
+        MultisampleEnable: false.into(),
+        AntialiasedLineEnable: false.into(),
+    };
+
+    unsafe {
+        expect!(
+            device.CreateRasterizerState(&desc),
+            CreateRasterizerState,
+            "Failed to create rasterizer state."
+        )
+    }
+}
+
+fn create_sampler_state(device: &ID3D10Device) -> ID3D10SamplerState {
+    let desc = D3D10_SAMPLER_DESC {
+        Filter: D3D10_FILTER_MIN_MAG_MIP_LINEAR,
+        AddressU: D3D10_TEXTURE_ADDRESS_BORDER,
+        AddressV: D3D10_TEXTURE_ADDRESS_BORDER,
+        AddressW: D3D10_TEXTURE_ADDRESS_BORDER,
+        MipLODBias: 0.,
+        ComparisonFunc: D3D10_COMPARISON_ALWAYS,
+        MinLOD: 0.,
+        MaxLOD: 0.,
+        BorderColor: [1., 1., 1., 1.],
+        MaxAnisotropy: 0,
+    };
+
+    unsafe {
+        expect!(
+            device.CreateSamplerState(&desc),
+            CreateSamplerState,
+            "Failed to create sampler state."
+        )
+    }
+}
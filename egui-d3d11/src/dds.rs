@@ -0,0 +1,167 @@
+use windows::Win32::Graphics::Dxgi::Common::{
+    DXGI_FORMAT, DXGI_FORMAT_BC1_UNORM, DXGI_FORMAT_BC1_UNORM_SRGB, DXGI_FORMAT_BC2_UNORM,
+    DXGI_FORMAT_BC2_UNORM_SRGB, DXGI_FORMAT_BC3_UNORM, DXGI_FORMAT_BC3_UNORM_SRGB,
+    DXGI_FORMAT_BC4_SNORM, DXGI_FORMAT_BC4_UNORM, DXGI_FORMAT_BC5_SNORM, DXGI_FORMAT_BC5_UNORM,
+    DXGI_FORMAT_BC6H_SF16, DXGI_FORMAT_BC6H_UF16, DXGI_FORMAT_BC7_UNORM,
+    DXGI_FORMAT_BC7_UNORM_SRGB, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8G8B8A8_UNORM_SRGB,
+};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 124;
+const DX10_HEADER_SIZE: usize = 20;
+const DDPF_FOURCC: u32 = 0x4;
+
+/// Error returned when loading DDS image.
+#[derive(Debug, thiserror::Error)]
+pub enum DdsError {
+    #[error("Data is not a valid DDS image.")]
+    InvalidHeader,
+    #[error("DDS format {0:#x} is not supported.")]
+    UnsupportedFormat(u32),
+    #[error("DDS image is shorter than its header declares.")]
+    Truncated,
+    #[error("Failed to create texture: {0}")]
+    Device(#[from] windows::core::Error),
+}
+
+/// Single mip level of [`DdsImage`].
+pub(crate) struct DdsMip<'a> {
+    pub data: &'a [u8],
+    pub pitch: u32,
+}
+
+/// Parsed DDS image referencing the original bytes.
+pub(crate) struct DdsImage<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: DXGI_FORMAT,
+    pub mips: Vec<DdsMip<'a>>,
+}
+
+impl<'a> DdsImage<'a> {
+    /// Returns amount of video memory used by all mips.
+    #[inline]
+    pub fn byte_size(&self) -> usize {
+        self.mips.iter().map(|mip| mip.data.len()).sum()
+    }
+}
+
+/// Parses 2D DDS image with block-compressed (BC1-BC7) or RGBA8 pixels.
+/// Cube maps, volumes and arrays are not supported, only the first surface is used.
+pub(crate) fn parse(bytes: &[u8]) -> Result<DdsImage, DdsError> {
+    if bytes.len() < 4 + HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(DdsError::InvalidHeader);
+    }
+
+    let header = &bytes[4..4 + HEADER_SIZE];
+    let read = |offset: usize| read_u32(&header[offset..]);
+
+    if read(0) != HEADER_SIZE as u32 {
+        return Err(DdsError::InvalidHeader);
+    }
+
+    let height = read(8);
+    let width = read(12);
+    let pf_flags = read(76);
+    let fourcc = read(80);
+
+    let mut offset = 4 + HEADER_SIZE;
+    let format = if pf_flags & DDPF_FOURCC == 0 {
+        // Uncompressed images are only accepted as 32 bit RGBA.
+        if read(84) != 32 || read(88) != 0xFF || read(100) != 0xFF00_0000 {
+            return Err(DdsError::UnsupportedFormat(pf_flags));
+        }
+        DXGI_FORMAT_R8G8B8A8_UNORM
+    } else if &fourcc.to_le_bytes() == b"DX10" {
+        let dx10 = bytes
+            .get(offset..offset + DX10_HEADER_SIZE)
+            .ok_or(DdsError::Truncated)?;
+        offset += DX10_HEADER_SIZE;
+
+        let format = DXGI_FORMAT(read_u32(dx10));
+        if block_size(format).is_none()
+            && format != DXGI_FORMAT_R8G8B8A8_UNORM
+            && format != DXGI_FORMAT_R8G8B8A8_UNORM_SRGB
+        {
+            return Err(DdsError::UnsupportedFormat(format.0));
+        }
+        format
+    } else {
+        match &fourcc.to_le_bytes() {
+            b"DXT1" => DXGI_FORMAT_BC1_UNORM,
+            b"DXT2" | b"DXT3" => DXGI_FORMAT_BC2_UNORM,
+            b"DXT4" | b"DXT5" => DXGI_FORMAT_BC3_UNORM,
+            b"ATI1" | b"BC4U" => DXGI_FORMAT_BC4_UNORM,
+            b"BC4S" => DXGI_FORMAT_BC4_SNORM,
+            b"ATI2" | b"BC5U" => DXGI_FORMAT_BC5_UNORM,
+            b"BC5S" => DXGI_FORMAT_BC5_SNORM,
+            _ => return Err(DdsError::UnsupportedFormat(fourcc)),
+        }
+    };
+
+    if width == 0 || height == 0 {
+        return Err(DdsError::InvalidHeader);
+    }
+
+    // Header is untrusted, a full mip chain of the largest side is all there can be.
+    let max_mips = 32 - width.max(height).leading_zeros();
+    let mip_count = read(24).clamp(1, max_mips);
+
+    let mut mips = Vec::with_capacity(mip_count as usize);
+    let (mut w, mut h) = (width, height);
+    for _ in 0..mip_count {
+        let (pitch, rows) = match block_size(format) {
+            Some(block) => (
+                (w / 4 + (w % 4 != 0) as u32).checked_mul(block),
+                h / 4 + (h % 4 != 0) as u32,
+            ),
+            None => (w.checked_mul(4), h),
+        };
+        let pitch = pitch.ok_or(DdsError::InvalidHeader)?;
+
+        let len = (pitch as usize)
+            .checked_mul(rows as usize)
+            .ok_or(DdsError::InvalidHeader)?;
+        let end = offset.checked_add(len).ok_or(DdsError::InvalidHeader)?;
+        let data = bytes.get(offset..end).ok_or(DdsError::Truncated)?;
+        mips.push(DdsMip { data, pitch });
+
+        offset = end;
+        w = (w / 2).max(1);
+        h = (h / 2).max(1);
+    }
+
+    Ok(DdsImage {
+        width,
+        height,
+        format,
+        mips,
+    })
+}
+
+/// Returns size in bytes of a 4x4 block or `None` for uncompressed formats.
+fn block_size(format: DXGI_FORMAT) -> Option<u32> {
+    match format {
+        DXGI_FORMAT_BC1_UNORM
+        | DXGI_FORMAT_BC1_UNORM_SRGB
+        | DXGI_FORMAT_BC4_UNORM
+        | DXGI_FORMAT_BC4_SNORM => Some(8),
+        DXGI_FORMAT_BC2_UNORM
+        | DXGI_FORMAT_BC2_UNORM_SRGB
+        | DXGI_FORMAT_BC3_UNORM
+        | DXGI_FORMAT_BC3_UNORM_SRGB
+        | DXGI_FORMAT_BC5_UNORM
+        | DXGI_FORMAT_BC5_SNORM
+        | DXGI_FORMAT_BC6H_UF16
+        | DXGI_FORMAT_BC6H_SF16
+        | DXGI_FORMAT_BC7_UNORM
+        | DXGI_FORMAT_BC7_UNORM_SRGB => Some(16),
+        _ => None,
+    }
+}
+
+/// Reads little endian `u32` from the start of `bytes`, callers check the length beforehand.
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
@@ -0,0 +1,158 @@
+use egui::{Context, Grid, ScrollArea, Vec2};
+use std::{collections::VecDeque, time::Duration};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
+
+use crate::DirectX11App;
+
+/// How many errors are kept in [`RenderStats::last_errors`].
+const MAX_ERRORS: usize = 16;
+
+/// Video memory currently used by the renderer.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MemoryUsage {
+    pub texture_count: usize,
+    pub texture_bytes: usize,
+    /// Size of vertex and index buffers held by the buffer pool.
+    pub buffer_bytes: usize,
+}
+
+impl MemoryUsage {
+    #[inline]
+    pub fn total_bytes(&self) -> usize {
+        self.texture_bytes + self.buffer_bytes
+    }
+}
+
+/// Statistics of the last rendered frame.
+#[derive(Clone, Default)]
+pub struct RenderStats {
+    pub backbuffer_format: DXGI_FORMAT,
+    pub backbuffer_size: [u32; 2],
+    pub draw_calls: usize,
+    pub meshes: usize,
+    /// Meshes dropped before rendering, e.g. without triangles or clipped outside of the window.
+    pub culled_meshes: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    /// Size of vertex and index data uploaded during the frame.
+    pub buffer_bytes: usize,
+    /// Time spent saving and restoring the game's pipeline state.
+    pub backup_cost: Duration,
+    /// Most recent non-fatal errors, oldest first.
+    pub last_errors: VecDeque<String>,
+}
+
+impl RenderStats {
+    #[inline]
+    pub(crate) fn begin_frame(&mut self) {
+        self.draw_calls = 0;
+        self.meshes = 0;
+        self.culled_meshes = 0;
+        self.vertices = 0;
+        self.indices = 0;
+        self.buffer_bytes = 0;
+        self.backup_cost = Duration::ZERO;
+    }
+
+    pub(crate) fn push_error(&mut self, error: String) {
+        if self.last_errors.len() == MAX_ERRORS {
+            self.last_errors.pop_front();
+        }
+        self.last_errors.push_back(error);
+    }
+}
+
+/// Shows window with internals of the renderer.
+/// Should be called inside of the ui closure, displayed values are from the previous frame.
+pub fn debug_window<T>(ctx: &Context, app: &DirectX11App<T>) {
+    let stats = app.stats();
+    let memory = app.memory_usage();
+
+    egui::Window::new("egui-d3d11 debug")
+        .default_width(320.)
+        .show(ctx, |ui| {
+            Grid::new("egui_d3d11_debug_stats")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Backbuffer format");
+                    ui.label(format!("{}", stats.backbuffer_format.0));
+                    ui.end_row();
+
+                    ui.label("Backbuffer size");
+                    ui.label(format!(
+                        "{}x{}",
+                        stats.backbuffer_size[0], stats.backbuffer_size[1]
+                    ));
+                    ui.end_row();
+
+                    ui.label("Draw calls");
+                    ui.label(stats.draw_calls.to_string());
+                    ui.end_row();
+
+                    ui.label("Meshes");
+                    ui.label(stats.meshes.to_string());
+                    ui.end_row();
+
+                    ui.label("Culled meshes");
+                    ui.label(stats.culled_meshes.to_string());
+                    ui.end_row();
+
+                    ui.label("Vertices");
+                    ui.label(stats.vertices.to_string());
+                    ui.end_row();
+
+                    ui.label("Indices");
+                    ui.label(stats.indices.to_string());
+                    ui.end_row();
+
+                    ui.label("Backup cost");
+                    ui.label(format!("{:.3} ms", stats.backup_cost.as_secs_f64() * 1000.));
+                    ui.end_row();
+
+                    ui.label("Texture memory");
+                    ui.label(format!(
+                        "{} in {} textures",
+                        format_bytes(memory.texture_bytes),
+                        memory.texture_count
+                    ));
+                    ui.end_row();
+
+                    ui.label("Buffer memory");
+                    ui.label(format_bytes(memory.buffer_bytes));
+                    ui.end_row();
+                });
+
+            ui.collapsing("Textures", |ui| {
+                ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                    for (id, [w, h]) in app.texture_list() {
+                        ui.label(format!("{:?} - {}x{}", id, w, h));
+
+                        let scale = (128. / w.max(h).max(1) as f32).min(1.);
+                        ui.image(id, Vec2::new(w as f32 * scale, h as f32 * scale));
+                        ui.separator();
+                    }
+                });
+            });
+
+            ui.collapsing("Last errors", |ui| {
+                if stats.last_errors.is_empty() {
+                    ui.label("None");
+                }
+
+                for error in stats.last_errors.iter().rev() {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+            });
+        });
+}
+
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.2} MiB", bytes as f64 / (1024. * 1024.))
+    } else if bytes >= 1024 {
+        format!("{:.2} KiB", bytes as f64 / 1024.)
+    } else {
+        format!("{} B", bytes)
+    }
+}
@@ -0,0 +1,77 @@
+use windows::{
+    core::Interface,
+    Win32::Graphics::{
+        Direct3D::D3D11_SRV_DIMENSION_TEXTURE2D,
+        Direct3D11::{
+            ID3D11DepthStencilView, ID3D11Device, ID3D11ShaderResourceView, ID3D11Texture2D,
+            D3D11_BIND_SHADER_RESOURCE, D3D11_SHADER_RESOURCE_VIEW_DESC,
+            D3D11_SHADER_RESOURCE_VIEW_DESC_0, D3D11_TEX2D_SRV, D3D11_TEXTURE2D_DESC,
+        },
+        Dxgi::Common::{
+            DXGI_FORMAT, DXGI_FORMAT_R16_TYPELESS, DXGI_FORMAT_R16_UNORM,
+            DXGI_FORMAT_R24G8_TYPELESS, DXGI_FORMAT_R24_UNORM_X8_TYPELESS,
+            DXGI_FORMAT_R32G8X24_TYPELESS, DXGI_FORMAT_R32_FLOAT,
+            DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS, DXGI_FORMAT_R32_TYPELESS,
+        },
+    },
+};
+
+/// Shader resource view of the game's depth buffer.
+pub(crate) struct DepthBuffer {
+    pub resource: ID3D11ShaderResourceView,
+    pub size: [usize; 2],
+}
+
+impl DepthBuffer {
+    /// Creates view reading depth of the texture behind `view`. Returns `None` if the texture
+    /// wasn't created with a typeless format and `D3D11_BIND_SHADER_RESOURCE`,
+    /// depth buffers created with a typed depth format can't be sampled.
+    pub fn new(view: &ID3D11DepthStencilView) -> Option<Self> {
+        unsafe {
+            let mut resource = None;
+            view.GetResource(&mut resource);
+            let texture = resource?.cast::<ID3D11Texture2D>().ok()?;
+
+            let mut desc = D3D11_TEXTURE2D_DESC::default();
+            texture.GetDesc(&mut desc);
+
+            if desc.BindFlags.0 & D3D11_BIND_SHADER_RESOURCE.0 == 0 {
+                return None;
+            }
+            let format = depth_read_format(desc.Format)?;
+
+            let mut device: Option<ID3D11Device> = None;
+            view.GetDevice(&mut device);
+
+            let view_desc = D3D11_SHADER_RESOURCE_VIEW_DESC {
+                Format: format,
+                ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
+                Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2D: D3D11_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                    },
+                },
+            };
+            let resource = device?
+                .CreateShaderResourceView(&texture, &view_desc)
+                .ok()?;
+
+            Some(Self {
+                resource,
+                size: [desc.Width as _, desc.Height as _],
+            })
+        }
+    }
+}
+
+/// Maps typeless depth format to the format that reads its depth component.
+fn depth_read_format(format: DXGI_FORMAT) -> Option<DXGI_FORMAT> {
+    match format {
+        DXGI_FORMAT_R24G8_TYPELESS => Some(DXGI_FORMAT_R24_UNORM_X8_TYPELESS),
+        DXGI_FORMAT_R32_TYPELESS => Some(DXGI_FORMAT_R32_FLOAT),
+        DXGI_FORMAT_R32G8X24_TYPELESS => Some(DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS),
+        DXGI_FORMAT_R16_TYPELESS => Some(DXGI_FORMAT_R16_UNORM),
+        _ => None,
+    }
+}
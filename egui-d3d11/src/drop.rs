@@ -0,0 +1,242 @@
+use egui::{DroppedFile, HoveredFile, Pos2};
+use std::{ffi::OsString, os::windows::ffi::OsStringExt, path::PathBuf, sync::Arc};
+use windows::{
+    core::{implement, Result},
+    Win32::{
+        Foundation::{HWND, POINT, POINTL, PWSTR},
+        Graphics::Gdi::ScreenToClient,
+        System::{
+            Com::{IDataObject, DVASPECT_CONTENT, FORMATETC, TYMED_HGLOBAL},
+            Memory::{GlobalLock, GlobalUnlock},
+            Ole::{
+                IDropTarget, IDropTarget_Impl, OleInitialize, RegisterDragDrop, ReleaseStgMedium,
+                RevokeDragDrop, DROPEFFECT_COPY, DROPEFFECT_NONE,
+            },
+            SystemServices::{CF_HDROP, CF_UNICODETEXT},
+        },
+        UI::Shell::{DragQueryFileW, HDROP},
+    },
+};
+
+use crate::sync::Mutex;
+
+/// Everything received by [`DropTarget`] since the last frame.
+#[derive(Default)]
+pub(crate) struct DropState {
+    /// Position of the dragged item in client coordinates.
+    pub pos: Option<Pos2>,
+    /// Files being dragged over the window, empty when nothing is dragged.
+    pub hovered: Vec<HoveredFile>,
+    pub dropped: Vec<DroppedFile>,
+    /// Dropped text or URL.
+    pub text: Option<String>,
+}
+
+/// OLE drop target registered on the game window.
+/// Unlike `WM_DROPFILES` it reports files during the drag and accepts text.
+#[implement(windows::Win32::System::Ole::IDropTarget)]
+struct DropTarget {
+    hwnd: HWND,
+    state: Arc<Mutex<DropState>>,
+}
+
+#[allow(non_snake_case)]
+impl IDropTarget_Impl for DropTarget {
+    fn DragEnter(
+        &mut self,
+        data: &Option<IDataObject>,
+        _key_state: u32,
+        pt: &POINTL,
+        effect: *mut u32,
+    ) -> Result<()> {
+        let files = data.as_ref().map(get_files).unwrap_or_default();
+        let accepted = !files.is_empty() || data.as_ref().and_then(get_text).is_some();
+
+        let state = &mut *self.state.lock();
+        state.hovered = files
+            .into_iter()
+            .map(|path| HoveredFile {
+                path: Some(path),
+                ..Default::default()
+            })
+            .collect();
+        state.pos = Some(self.to_client(pt));
+
+        unsafe {
+            *effect = if accepted {
+                DROPEFFECT_COPY
+            } else {
+                DROPEFFECT_NONE
+            };
+        }
+        Ok(())
+    }
+
+    fn DragOver(&mut self, _key_state: u32, pt: &POINTL, effect: *mut u32) -> Result<()> {
+        self.state.lock().pos = Some(self.to_client(pt));
+
+        unsafe {
+            *effect = DROPEFFECT_COPY;
+        }
+        Ok(())
+    }
+
+    fn DragLeave(&mut self) -> Result<()> {
+        self.state.lock().hovered.clear();
+        Ok(())
+    }
+
+    fn Drop(
+        &mut self,
+        data: &Option<IDataObject>,
+        _key_state: u32,
+        pt: &POINTL,
+        effect: *mut u32,
+    ) -> Result<()> {
+        let state = &mut *self.state.lock();
+        state.hovered.clear();
+        state.pos = Some(self.to_client(pt));
+
+        if let Some(data) = data {
+            let files = get_files(data);
+            if files.is_empty() {
+                state.text = get_text(data);
+            } else {
+                state.dropped.extend(files.into_iter().map(to_dropped_file));
+            }
+        }
+
+        unsafe {
+            *effect = DROPEFFECT_COPY;
+        }
+        Ok(())
+    }
+}
+
+impl DropTarget {
+    fn to_client(&self, pt: &POINTL) -> Pos2 {
+        let mut point = POINT { x: pt.x, y: pt.y };
+        unsafe {
+            ScreenToClient(self.hwnd, &mut point);
+        }
+        Pos2::new(point.x as f32, point.y as f32)
+    }
+}
+
+/// Keeps [`DropTarget`] registered until dropped.
+pub(crate) struct DropTargetRegistration {
+    hwnd: HWND,
+    state: Arc<Mutex<DropState>>,
+}
+
+impl DropTargetRegistration {
+    /// Registers drop target on the window. Has to be called from the window's thread,
+    /// fails if the game already registered its own target.
+    pub fn new(hwnd: HWND) -> Option<Self> {
+        let state = Arc::new(Mutex::new(DropState::default()));
+        let target: IDropTarget = DropTarget {
+            hwnd,
+            state: state.clone(),
+        }
+        .into();
+
+        unsafe {
+            // Fails with `RPC_E_CHANGED_MODE` on multithreaded apartments, registration below
+            // reports it anyway.
+            let _ = OleInitialize(std::ptr::null_mut());
+            RegisterDragDrop(hwnd, target).ok()?;
+        }
+
+        Some(Self { hwnd, state })
+    }
+
+    #[inline]
+    pub fn state(&self) -> &Mutex<DropState> {
+        &self.state
+    }
+}
+
+impl Drop for DropTargetRegistration {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RevokeDragDrop(self.hwnd);
+        }
+    }
+}
+
+/// Reads all paths from `HDROP` handle.
+pub(crate) fn query_files(hdrop: HDROP) -> Vec<PathBuf> {
+    unsafe {
+        let count = DragQueryFileW(hdrop, u32::MAX, PWSTR::default(), 0);
+        (0..count)
+            .filter_map(|i| {
+                let len = DragQueryFileW(hdrop, i, PWSTR::default(), 0);
+                let mut buf = vec![0u16; len as usize + 1];
+                if DragQueryFileW(hdrop, i, PWSTR(buf.as_mut_ptr()), buf.len() as _) == 0 {
+                    return None;
+                }
+                buf.truncate(len as usize);
+
+                Some(PathBuf::from(OsString::from_wide(&buf)))
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn to_dropped_file(path: PathBuf) -> DroppedFile {
+    DroppedFile {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: Some(path),
+        ..Default::default()
+    }
+}
+
+fn get_files(data: &IDataObject) -> Vec<PathBuf> {
+    unsafe {
+        let mut medium = match data.GetData(&format(CF_HDROP.0)) {
+            Ok(medium) => medium,
+            Err(_) => return vec![],
+        };
+
+        let files = query_files(HDROP(medium.Anonymous.hGlobal));
+        ReleaseStgMedium(&mut medium);
+        files
+    }
+}
+
+fn get_text(data: &IDataObject) -> Option<String> {
+    unsafe {
+        let mut medium = data.GetData(&format(CF_UNICODETEXT.0)).ok()?;
+
+        let ptr = GlobalLock(medium.Anonymous.hGlobal) as *const u16;
+        let text = if ptr.is_null() {
+            None
+        } else {
+            let mut len = 0;
+            while *ptr.add(len) != 0 {
+                len += 1;
+            }
+
+            let text = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
+            GlobalUnlock(medium.Anonymous.hGlobal);
+            Some(text)
+        };
+
+        ReleaseStgMedium(&mut medium);
+        text.filter(|text| !text.is_empty())
+    }
+}
+
+#[inline]
+fn format(cf: u32) -> FORMATETC {
+    FORMATETC {
+        cfFormat: cf as _,
+        ptd: std::ptr::null_mut(),
+        dwAspect: DVASPECT_CONTENT.0 as _,
+        lindex: -1,
+        tymed: TYMED_HGLOBAL.0 as _,
+    }
+}
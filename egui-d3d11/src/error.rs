@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use windows::{core::HRESULT, Win32::Foundation::E_POINTER};
+
+/// Step that failed, reported to the sink set with [`set_error_sink`] before panicking.
+/// Numeric values are stable, so binaries built with `no-msgs` can still be diagnosed.
+#[repr(u16)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorCode {
+    GetSwapChainDesc = 1,
+    GetBackBuffer = 2,
+    GetDevice = 3,
+    GetImmediateContext = 4,
+    CreateRenderTargetView = 5,
+    CompileShader = 6,
+    CreateVertexShader = 7,
+    CreatePixelShader = 8,
+    CreateInputLayout = 9,
+    CreateSamplerState = 10,
+    CreateBlendState = 11,
+    CreateRasterizerState = 12,
+    CreateBuffer = 13,
+    MapBuffer = 14,
+    CreateTexture = 15,
+    CreateShaderResourceView = 16,
+    QueryPerformanceFrequency = 17,
+    CreateStateBlock = 18,
+    CaptureState = 19,
+    ApplyState = 20,
+    CreateDevice = 21,
+    CreateSwapChain = 22,
+    GetDxgiFactory = 23,
+    CreateComposition = 24,
+    ExtendFrame = 25,
+    CreateMediaAttributes = 26,
+    GetDxgiAdapter = 27,
+    CreateCompositionTarget = 28,
+    CreateCompositionVisual = 29,
+    SetCompositionContent = 30,
+    SetCompositionRoot = 31,
+    CommitComposition = 32,
+    QueryDxgiDevice = 33,
+    QuerySwapChain = 34,
+    InvalidOutputWindow = 35,
+    CreateOverlayWindow = 36,
+    UnsupportedFeatureLevel = 37,
+    InvalidImageSize = 38,
+    StreamOverflow = 39,
+}
+
+impl ErrorCode {
+    /// Returns the code as a customer defined `HRESULT` with `FACILITY_ITF`, `0xA004xxxx`.
+    #[inline]
+    pub fn to_hresult(self) -> HRESULT {
+        HRESULT((0xA004_0000u32 | self as u32) as i32)
+    }
+}
+
+/// Receives failures together with `HRESULT` returned by the failed call.
+pub type ErrorSink = fn(ErrorCode, HRESULT);
+
+static SINK: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets function called on every failure before the panic, e.g. to write a crash log.
+/// `None` removes the previous sink.
+pub fn set_error_sink(sink: Option<ErrorSink>) {
+    SINK.store(sink.map_or(0, |f| f as usize), Ordering::SeqCst);
+}
+
+/// Results of calls checked by `expect!`.
+pub(crate) trait Checked<T> {
+    fn check(self) -> Result<T, HRESULT>;
+}
+
+impl<T> Checked<T> for windows::core::Result<T> {
+    #[inline]
+    fn check(self) -> Result<T, HRESULT> {
+        self.map_err(|e| e.code())
+    }
+}
+
+/// Out parameters that stayed empty.
+impl<T> Checked<T> for Option<T> {
+    #[inline]
+    fn check(self) -> Result<T, HRESULT> {
+        self.ok_or(E_POINTER)
+    }
+}
+
+#[cold]
+#[track_caller]
+pub(crate) fn fail(code: ErrorCode, hresult: HRESULT, msg: &str) -> ! {
+    let sink = SINK.load(Ordering::SeqCst);
+    if sink != 0 {
+        let sink: ErrorSink = unsafe { std::mem::transmute(sink) };
+        sink(code, hresult);
+    }
+
+    if cfg!(feature = "no-msgs") {
+        panic!()
+    } else {
+        panic!("{} ({:?}, {:?})", msg, code, hresult)
+    }
+}
@@ -0,0 +1,329 @@
+use std::{mem::zeroed, ptr::null, sync::Once};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+    Graphics::{
+        Dwm::DwmExtendFrameIntoClientArea,
+        Dxgi::{
+            Common::DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SWAP_CHAIN_DESC, DXGI_SWAP_EFFECT_DISCARD,
+            DXGI_USAGE_RENDER_TARGET_OUTPUT,
+        },
+        Gdi::{ClientToScreen, ScreenToClient},
+    },
+    System::LibraryLoader::GetModuleHandleA,
+    UI::{
+        Controls::MARGINS,
+        Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON},
+        WindowsAndMessaging::{
+            CreateWindowExA, DefWindowProcA, DestroyWindow, DispatchMessageA, GetClientRect,
+            GetCursorPos, GetForegroundWindow, GetWindowLongA, IsIconic, IsWindow, PeekMessageA,
+            RegisterClassExA, SetLayeredWindowAttributes, SetWindowDisplayAffinity, SetWindowLongA,
+            SetWindowPos, ShowWindow, TranslateMessage, GWL_EXSTYLE, HWND_TOPMOST, LWA_ALPHA, MSG,
+            PM_REMOVE, SWP_NOACTIVATE, SW_HIDE, SW_SHOWNOACTIVATE, WDA_EXCLUDEFROMCAPTURE,
+            WDA_MONITOR, WDA_NONE, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WNDCLASSEXA,
+            WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT,
+            WS_POPUP,
+        },
+    },
+};
+
+use crate::{
+    swapchain::{create_device, OverlaySwapChain},
+    DirectX11App, DirectX11AppBuilder,
+};
+
+static REGISTER_CLASS: Once = Once::new();
+
+/// Transparent topmost window laid over the client area of another window, rendering egui
+/// into its own swapchain. Used for games whose `Present` can't be hooked.
+///
+/// The overlay is click-through unless egui wants pointer input. Keyboard input only reaches
+/// egui while the overlay window is focused, e.g. after clicking into one of its windows.
+pub struct ExternalOverlay<T = ()> {
+    app: DirectX11App<T>,
+    swap_chain: OverlaySwapChain,
+    hwnd: HWND,
+    target: HWND,
+    rect: RECT,
+    click_through: bool,
+    /// Cursor position and left button state last fed to the app while click-through.
+    polled_cursor: Option<POINT>,
+    polled_button: bool,
+}
+
+impl<T> ExternalOverlay<T> {
+    /// Creates overlay window over `target` and the app using its swapchain.
+    /// All methods have to be called from the thread that created the overlay.
+    pub fn new(target: HWND, builder: DirectX11AppBuilder<T>) -> Self {
+        unsafe {
+            let rect = client_screen_rect(target);
+            let hwnd = create_window(&rect);
+            let swap_chain = create_swap_chain(hwnd, &rect);
+            let app = builder.build(&swap_chain.swap_chain);
+
+            ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+
+            Self {
+                app,
+                swap_chain,
+                hwnd,
+                target,
+                rect,
+                click_through: true,
+                polled_cursor: None,
+                polled_button: false,
+            }
+        }
+    }
+
+    #[inline]
+    pub fn app(&self) -> &DirectX11App<T> {
+        &self.app
+    }
+
+    /// Returns overlay's own window.
+    #[inline]
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
+    /// Returns window the overlay follows.
+    #[inline]
+    pub fn target(&self) -> HWND {
+        self.target
+    }
+
+    /// Hides the overlay from screen capture and recording while it stays visible on screen,
+    /// e.g. to keep it out of a stream. Needs Windows 10 2004, older versions fall back to
+    /// showing a black rectangle in captures. Returns `false` if neither is supported.
+    ///
+    /// Only possible for the overlay's own window, excluding a hooked game's window
+    /// would hide the game as well.
+    pub fn set_capture_excluded(&self, excluded: bool) -> bool {
+        unsafe {
+            if !excluded {
+                return SetWindowDisplayAffinity(self.hwnd, WDA_NONE).as_bool();
+            }
+
+            SetWindowDisplayAffinity(self.hwnd, WDA_EXCLUDEFROMCAPTURE).as_bool()
+                || SetWindowDisplayAffinity(self.hwnd, WDA_MONITOR).as_bool()
+        }
+    }
+
+    /// Processes window messages, follows the target window and renders a frame.
+    /// Returns `false` once the target window is destroyed.
+    pub fn run_frame(&mut self) -> bool {
+        unsafe {
+            if !IsWindow(self.target).as_bool() {
+                return false;
+            }
+
+            self.pump_messages();
+
+            let foreground = GetForegroundWindow();
+            if IsIconic(self.target).as_bool()
+                || (foreground != self.target && foreground != self.hwnd)
+            {
+                ShowWindow(self.hwnd, SW_HIDE);
+                return true;
+            }
+            ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+
+            self.follow_target();
+            self.poll_pointer();
+
+            self.swap_chain.present(&self.app);
+
+            self.set_click_through(!self.app.wants_input().pointer);
+            true
+        }
+    }
+
+    unsafe fn pump_messages(&self) {
+        let mut msg: MSG = zeroed();
+        while PeekMessageA(&mut msg, self.hwnd, 0, 0, PM_REMOVE).as_bool() {
+            self.app.wnd_proc(msg.message, msg.wParam, msg.lParam);
+            TranslateMessage(&msg);
+            DispatchMessageA(&msg);
+        }
+    }
+
+    /// Moves the window over target's client area and resizes buffers when it changes.
+    unsafe fn follow_target(&mut self) {
+        let rect = client_screen_rect(self.target);
+        if rect == self.rect {
+            return;
+        }
+
+        let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+        SetWindowPos(
+            self.hwnd,
+            HWND_TOPMOST,
+            rect.left,
+            rect.top,
+            width,
+            height,
+            SWP_NOACTIVATE,
+        );
+
+        let old_size = (
+            self.rect.right - self.rect.left,
+            self.rect.bottom - self.rect.top,
+        );
+        self.rect = rect;
+        if old_size == (width, height) || width <= 0 || height <= 0 {
+            return;
+        }
+
+        self.swap_chain.resize(&self.app, width as _, height as _);
+    }
+
+    /// Click-through window receives no mouse messages, so the cursor is polled and fed
+    /// to the app as if it did. Otherwise egui would never see the pointer over its windows
+    /// and the overlay could never stop being click-through.
+    unsafe fn poll_pointer(&mut self) {
+        if !self.click_through {
+            // Real messages arrive meanwhile, polling starts over once they stop.
+            self.polled_cursor = None;
+            self.polled_button = false;
+            return;
+        }
+
+        let mut pos = POINT::default();
+        if !GetCursorPos(&mut pos).as_bool() || !ScreenToClient(self.hwnd, &mut pos).as_bool() {
+            return;
+        }
+        let lparam = LPARAM((pos.y as u16 as isize) << 16 | pos.x as u16 as isize);
+
+        if self.polled_cursor != Some(pos) {
+            self.polled_cursor = Some(pos);
+            self.app.wnd_proc(WM_MOUSEMOVE, WPARAM(0), lparam);
+        }
+
+        let down = GetAsyncKeyState(VK_LBUTTON.0 as _) as u16 & 0x8000 != 0;
+        if down != self.polled_button {
+            self.polled_button = down;
+            let msg = if down { WM_LBUTTONDOWN } else { WM_LBUTTONUP };
+            self.app.wnd_proc(msg, WPARAM(0), lparam);
+        }
+    }
+
+    unsafe fn set_click_through(&mut self, click_through: bool) {
+        if self.click_through == click_through {
+            return;
+        }
+        self.click_through = click_through;
+
+        let style = GetWindowLongA(self.hwnd, GWL_EXSTYLE) as u32;
+        let style = if click_through {
+            style | WS_EX_TRANSPARENT.0 | WS_EX_NOACTIVATE.0
+        } else {
+            style & !(WS_EX_TRANSPARENT.0 | WS_EX_NOACTIVATE.0)
+        };
+        SetWindowLongA(self.hwnd, GWL_EXSTYLE, style as _);
+    }
+}
+
+impl<T> Drop for ExternalOverlay<T> {
+    fn drop(&mut self) {
+        unsafe {
+            DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+extern "system" fn wnd_proc(hwnd: HWND, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe { DefWindowProcA(hwnd, umsg, wparam, lparam) }
+}
+
+unsafe fn client_screen_rect(hwnd: HWND) -> RECT {
+    let mut rect = RECT::default();
+    GetClientRect(hwnd, &mut rect);
+
+    let mut origin = POINT::default();
+    ClientToScreen(hwnd, &mut origin);
+
+    RECT {
+        left: origin.x,
+        top: origin.y,
+        right: origin.x + rect.right,
+        bottom: origin.y + rect.bottom,
+    }
+}
+
+unsafe fn create_window(rect: &RECT) -> HWND {
+    let instance = GetModuleHandleA(None);
+
+    REGISTER_CLASS.call_once(|| {
+        let class = WNDCLASSEXA {
+            cbSize: std::mem::size_of::<WNDCLASSEXA>() as _,
+            lpfnWndProc: Some(wnd_proc),
+            hInstance: instance,
+            lpszClassName: c_str!("egui_d3d11_overlay"),
+            ..zeroed()
+        };
+        RegisterClassExA(&class);
+    });
+
+    let hwnd = CreateWindowExA(
+        WS_EX_TOPMOST | WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+        c_str!("egui_d3d11_overlay"),
+        c_str!("egui overlay"),
+        WS_POPUP,
+        rect.left,
+        rect.top,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+        None,
+        None,
+        instance,
+        null(),
+    );
+
+    if hwnd.is_invalid() {
+        crate::error::fail(
+            crate::ErrorCode::CreateOverlayWindow,
+            windows::core::Error::from_win32().code(),
+            if cfg!(feature = "no-msgs") {
+                ""
+            } else {
+                "Failed to create overlay window."
+            },
+        );
+    }
+
+    // Layered window composed by DWM keeps per-pixel alpha of the swapchain.
+    SetLayeredWindowAttributes(hwnd, 0, 255, LWA_ALPHA);
+    let margins = MARGINS {
+        cxLeftWidth: -1,
+        cxRightWidth: -1,
+        cyTopHeight: -1,
+        cyBottomHeight: -1,
+    };
+    expect!(
+        DwmExtendFrameIntoClientArea(hwnd, &margins),
+        ExtendFrame,
+        "Failed to extend frame into client area."
+    );
+
+    hwnd
+}
+
+unsafe fn create_swap_chain(hwnd: HWND, rect: &RECT) -> OverlaySwapChain {
+    let mut desc: DXGI_SWAP_CHAIN_DESC = zeroed();
+    desc.BufferDesc.Width = (rect.right - rect.left).max(1) as _;
+    desc.BufferDesc.Height = (rect.bottom - rect.top).max(1) as _;
+    desc.BufferDesc.Format = DXGI_FORMAT_R8G8B8A8_UNORM;
+    desc.SampleDesc.Count = 1;
+    desc.BufferUsage = DXGI_USAGE_RENDER_TARGET_OUTPUT;
+    desc.BufferCount = 1;
+    desc.OutputWindow = hwnd;
+    desc.Windowed = true.into();
+    desc.SwapEffect = DXGI_SWAP_EFFECT_DISCARD;
+
+    let (swap_chain, device, context) = create_device(Some(&desc));
+    OverlaySwapChain::new(
+        expect!(swap_chain, CreateSwapChain, "Swapchain wasn't created."),
+        device,
+        context,
+    )
+}
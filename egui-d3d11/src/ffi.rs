@@ -0,0 +1,296 @@
+//! Flat C API, declared in `include/egui_d3d11.h` generated with cbindgen.
+//! All functions catch panics, which must not unwind into foreign frames.
+//! Every function shares one safety contract: pointers are either null or point
+//! to live objects of the documented type.
+#![allow(clippy::missing_safety_doc)]
+
+use egui::{Align2, Color32, Context, FontId, Id, LayerId, Order, Painter, Pos2, Rect, Stroke};
+use std::{
+    ffi::{c_void, CStr},
+    mem::{transmute_copy, ManuallyDrop},
+    os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use windows::{
+    core::HRESULT,
+    Win32::{
+        Foundation::{LPARAM, WPARAM},
+        Graphics::Dxgi::IDXGISwapChain,
+    },
+};
+
+use crate::{set_error_sink, DirectX11App, ErrorCode};
+
+/// Called every frame to draw the UI, `painter` is only valid during the call.
+pub type EguiUiCallback = extern "C" fn(painter: *mut EguiPainter, user_data: *mut c_void);
+
+/// Calls the original `ResizeBuffers` and returns its `HRESULT`.
+pub type EguiResizeCallback = extern "C" fn(user_data: *mut c_void) -> i32;
+
+/// Receives numeric code of the failed step and `HRESULT` of the failed call.
+/// Codes are the values of `ErrorCode`, they never change between versions.
+pub type EguiErrorCallback = extern "C" fn(code: u32, hresult: i32);
+
+static ERROR_CALLBACK: AtomicUsize = AtomicUsize::new(0);
+
+fn forward_error(code: ErrorCode, hresult: HRESULT) {
+    let callback = ERROR_CALLBACK.load(Ordering::SeqCst);
+    if callback != 0 {
+        let callback: EguiErrorCallback = unsafe { std::mem::transmute(callback) };
+        callback(code as u32, hresult.0);
+    }
+}
+
+/// Opaque app handle.
+pub struct EguiApp(DirectX11App);
+
+/// Opaque painter handle, draws above the game on a layer without any windows.
+pub struct EguiPainter {
+    pub(crate) painter: Painter,
+}
+
+/// Reinterprets a raw swapchain pointer without taking ownership of its reference.
+unsafe fn borrow_swap_chain(ptr: *mut c_void) -> Option<ManuallyDrop<IDXGISwapChain>> {
+    (!ptr.is_null()).then(|| ManuallyDrop::new(transmute_copy(&ptr)))
+}
+
+#[inline]
+fn color(rgba: u32) -> Color32 {
+    let [r, g, b, a] = rgba.to_be_bytes();
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Creates app for `IDXGISwapChain*`. Returns null on failure.
+/// `ui` is called with `user_data` every frame.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_create(
+    swap_chain: *mut c_void,
+    ui: EguiUiCallback,
+    user_data: *mut c_void,
+) -> *mut EguiApp {
+    let swap_chain = match borrow_swap_chain(swap_chain) {
+        Some(swap_chain) => swap_chain,
+        None => return std::ptr::null_mut(),
+    };
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let draw = move |ctx: &Context, _: &mut ()| {
+            let layer = LayerId::new(Order::Foreground, Id::new("egui_d3d11_ffi"));
+            let mut painter = EguiPainter {
+                painter: ctx.layer_painter(layer),
+            };
+            ui(&mut painter, user_data);
+        };
+        Box::into_raw(Box::new(EguiApp(DirectX11App::new_with_state(
+            draw,
+            &swap_chain,
+            (),
+        ))))
+    }))
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Destroys app created with [`egui_d3d11_create`]. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_destroy(app: *mut EguiApp) {
+    if !app.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(app))));
+    }
+}
+
+/// Should be called before the original `Present`.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_present(
+    app: *const EguiApp,
+    swap_chain: *mut c_void,
+    sync_interval: u32,
+    flags: u32,
+) {
+    if let (Some(app), Some(swap_chain)) = (app.as_ref(), borrow_swap_chain(swap_chain)) {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            app.0.present(&swap_chain, sync_interval, flags)
+        }));
+    }
+}
+
+/// Should be called **INSTEAD** of `ResizeBuffers`, `original` has to call the original one.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_resize_buffers(
+    app: *const EguiApp,
+    swap_chain: *mut c_void,
+    original: EguiResizeCallback,
+    user_data: *mut c_void,
+) -> i32 {
+    match (app.as_ref(), borrow_swap_chain(swap_chain)) {
+        (Some(app), Some(swap_chain)) => catch_unwind(AssertUnwindSafe(|| {
+            app.0
+                .resize_buffers(&swap_chain, || HRESULT(original(user_data)))
+                .0
+        }))
+        // E_FAIL
+        .unwrap_or(0x80004005u32 as i32),
+        _ => original(user_data),
+    }
+}
+
+/// Should be called on each `WndProc`. Returns `true` if the original procedure
+/// should be called, otherwise `WndProc` should return 1.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_wnd_proc(
+    app: *const EguiApp,
+    umsg: u32,
+    wparam: usize,
+    lparam: isize,
+) -> bool {
+    match app.as_ref() {
+        Some(app) => catch_unwind(AssertUnwindSafe(|| {
+            app.0
+                .wnd_proc(umsg, WPARAM(wparam), LPARAM(lparam))
+                .should_forward()
+        }))
+        .unwrap_or(true),
+        None => true,
+    }
+}
+
+/// Shows or hides the UI.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_set_visible(app: *const EguiApp, visible: bool) {
+    if let Some(app) = app.as_ref() {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            app.0.set_visible(visible);
+        }));
+    }
+}
+
+/// Sets callback receiving failures, called before the function that failed returns
+/// its error value. Null removes the previous callback.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_set_error_callback(callback: Option<EguiErrorCallback>) {
+    let _ = catch_unwind(|| {
+        ERROR_CALLBACK.store(callback.map_or(0, |f| f as usize), Ordering::SeqCst);
+        set_error_sink(callback.map(|_| forward_error as _));
+    });
+}
+
+/// Colors are `0xRRGGBBAA`, coordinates are in points.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_painter_line(
+    painter: *mut EguiPainter,
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    width: f32,
+    rgba: u32,
+) {
+    if let Some(p) = painter.as_ref() {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            p.painter.line_segment(
+                [Pos2::new(x0, y0), Pos2::new(x1, y1)],
+                Stroke::new(width, color(rgba)),
+            );
+        }));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_painter_rect(
+    painter: *mut EguiPainter,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    rounding: f32,
+    width: f32,
+    rgba: u32,
+) {
+    if let Some(p) = painter.as_ref() {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            p.painter.rect_stroke(
+                Rect::from_min_size(Pos2::new(x, y), egui::vec2(w, h)),
+                rounding,
+                Stroke::new(width, color(rgba)),
+            );
+        }));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_painter_rect_filled(
+    painter: *mut EguiPainter,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    rounding: f32,
+    rgba: u32,
+) {
+    if let Some(p) = painter.as_ref() {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            p.painter.rect_filled(
+                Rect::from_min_size(Pos2::new(x, y), egui::vec2(w, h)),
+                rounding,
+                color(rgba),
+            );
+        }));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_painter_circle(
+    painter: *mut EguiPainter,
+    x: f32,
+    y: f32,
+    radius: f32,
+    width: f32,
+    rgba: u32,
+) {
+    if let Some(p) = painter.as_ref() {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            p.painter
+                .circle_stroke(Pos2::new(x, y), radius, Stroke::new(width, color(rgba)));
+        }));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_painter_circle_filled(
+    painter: *mut EguiPainter,
+    x: f32,
+    y: f32,
+    radius: f32,
+    rgba: u32,
+) {
+    if let Some(p) = painter.as_ref() {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            p.painter
+                .circle_filled(Pos2::new(x, y), radius, color(rgba));
+        }));
+    }
+}
+
+/// Draws zero terminated UTF-8 `text` with its top left corner at `x`, `y`.
+#[no_mangle]
+pub unsafe extern "C" fn egui_d3d11_painter_text(
+    painter: *mut EguiPainter,
+    x: f32,
+    y: f32,
+    text: *const c_char,
+    size: f32,
+    rgba: u32,
+) {
+    if let (Some(p), false) = (painter.as_ref(), text.is_null()) {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            let text = CStr::from_ptr(text).to_string_lossy();
+            p.painter.text(
+                Pos2::new(x, y),
+                Align2::LEFT_TOP,
+                text,
+                FontId::proportional(size),
+                color(rgba),
+            );
+        }));
+    }
+}
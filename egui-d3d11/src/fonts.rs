@@ -0,0 +1,184 @@
+use egui::{Context, FontData, FontDefinitions, FontFamily};
+use windows::Win32::{
+    Foundation::{HWND, PWSTR},
+    Graphics::Gdi::{
+        CreateFontIndirectW, DeleteObject, GetDC, GetFontData, GetTextFaceW, ReleaseDC,
+        SelectObject, GDI_ERROR, HDC, HGDIOBJ, LOGFONTW,
+    },
+};
+
+/// System fonts appended to the end of both families, covering scripts and emoji
+/// missing from egui's default fonts.
+const FALLBACK_FONTS: &[&str] = &[
+    "Microsoft YaHei",
+    "Yu Gothic",
+    "Malgun Gothic",
+    "Nirmala UI",
+    "Segoe UI Emoji",
+    "Segoe UI Symbol",
+];
+
+/// Fonts and text size applied to the context before the first frame.
+#[derive(Default)]
+pub(crate) struct FontOptions {
+    pub definitions: Option<FontDefinitions>,
+    pub text_scale: Option<f32>,
+    pub fallbacks: bool,
+}
+
+impl FontOptions {
+    /// Adds font with the highest priority for `family`.
+    pub fn push(&mut self, name: String, data: FontData, family: FontFamily) {
+        let fonts = self
+            .definitions
+            .get_or_insert_with(FontDefinitions::default);
+        fonts.font_data.insert(name.clone(), data);
+        fonts.families.entry(family).or_default().insert(0, name);
+    }
+
+    /// Adds installed [`FALLBACK_FONTS`] with the lowest priority, so they're only used
+    /// for glyphs no other font has.
+    fn push_fallbacks(&mut self) {
+        let fonts = self
+            .definitions
+            .get_or_insert_with(FontDefinitions::default);
+        for &name in FALLBACK_FONTS {
+            if fonts.font_data.contains_key(name) {
+                continue;
+            }
+            if let Some(data) = load_system_font(name) {
+                fonts.font_data.insert(name.to_owned(), data);
+                for family in [FontFamily::Proportional, FontFamily::Monospace] {
+                    fonts
+                        .families
+                        .entry(family)
+                        .or_default()
+                        .push(name.to_owned());
+                }
+            }
+        }
+    }
+
+    pub fn apply(mut self, ctx: &Context) {
+        if self.fallbacks {
+            self.push_fallbacks();
+        }
+
+        if let Some(definitions) = self.definitions {
+            ctx.set_fonts(definitions);
+        }
+
+        if let Some(scale) = self.text_scale {
+            let mut style = (*ctx.style()).clone();
+            for font in style.text_styles.values_mut() {
+                font.size *= scale;
+            }
+            ctx.set_style(style);
+        }
+    }
+}
+
+#[inline]
+const fn table_tag(tag: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*tag)
+}
+
+/// Returns `None` when the font has no such table.
+unsafe fn read_table(hdc: HDC, tag: u32) -> Option<Vec<u8>> {
+    let len = GetFontData(hdc, tag, 0, std::ptr::null_mut(), 0);
+    if len == GDI_ERROR as u32 || len == 0 {
+        return None;
+    }
+
+    let mut data = vec![0u8; len as usize];
+    (GetFontData(hdc, tag, 0, data.as_mut_ptr() as _, len) == len).then(|| data)
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[inline]
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// Finds which font of a TrueType collection has `name` table equal to the selected one.
+fn collection_index(collection: &[u8], name: &[u8]) -> Option<u32> {
+    let count = read_u32(collection, 8)?;
+    (0..count).find(|&i| {
+        let table_dir = || -> Option<bool> {
+            let offset = read_u32(collection, 12 + i as usize * 4)? as usize;
+            let tables = read_u16(collection, offset + 4)? as usize;
+            for t in 0..tables {
+                let record = offset + 12 + t * 16;
+                if collection.get(record..record + 4)? == b"name" {
+                    let start = read_u32(collection, record + 8)? as usize;
+                    let len = read_u32(collection, record + 12)? as usize;
+                    return Some(collection.get(start..start + len)? == name);
+                }
+            }
+            Some(false)
+        };
+        table_dir().unwrap_or(false)
+    })
+}
+
+/// Loads installed font family, e.g. `"Segoe UI"` or `"Microsoft YaHei"`, through GDI.
+/// Returns `None` if the family isn't installed, instead of a substituted font.
+pub fn load_system_font(family: &str) -> Option<FontData> {
+    let face: Vec<u16> = family.encode_utf16().collect();
+    if face.len() >= 32 {
+        return None;
+    }
+
+    let mut logfont = LOGFONTW::default();
+    logfont.lfFaceName[..face.len()].copy_from_slice(&face);
+
+    unsafe {
+        let font = CreateFontIndirectW(&logfont);
+        if font.0 == 0 {
+            return None;
+        }
+
+        let hdc = GetDC(HWND(0));
+        let previous = SelectObject(hdc, HGDIOBJ(font.0));
+
+        let result = (|| {
+            // GDI silently falls back to another font for unknown names.
+            let mut selected = [0u16; 32];
+            GetTextFaceW(hdc, selected.len() as _, PWSTR(selected.as_mut_ptr()));
+            let len = selected
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(selected.len());
+            let selected = String::from_utf16_lossy(&selected[..len]);
+            if !selected.eq_ignore_ascii_case(family) {
+                return None;
+            }
+
+            // Fonts shipped in `.ttc` files are only valid together with the whole collection.
+            match read_table(hdc, table_tag(b"ttcf")) {
+                Some(collection) => {
+                    let name = read_table(hdc, table_tag(b"name"))?;
+                    let index = collection_index(&collection, &name)?;
+                    let mut data = FontData::from_owned(collection);
+                    data.index = index;
+                    Some(data)
+                }
+                None => read_table(hdc, 0).map(FontData::from_owned),
+            }
+        })();
+
+        SelectObject(hdc, previous);
+        ReleaseDC(HWND(0), hdc);
+        DeleteObject(HGDIOBJ(font.0));
+
+        result
+    }
+}
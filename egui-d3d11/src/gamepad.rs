@@ -0,0 +1,74 @@
+use egui::{Event, Key, Modifiers};
+use windows::Win32::UI::Input::XboxController::{
+    XInputGetState, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_DPAD_DOWN,
+    XINPUT_GAMEPAD_DPAD_LEFT, XINPUT_GAMEPAD_DPAD_RIGHT, XINPUT_GAMEPAD_DPAD_UP, XINPUT_STATE,
+};
+
+/// Gamepad buttons used for navigating the overlay.
+/// Can be filled from any input library and passed to [`crate::DirectX11App::set_gamepad`].
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct GamepadButtons {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    /// Activates focused widget, `A` on Xbox controllers.
+    pub accept: bool,
+    /// Closes popups and unfocuses widgets, `B` on Xbox controllers.
+    pub back: bool,
+}
+
+impl GamepadButtons {
+    /// Reads state of XInput controller at `index`, returns `None` if it's not connected.
+    pub fn from_xinput(index: u32) -> Option<Self> {
+        let mut state = XINPUT_STATE::default();
+        if unsafe { XInputGetState(index, &mut state) } != 0 {
+            return None;
+        }
+
+        let buttons = state.Gamepad.wButtons;
+        let pressed = |flag| buttons & flag as u16 != 0;
+
+        Some(Self {
+            up: pressed(XINPUT_GAMEPAD_DPAD_UP),
+            down: pressed(XINPUT_GAMEPAD_DPAD_DOWN),
+            left: pressed(XINPUT_GAMEPAD_DPAD_LEFT),
+            right: pressed(XINPUT_GAMEPAD_DPAD_RIGHT),
+            accept: pressed(XINPUT_GAMEPAD_A),
+            back: pressed(XINPUT_GAMEPAD_B),
+        })
+    }
+}
+
+/// Turns changes of gamepad buttons into keyboard events egui uses for focus navigation.
+/// Up and down move focus like shift+tab and tab, left and right are arrows for sliders,
+/// accept is enter and back is escape.
+#[derive(Default)]
+pub(crate) struct GamepadNavigator {
+    previous: GamepadButtons,
+}
+
+impl GamepadNavigator {
+    pub fn update(&mut self, current: GamepadButtons, events: &mut Vec<Event>) {
+        let previous = std::mem::replace(&mut self.previous, current);
+        let mut push = |was: bool, is: bool, key: Key, shift: bool| {
+            if was != is {
+                events.push(Event::Key {
+                    key,
+                    pressed: is,
+                    modifiers: Modifiers {
+                        shift,
+                        ..Default::default()
+                    },
+                });
+            }
+        };
+
+        push(previous.up, current.up, Key::Tab, true);
+        push(previous.down, current.down, Key::Tab, false);
+        push(previous.left, current.left, Key::ArrowLeft, false);
+        push(previous.right, current.right, Key::ArrowRight, false);
+        push(previous.accept, current.accept, Key::Enter, false);
+        push(previous.back, current.back, Key::Escape, false);
+    }
+}
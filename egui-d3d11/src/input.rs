@@ -1,48 +1,294 @@
-use egui::{Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, Vec2};
-use parking_lot::Mutex;
-use std::ffi::CStr;
+use egui::{
+    DroppedFile, Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, TouchDeviceId,
+    TouchId, TouchPhase, Vec2,
+};
 use windows::Win32::{
-    Foundation::{HWND, RECT},
-    System::{
-        DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard},
-        SystemServices::CF_TEXT,
-        WindowsProgramming::NtQuerySystemTime,
-    },
+    Foundation::{HWND, POINT, RECT},
+    Globalization::HIMC,
+    Graphics::Gdi::ScreenToClient,
     UI::{
+        Input::Ime::{
+            ImmGetCompositionStringW, ImmGetContext, ImmReleaseContext, GCS_COMPSTR, GCS_RESULTSTR,
+        },
         Input::KeyboardAndMouse::{
-            GetAsyncKeyState, VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
-            VK_ESCAPE, VK_HOME, VK_INSERT, VK_LEFT, VK_LSHIFT, VK_NEXT, VK_PRIOR, VK_RETURN,
-            VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
+            GetKeyboardLayout, MapVirtualKeyExW, MapVirtualKeyW, VIRTUAL_KEY, VK_BACK, VK_CONTROL,
+            VK_DELETE, VK_DIVIDE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_F24, VK_HOME, VK_INSERT,
+            VK_LCONTROL, VK_LEFT, VK_LMENU, VK_LSHIFT, VK_MENU, VK_MULTIPLY, VK_NEXT, VK_NUMPAD0,
+            VK_NUMPAD9, VK_PRIOR, VK_PROCESSKEY, VK_RCONTROL, VK_RETURN, VK_RIGHT, VK_RMENU,
+            VK_RSHIFT, VK_SHIFT, VK_SPACE, VK_TAB, VK_UP,
         },
+        Input::Pointer::{GetPointerTouchInfo, POINTER_TOUCH_INFO},
+        Input::Touch::{CloseGestureInfoHandle, GetGestureInfo, GESTUREINFO, HGESTUREINFO},
+        Input::{GetRawInputData, HRAWINPUT, RAWINPUT, RAWINPUTHEADER, RID_INPUT, RIM_TYPEMOUSE},
+        Shell::{DragAcceptFiles, DragFinish, HDROP},
+        TextServices::HKL,
         WindowsAndMessaging::{
-            GetClientRect, MK_CONTROL, MK_SHIFT, WHEEL_DELTA, WM_CHAR, WM_KEYDOWN, WM_KEYUP,
-            WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN,
-            WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDBLCLK,
-            WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+            GetClientRect, GetMessageExtraInfo, GetWindowThreadProcessId, SystemParametersInfoW,
+            GF_BEGIN, MK_CONTROL, MOUSEHOOKSTRUCTEX_MOUSE_DATA, POINTER_MESSAGE_FLAG_PRIMARY,
+            SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+            TOUCH_MASK_PRESSURE, WHEEL_DELTA, WM_CHAR, WM_DROPFILES, WM_GESTURE,
+            WM_IME_COMPOSITION, WM_IME_ENDCOMPOSITION, WM_IME_STARTCOMPOSITION, WM_INPUT,
+            WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDBLCLK,
+            WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP,
+            WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_POINTERDOWN, WM_POINTERUP,
+            WM_POINTERUPDATE, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSCHAR,
+            WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1,
+            XBUTTON2,
         },
     },
 };
 
+#[cfg(feature = "record")]
+use crate::record::{InputRecorder, InputReplayer};
+use crate::{
+    clipboard::ClipboardProvider,
+    drop::{query_files, to_dropped_file, DropTargetRegistration},
+    gamepad::{GamepadButtons, GamepadNavigator},
+    sync::Mutex,
+    time::TimeSource,
+};
+
+/// Pressed state of modifier keys on both sides of the keyboard.
+#[derive(Default)]
+struct ModifierKeys {
+    left_shift: bool,
+    right_shift: bool,
+    left_ctrl: bool,
+    right_ctrl: bool,
+    left_alt: bool,
+    right_alt: bool,
+}
+
+impl ModifierKeys {
+    fn update(&mut self, vk: usize, lparam: isize, pressed: bool) {
+        // Key messages carry generic virtual keys, side is encoded in lparam.
+        let scan_code = ((lparam >> 16) & 0xFF) as u32;
+        let extended = (lparam >> 24) & 1 != 0;
+
+        match VIRTUAL_KEY(vk as u16) {
+            VK_SHIFT => {
+                let vk = unsafe { MapVirtualKeyW(scan_code, MAPVK_VSC_TO_VK_EX) };
+                if vk == VK_RSHIFT.0 as u32 {
+                    self.right_shift = pressed;
+                } else {
+                    self.left_shift = pressed;
+                }
+            }
+            VK_LSHIFT => self.left_shift = pressed,
+            VK_RSHIFT => self.right_shift = pressed,
+            VK_CONTROL if extended => self.right_ctrl = pressed,
+            VK_CONTROL | VK_LCONTROL => self.left_ctrl = pressed,
+            VK_RCONTROL => self.right_ctrl = pressed,
+            VK_MENU if extended => self.right_alt = pressed,
+            VK_MENU | VK_LMENU => self.left_alt = pressed,
+            VK_RMENU => self.right_alt = pressed,
+            _ => {}
+        }
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        // AltGr is reported as left control + right alt, neither of them
+        // should act as a modifier while it's producing characters.
+        let alt_gr = self.right_alt && self.left_ctrl;
+        let ctrl = self.right_ctrl || (self.left_ctrl && !alt_gr);
+
+        Modifiers {
+            alt: self.left_alt || (self.right_alt && !alt_gr),
+            shift: self.left_shift || self.right_shift,
+            mac_cmd: false,
+            command: ctrl,
+            ctrl,
+        }
+    }
+}
+
+/// State of side mouse buttons (Mouse4 and Mouse5) which egui doesn't support yet.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ExtraButtons {
+    /// Currently held buttons.
+    pub down: [bool; 2],
+    /// Buttons pressed since the previous frame.
+    pub pressed: [bool; 2],
+    /// Buttons released since the previous frame.
+    pub released: [bool; 2],
+}
+
+/// State of function keys F1 to F24 which egui doesn't have a `Key` for yet, index 0 is F1.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FunctionKeys {
+    /// Currently held keys.
+    pub down: [bool; 24],
+    /// Keys pressed since the previous frame, including auto-repeated presses.
+    pub pressed: [bool; 24],
+    /// Keys released since the previous frame.
+    pub released: [bool; 24],
+}
+
+impl FunctionKeys {
+    fn update(&mut self, wparam: usize, pressed: bool) {
+        let index = match (wparam as u16).checked_sub(VK_F1.0) {
+            Some(index) if wparam as u16 <= VK_F24.0 => index as usize,
+            _ => return,
+        };
+
+        self.down[index] = pressed;
+        if pressed {
+            self.pressed[index] = true;
+        } else {
+            self.released[index] = true;
+        }
+    }
+}
+
+/// Approximate height of a single line of text in points.
+const POINTS_PER_LINE: f32 = 16.;
+/// Approximate width of a single character in points.
+const POINTS_PER_CHAR: f32 = 8.;
+/// Zoom factor applied per single wheel notch.
+const ZOOM_PER_NOTCH: f32 = 1.1;
+/// `MapVirtualKeyExW` mode translating virtual keys to unshifted characters,
+/// not exported by windows 0.32.
+const MAPVK_VK_TO_CHAR: u32 = 2;
+/// `MapVirtualKeyW` mode translating scan codes to side specific virtual keys,
+/// not exported by windows 0.32.
+const MAPVK_VSC_TO_VK_EX: u32 = 3;
+/// Wheel scroll setting meaning a whole page per notch, not exported by windows 0.32.
+const WHEEL_PAGESCROLL: u32 = u32::MAX;
+/// Id of the pinch zoom gesture, not exported by windows 0.32.
+const GID_ZOOM: u32 = 3;
+
+/// Options of [`InputCollector`] set through [`crate::DirectX11AppBuilder`].
+#[derive(Clone, Copy)]
+pub(crate) struct InputOptions {
+    pub scroll_speed: f32,
+    /// Moves egui pointer with relative `WM_INPUT` deltas instead of `WM_MOUSEMOVE`.
+    /// `WM_INPUT` itself is always passed to the game, [`InputPolicy`] never blocks it.
+    pub raw_input: bool,
+    /// Registers window as a target of files dropped from explorer.
+    /// `WM_DROPFILES` is then always consumed by the overlay.
+    pub accept_files: bool,
+    /// Registers OLE drop target reporting hovered files and dropped text.
+    pub drop_target: bool,
+    /// Index of XInput controller polled for navigation.
+    pub xinput: Option<u32>,
+    /// Types characters of alt+key combinations instead of only reporting keys.
+    pub syschar_as_text: bool,
+}
+
+impl Default for InputOptions {
+    fn default() -> Self {
+        Self {
+            scroll_speed: 1.,
+            raw_input: false,
+            accept_files: false,
+            drop_target: false,
+            xinput: None,
+            syschar_as_text: false,
+        }
+    }
+}
+
+/// Rolling average of time between frames, used as [`RawInput::predicted_dt`].
+struct FrameTimer {
+    last: Option<f64>,
+    average: f32,
+}
+
+impl Default for FrameTimer {
+    fn default() -> Self {
+        Self {
+            last: None,
+            average: 1. / 60.,
+        }
+    }
+}
+
+impl FrameTimer {
+    /// Weight of the newest sample.
+    const SMOOTHING: f32 = 0.1;
+    /// Longer gaps come from the overlay being hidden or the game being paused.
+    const MAX_DT: f64 = 0.25;
+
+    fn update(&mut self, time: f64) -> f32 {
+        if let Some(last) = self.last.replace(time) {
+            let dt = time - last;
+            if dt > 0. && dt < Self::MAX_DT {
+                self.average += (dt as f32 - self.average) * Self::SMOOTHING;
+            }
+        }
+        self.average
+    }
+}
+
 pub struct InputCollector {
     hwnd: HWND,
+    clipboard: Box<dyn ClipboardProvider>,
+    time: Box<dyn TimeSource>,
     events: Mutex<Vec<Event>>,
+    dropped_files: Mutex<Vec<DroppedFile>>,
+    drop_target: Option<DropTargetRegistration>,
+    gamepad: Mutex<GamepadNavigator>,
+    frame_timer: Mutex<FrameTimer>,
+    /// Buttons passed with [`crate::DirectX11App::set_gamepad`].
+    gamepad_buttons: Mutex<Option<GamepadButtons>>,
+    /// Result of the last IME composition, `WM_CHAR`s repeating it are skipped.
+    ime_result: Mutex<String>,
+    /// Set while egui shows a non-empty composition string as selected preview text.
+    ime_preview: Mutex<bool>,
+    /// Character of the last numpad operator or decimal key, already reported on key press,
+    /// its `WM_CHAR` is skipped.
+    numpad_char: Mutex<Option<char>>,
+    /// Keyboard layout of the window's thread.
+    layout: Mutex<HKL>,
+    /// Modifiers tracked from key messages.
+    modifiers: Mutex<ModifierKeys>,
+    options: InputOptions,
+    /// Pointer position integrated from raw input deltas.
+    raw_pos: Mutex<Option<Pos2>>,
+    /// Wheel notches with ctrl held since the last frame, fractional for high-resolution wheels.
+    zoom_notches: Mutex<f32>,
+    /// Distance between fingers reported by the previous pinch gesture message.
+    gesture_distance: Mutex<f32>,
+    /// Primary, secondary and middle buttons as last reported to egui.
+    buttons: Mutex<[bool; 3]>,
+    /// Side buttons collected during the current frame.
+    extra_buttons: Mutex<ExtraButtons>,
+    /// Side buttons reported to the last frame.
+    frame_extra_buttons: Mutex<ExtraButtons>,
+    /// Function keys collected during the current frame.
+    function_keys: Mutex<FunctionKeys>,
+    /// Function keys reported to the last frame.
+    frame_function_keys: Mutex<FunctionKeys>,
+    #[cfg(feature = "record")]
+    recorder: Mutex<Option<InputRecorder>>,
+    #[cfg(feature = "record")]
+    replayer: Mutex<Option<InputReplayer>>,
 }
 
 /// High-level overview of recognized `WndProc` messages.
+/// Was named `InputResult` before, which now describes how the message was handled.
 #[repr(u8)]
-pub enum InputResult {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
     Unknown,
     MouseMove,
     MouseLeft,
     MouseRight,
     MouseMiddle,
+    MouseExtra,
+    Touch,
+    /// Files were dropped onto the window.
+    DropFiles,
     Character,
     Scroll,
     Zoom,
     Key,
+    Composition,
+    /// Cursor was set by egui.
+    Cursor,
 }
 
-impl InputResult {
+impl MessageKind {
     #[inline]
     pub fn is_valid(&self) -> bool {
         !self.is_unknown()
@@ -50,123 +296,470 @@ impl InputResult {
 
     #[inline]
     pub fn is_unknown(&self) -> bool {
-        matches!(*self, InputResult::Unknown)
+        matches!(*self, MessageKind::Unknown)
+    }
+
+    /// Returns `true` for messages targeting widgets under the pointer.
+    #[inline]
+    pub fn is_pointer(&self) -> bool {
+        matches!(
+            *self,
+            MessageKind::MouseMove
+                | MessageKind::MouseLeft
+                | MessageKind::MouseRight
+                | MessageKind::MouseMiddle
+                | MessageKind::MouseExtra
+                | MessageKind::Touch
+                | MessageKind::DropFiles
+                | MessageKind::Scroll
+                | MessageKind::Zoom
+        )
+    }
+
+    /// Returns `true` for messages targeting the focused widget.
+    #[inline]
+    pub fn is_keyboard(&self) -> bool {
+        matches!(
+            *self,
+            MessageKind::Character | MessageKind::Key | MessageKind::Composition
+        )
+    }
+}
+
+/// Why [`InputPolicy`] kept message from the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockReason {
+    /// Pointer is above egui or egui is dragging something.
+    PointerOverUi,
+    /// Some widget has keyboard focus.
+    KeyboardFocus,
+    /// [`InputPolicy::BlockAllWhileVisible`] is used and the overlay is visible.
+    OverlayVisible,
+    /// [`InputPolicy::Custom`] closure returned `true`.
+    Custom,
+}
+
+/// What [`crate::DirectX11App::wnd_proc`] did with a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputResult {
+    /// Message was handled by the overlay itself, e.g. egui set the cursor or a keybind was triggered.
+    Consumed,
+    /// Message should be passed to the game.
+    PassedThrough,
+    /// Message was collected by egui but kept from the game.
+    Blocked { reason: BlockReason },
+}
+
+impl InputResult {
+    /// Returns `true` if the original window procedure should be called.
+    /// Otherwise `WndProc` should return `LRESULT(1)`.
+    #[inline]
+    pub fn should_forward(&self) -> bool {
+        matches!(self, InputResult::PassedThrough)
+    }
+}
+
+/// Input which egui currently captures, reported at the end of the last frame.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct WantsInput {
+    /// Pointer is above egui or egui is dragging something.
+    pub pointer: bool,
+    /// Some widget has keyboard focus.
+    pub keyboard: bool,
+}
+
+impl WantsInput {
+    #[inline]
+    pub fn any(&self) -> bool {
+        self.pointer || self.keyboard
+    }
+}
+
+/// State passed to [`InputPolicy::Custom`] for each message.
+pub struct MessageInfo<'a> {
+    pub umsg: u32,
+    pub wparam: usize,
+    pub lparam: isize,
+    pub kind: &'a MessageKind,
+    pub visible: bool,
+    pub wants_pointer: bool,
+    pub wants_keyboard: bool,
+}
+
+/// Decides which window messages are forwarded to the game.
+pub enum InputPolicy {
+    /// Every message is passed to the game.
+    PassAll,
+    /// Messages are blocked only while egui wants pointer or keyboard input. Default one.
+    BlockOverUi,
+    /// All recognized input messages are blocked while the overlay is visible.
+    BlockAllWhileVisible,
+    /// Closure returns `true` if message should be blocked. It's called without
+    /// holding any lock of the app, so it can change the policy itself.
+    Custom(Box<dyn Fn(&MessageInfo) -> bool + 'static>),
+}
+
+impl Default for InputPolicy {
+    fn default() -> Self {
+        Self::BlockOverUi
+    }
+}
+
+impl InputPolicy {
+    /// Returns `true` if message shouldn't reach the game.
+    #[inline]
+    pub fn should_block(&self, info: &MessageInfo) -> bool {
+        self.check(info).is_some()
+    }
+
+    /// Returns reason why message shouldn't reach the game or `None` if it should.
+    pub fn check(&self, info: &MessageInfo) -> Option<BlockReason> {
+        if let InputPolicy::Custom(f) = self {
+            return f(info).then(|| BlockReason::Custom);
+        }
+
+        if !info.visible {
+            return None;
+        }
+
+        match self {
+            InputPolicy::PassAll => None,
+            InputPolicy::BlockAllWhileVisible => {
+                info.kind.is_valid().then(|| BlockReason::OverlayVisible)
+            }
+            _ => match info.kind {
+                k if k.is_pointer() && info.wants_pointer => Some(BlockReason::PointerOverUi),
+                k if k.is_keyboard() && info.wants_keyboard => Some(BlockReason::KeyboardFocus),
+                _ => None,
+            },
+        }
     }
 }
 
 impl InputCollector {
-    pub fn new(hwnd: HWND) -> Self {
+    pub(crate) fn new(
+        hwnd: HWND,
+        clipboard: Box<dyn ClipboardProvider>,
+        time: Box<dyn TimeSource>,
+        options: InputOptions,
+    ) -> Self {
+        if options.accept_files {
+            unsafe { DragAcceptFiles(hwnd, true) };
+        }
+
         Self {
             hwnd,
+            clipboard,
+            time,
+            options,
+            raw_pos: Mutex::new(None),
+            zoom_notches: Mutex::new(0.),
+            gesture_distance: Mutex::new(0.),
             events: Mutex::new(vec![]),
+            dropped_files: Mutex::new(vec![]),
+            drop_target: options
+                .drop_target
+                .then(|| DropTargetRegistration::new(hwnd))
+                .flatten(),
+            gamepad: Mutex::new(GamepadNavigator::default()),
+            frame_timer: Mutex::new(FrameTimer::default()),
+            gamepad_buttons: Mutex::new(None),
+            ime_result: Mutex::new(String::new()),
+            ime_preview: Mutex::new(false),
+            numpad_char: Mutex::new(None),
+            modifiers: Mutex::new(ModifierKeys::default()),
+            buttons: Mutex::new([false; 3]),
+            extra_buttons: Mutex::new(ExtraButtons::default()),
+            frame_extra_buttons: Mutex::new(ExtraButtons::default()),
+            function_keys: Mutex::new(FunctionKeys::default()),
+            frame_function_keys: Mutex::new(FunctionKeys::default()),
+            layout: Mutex::new(unsafe {
+                GetKeyboardLayout(GetWindowThreadProcessId(hwnd, std::ptr::null_mut()))
+            }),
+            #[cfg(feature = "record")]
+            recorder: Mutex::new(None),
+            #[cfg(feature = "record")]
+            replayer: Mutex::new(None),
         }
     }
 
-    pub fn process(&self, umsg: u32, wparam: usize, lparam: isize) -> InputResult {
+    /// Collects the message and decides with `policy` whether it should reach the game.
+    pub fn process(
+        &self,
+        umsg: u32,
+        wparam: usize,
+        lparam: isize,
+        policy: &InputPolicy,
+        visible: bool,
+        wants: WantsInput,
+    ) -> InputResult {
+        let kind = self.classify(umsg, wparam, lparam);
+        // Drop handle is already released with `DragFinish`, the game must not see it again.
+        if kind == MessageKind::DropFiles {
+            return InputResult::Consumed;
+        }
+
+        // Raw input buffer is freed by `DefWindowProc`, which a blocked message would skip.
+        if umsg == WM_INPUT {
+            return InputResult::PassedThrough;
+        }
+
+        let reason = policy.check(&MessageInfo {
+            umsg,
+            wparam,
+            lparam,
+            kind: &kind,
+            visible,
+            wants_pointer: wants.pointer,
+            wants_keyboard: wants.keyboard,
+        });
+
+        match reason {
+            Some(reason) => {
+                self.release_blocked(umsg, lparam);
+                InputResult::Blocked { reason }
+            }
+            None => InputResult::PassedThrough,
+        }
+    }
+
+    /// Turns the message into egui events and reports what kind of input it was.
+    fn classify(&self, umsg: u32, wparam: usize, lparam: isize) -> MessageKind {
         match umsg {
+            WM_INPUT if self.options.raw_input => {
+                let mut input = RAWINPUT::default();
+                let mut size = std::mem::size_of::<RAWINPUT>() as u32;
+
+                let read = unsafe {
+                    GetRawInputData(
+                        HRAWINPUT(lparam),
+                        RID_INPUT,
+                        &mut input as *mut _ as _,
+                        &mut size,
+                        std::mem::size_of::<RAWINPUTHEADER>() as _,
+                    )
+                };
+
+                if read == u32::MAX || input.header.dwType != RIM_TYPEMOUSE.0 {
+                    return MessageKind::Unknown;
+                }
+
+                const MOUSE_MOVE_ABSOLUTE: u16 = 1;
+
+                let mouse = unsafe { input.data.mouse };
+                // Absolute positions come from tablets and remote desktop, those send
+                // regular `WM_MOUSEMOVE`s as well.
+                if mouse.usFlags & MOUSE_MOVE_ABSOLUTE != 0 {
+                    return MessageKind::Unknown;
+                }
+
+                let rect = self.get_screen_rect();
+                let lock = &mut *self.raw_pos.lock();
+                let pos = lock.get_or_insert_with(|| rect.center());
+                *pos = rect.clamp(*pos + Vec2::new(mouse.lLastX as f32, mouse.lLastY as f32));
+
+                push_pointer_moved(&mut self.events.lock(), *pos);
+                MessageKind::MouseMove
+            }
             WM_MOUSEMOVE => {
-                self.events
-                    .lock()
-                    .push(Event::PointerMoved(get_pos(lparam)));
-                InputResult::MouseMove
+                // Games using raw input often keep the cursor locked in the center of the window.
+                if !is_touch_emulated() && !self.options.raw_input {
+                    push_pointer_moved(&mut self.events.lock(), get_pos(lparam));
+                }
+                MessageKind::MouseMove
             }
             WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
-                self.events.lock().push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Primary,
-                    pressed: true,
-                    modifiers: get_modifiers(wparam),
-                });
-                InputResult::MouseLeft
+                self.push_button(lparam, PointerButton::Primary, true);
+                MessageKind::MouseLeft
             }
             WM_LBUTTONUP => {
-                self.events.lock().push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Primary,
-                    pressed: false,
-                    modifiers: get_modifiers(wparam),
-                });
-                InputResult::MouseLeft
+                self.push_button(lparam, PointerButton::Primary, false);
+                MessageKind::MouseLeft
             }
             WM_RBUTTONDOWN | WM_RBUTTONDBLCLK => {
-                self.events.lock().push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Secondary,
-                    pressed: true,
-                    modifiers: get_modifiers(wparam),
-                });
-                InputResult::MouseRight
+                self.push_button(lparam, PointerButton::Secondary, true);
+                MessageKind::MouseRight
             }
             WM_RBUTTONUP => {
-                self.events.lock().push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Secondary,
-                    pressed: false,
-                    modifiers: get_modifiers(wparam),
-                });
-                InputResult::MouseRight
+                self.push_button(lparam, PointerButton::Secondary, false);
+                MessageKind::MouseRight
             }
             WM_MBUTTONDOWN | WM_MBUTTONDBLCLK => {
-                self.events.lock().push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Middle,
-                    pressed: true,
-                    modifiers: get_modifiers(wparam),
-                });
-                InputResult::MouseMiddle
+                self.push_button(lparam, PointerButton::Middle, true);
+                MessageKind::MouseMiddle
             }
             WM_MBUTTONUP => {
-                self.events.lock().push(Event::PointerButton {
-                    pos: get_pos(lparam),
-                    button: PointerButton::Middle,
-                    pressed: false,
-                    modifiers: get_modifiers(wparam),
+                self.push_button(lparam, PointerButton::Middle, false);
+                MessageKind::MouseMiddle
+            }
+            msg @ (WM_XBUTTONDOWN | WM_XBUTTONDBLCLK | WM_XBUTTONUP) => {
+                let pressed = msg != WM_XBUTTONUP;
+                let button = match MOUSEHOOKSTRUCTEX_MOUSE_DATA((wparam >> 16) as u16 as u32) {
+                    XBUTTON1 => 0,
+                    XBUTTON2 => 1,
+                    _ => return MessageKind::Unknown,
+                };
+
+                let lock = &mut *self.extra_buttons.lock();
+                lock.down[button] = pressed;
+                if pressed {
+                    lock.pressed[button] = true;
+                } else {
+                    lock.released[button] = true;
+                }
+                MessageKind::MouseExtra
+            }
+            msg @ (WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP) => {
+                let id = (wparam & 0xFFFF) as u32;
+                let flags = (wparam >> 16) as u32;
+
+                let mut info = POINTER_TOUCH_INFO::default();
+                // Fails for mouse and pen pointers, those are handled as mouse messages.
+                if !unsafe { GetPointerTouchInfo(id, &mut info) }.as_bool() {
+                    return MessageKind::Unknown;
+                }
+
+                let phase = match msg {
+                    WM_POINTERDOWN => TouchPhase::Start,
+                    WM_POINTERUP => TouchPhase::End,
+                    _ => TouchPhase::Move,
+                };
+                let pos = self.screen_to_client(lparam);
+                let force = if info.touchMask & TOUCH_MASK_PRESSURE != 0 {
+                    info.pressure as f32 / 1024.
+                } else {
+                    0.
+                };
+
+                let primary = flags & POINTER_MESSAGE_FLAG_PRIMARY != 0;
+                if primary && phase != TouchPhase::Move {
+                    self.buttons.lock()[0] = phase == TouchPhase::Start;
+                }
+
+                let lock = &mut *self.events.lock();
+                lock.push(Event::Touch {
+                    device_id: TouchDeviceId(info.pointerInfo.sourceDevice.0 as u64),
+                    id: TouchId(id as u64),
+                    phase,
+                    pos,
+                    force,
                 });
-                InputResult::MouseMiddle
+
+                // Primary touch drives the pointer so regular widgets can be clicked and dragged.
+                if primary {
+                    push_pointer_moved(lock, pos);
+
+                    if phase != TouchPhase::Move {
+                        lock.push(Event::PointerButton {
+                            pos,
+                            button: PointerButton::Primary,
+                            pressed: phase == TouchPhase::Start,
+                            modifiers: self.modifiers(),
+                        });
+                    }
+
+                    if phase == TouchPhase::End {
+                        lock.push(Event::PointerGone);
+                    }
+                }
+
+                MessageKind::Touch
             }
             WM_CHAR => {
-                if let Some(ch) = char::from_u32(wparam as _) {
-                    if !ch.is_control() {
+                let numpad_char = self.numpad_char.lock().take();
+                if let Some(ch) = char::from_u32(wparam as _).filter(|ch| numpad_char != Some(*ch))
+                {
+                    // `DefWindowProc` turns composition result into `WM_CHAR`s
+                    // which were already sent to egui with `CompositionEnd`.
+                    let ime_result = &mut *self.ime_result.lock();
+                    if ime_result.starts_with(ch) {
+                        ime_result.remove(0);
+                    } else if !ch.is_control() {
+                        ime_result.clear();
+                        self.events.lock().push(Event::Text(ch.into()));
+                    }
+                }
+                MessageKind::Character
+            }
+            WM_SYSCHAR => {
+                // Key itself was already reported by `WM_SYSKEYDOWN`. Message is still reported
+                // as character so the input policy can block it while egui has keyboard focus,
+                // otherwise `DefWindowProc` looks for a menu mnemonic and beeps.
+                if self.options.syschar_as_text {
+                    if let Some(ch) = char::from_u32(wparam as _).filter(|ch| !ch.is_control()) {
                         self.events.lock().push(Event::Text(ch.into()));
                     }
                 }
-                InputResult::Character
+                MessageKind::Character
             }
-            WM_MOUSEWHEEL => {
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
+            msg @ (WM_MOUSEWHEEL | WM_MOUSEHWHEEL) => {
+                let notches = (wparam >> 16) as i16 as f32 / WHEEL_DELTA as f32;
+                // Wheel messages carry screen coordinates unlike the rest of mouse messages.
+                push_pointer_moved(&mut self.events.lock(), self.screen_to_client(lparam));
 
                 if wparam & MK_CONTROL as usize != 0 {
-                    self.events
-                        .lock()
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
-                    InputResult::Zoom
+                    *self.zoom_notches.lock() += notches;
+                    MessageKind::Zoom
                 } else {
-                    self.events.lock().push(Event::Scroll(Vec2::new(0., delta)));
-                    InputResult::Scroll
+                    let horizontal = msg == WM_MOUSEHWHEEL;
+                    let delta =
+                        notches * self.get_scroll_distance(horizontal) * self.options.scroll_speed;
+                    let scroll = if horizontal {
+                        Vec2::new(delta, 0.)
+                    } else {
+                        Vec2::new(0., delta)
+                    };
+
+                    self.events.lock().push(Event::Scroll(scroll));
+                    MessageKind::Scroll
                 }
             }
-            WM_MOUSEHWHEEL => {
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
+            WM_GESTURE => {
+                let mut info = GESTUREINFO {
+                    cbSize: std::mem::size_of::<GESTUREINFO>() as _,
+                    ..Default::default()
+                };
 
-                if wparam & MK_CONTROL as usize != 0 {
-                    self.events
-                        .lock()
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
-                    InputResult::Zoom
-                } else {
-                    self.events.lock().push(Event::Scroll(Vec2::new(delta, 0.)));
-                    InputResult::Scroll
+                // Handle is left for `DefWindowProc` to close, or `Self::release_blocked`.
+                if !unsafe { GetGestureInfo(HGESTUREINFO(lparam), &mut info) }.as_bool()
+                    || info.dwID != GID_ZOOM
+                {
+                    return MessageKind::Unknown;
                 }
+
+                // Lower 32 bits hold distance between the fingers.
+                let distance = info.ullArguments as u32 as f32;
+                let previous = &mut *self.gesture_distance.lock();
+
+                if info.dwFlags & GF_BEGIN == 0 && *previous > 0. {
+                    let center = (info.ptsLocation.x as u16 as isize)
+                        | ((info.ptsLocation.y as u16 as isize) << 16);
+
+                    let lock = &mut *self.events.lock();
+                    push_pointer_moved(lock, self.screen_to_client(center));
+                    lock.push(Event::Zoom(distance / *previous));
+                }
+                *previous = distance;
+
+                MessageKind::Zoom
             }
-            msg @ (WM_KEYDOWN | WM_SYSKEYDOWN) => {
-                if let Some(key) = get_key(wparam) {
+            WM_KEYDOWN | WM_SYSKEYDOWN => {
+                self.modifiers.lock().update(wparam, lparam, true);
+                // Keys handled by the IME come as `VK_PROCESSKEY`, any other key press means
+                // the result's `WM_CHAR`s were all delivered and the next ones are typed.
+                if wparam != VK_PROCESSKEY.0 as usize {
+                    self.ime_result.lock().clear();
+                }
+                self.function_keys.lock().update(wparam, true);
+
+                if let Some(key) = self.get_key(wparam) {
                     let lock = &mut *self.events.lock();
-                    let mods = get_key_modifiers(msg);
+                    let mods = self.modifiers();
 
                     if key == Key::Space {
                         lock.push(Event::Text(String::from(" ")));
                     } else if key == Key::V && mods.ctrl {
-                        if let Some(clipboard) = get_clipboard_text() {
+                        if let Some(clipboard) = self.clipboard.get_text() {
                             lock.push(Event::Text(clipboard));
                         }
                     } else if key == Key::C && mods.ctrl {
@@ -177,54 +770,396 @@ impl InputCollector {
                         lock.push(Event::Key {
                             key,
                             pressed: true,
-                            modifiers: get_key_modifiers(msg),
+                            modifiers: mods,
                         });
                     }
+                } else if let Some(ch) = self.get_numpad_char(wparam) {
+                    // Typed right away, games not calling `TranslateMessage` never get `WM_CHAR`.
+                    let mods = self.modifiers();
+                    if !mods.ctrl && !mods.alt {
+                        self.events.lock().push(Event::Text(ch.into()));
+                        *self.numpad_char.lock() = Some(ch);
+                    }
                 }
-                InputResult::Key
+                MessageKind::Key
             }
-            msg @ (WM_KEYUP | WM_SYSKEYUP) => {
-                if let Some(key) = get_key(wparam) {
+            WM_KEYUP | WM_SYSKEYUP => {
+                self.modifiers.lock().update(wparam, lparam, false);
+                self.function_keys.lock().update(wparam, false);
+
+                if let Some(key) = self.get_key(wparam) {
                     self.events.lock().push(Event::Key {
                         key,
                         pressed: false,
-                        modifiers: get_key_modifiers(msg),
+                        modifiers: self.modifiers(),
                     });
                 }
-                InputResult::Key
+                MessageKind::Key
+            }
+            WM_DROPFILES if self.options.accept_files => {
+                let hdrop = HDROP(wparam as _);
+                let files = query_files(hdrop);
+                unsafe { DragFinish(hdrop) };
+
+                self.dropped_files
+                    .lock()
+                    .extend(files.into_iter().map(to_dropped_file));
+                MessageKind::DropFiles
             }
-            _ => InputResult::Unknown,
+            WM_KILLFOCUS => {
+                // Key releases are not delivered after alt-tab, keys would stay pressed.
+                *self.modifiers.lock() = ModifierKeys::default();
+                self.function_keys.lock().down = [false; 24];
+                MessageKind::Unknown
+            }
+            WM_INPUTLANGCHANGE => {
+                // New layout is passed in lparam, unfinished composition belongs to the old one.
+                *self.layout.lock() = HKL(lparam);
+                self.ime_result.lock().clear();
+                MessageKind::Unknown
+            }
+            WM_IME_STARTCOMPOSITION => {
+                self.ime_result.lock().clear();
+                self.events.lock().push(Event::CompositionStart);
+                MessageKind::Composition
+            }
+            WM_IME_COMPOSITION => {
+                let lock = &mut *self.events.lock();
+                let flags = lparam as u32;
+
+                if flags & GCS_RESULTSTR != 0 {
+                    if let Some(result) = self.get_composition_string(GCS_RESULTSTR) {
+                        // Result replaces the selected preview.
+                        *self.ime_preview.lock() = false;
+                        *self.ime_result.lock() = result.clone();
+                        lock.push(Event::CompositionEnd(result));
+                    }
+
+                    // Composition may continue right after committing part of the text.
+                    if flags & GCS_COMPSTR != 0 {
+                        lock.push(Event::CompositionStart);
+                    }
+                }
+
+                if flags & GCS_COMPSTR != 0 {
+                    // Empty when the user deleted all composed characters.
+                    let comp = self.get_composition_string(GCS_COMPSTR).unwrap_or_default();
+                    self.update_composition(lock, comp);
+                }
+                MessageKind::Composition
+            }
+            WM_IME_ENDCOMPOSITION => {
+                let lock = &mut *self.events.lock();
+                // Composition cancelled with Esc ends without a result.
+                self.update_composition(lock, String::new());
+                lock.push(Event::CompositionEnd(String::new()));
+                MessageKind::Composition
+            }
+            _ => MessageKind::Unknown,
+        }
+    }
+
+    /// Frees resources of a message kept from the original window procedure,
+    /// which would release them through `DefWindowProc` otherwise.
+    fn release_blocked(&self, umsg: u32, lparam: isize) {
+        if umsg == WM_GESTURE {
+            unsafe { CloseGestureInfoHandle(HGESTUREINFO(lparam)) };
+        }
+    }
+
+    /// Pushes button event keeping presses and releases paired.
+    /// Windows replaces the second press of a double click with `WM_*BUTTONDBLCLK`
+    /// and drops releases which happen outside of the window, egui needs
+    /// strict press/release sequence to detect clicks and double clicks.
+    /// Missing releases are synthesized before the next press,
+    /// releases without a press are dropped.
+    fn push_button(&self, lparam: isize, button: PointerButton, pressed: bool) {
+        let pos = match *self.raw_pos.lock() {
+            Some(pos) if self.options.raw_input => pos,
+            _ => get_pos(lparam),
+        };
+        let modifiers = self.modifiers();
+        let index = match button {
+            PointerButton::Primary => 0,
+            PointerButton::Secondary => 1,
+            PointerButton::Middle => 2,
+        };
+
+        // Touches are already reported from `WM_POINTER*` messages.
+        if is_touch_emulated() {
+            return;
+        }
+
+        let buttons = &mut *self.buttons.lock();
+        let events = &mut *self.events.lock();
+
+        if buttons[index] == pressed {
+            // Release without a tracked press, e.g. pressed over the game while the overlay
+            // was hidden, would otherwise click whatever widget it happens over.
+            if !pressed {
+                return;
+            }
+
+            events.push(Event::PointerButton {
+                pos,
+                button,
+                pressed: false,
+                modifiers,
+            });
+        }
+
+        events.push(Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers,
+        });
+        buttons[index] = pressed;
+    }
+
+    /// Returns distance in points to scroll per wheel notch, honoring system settings.
+    fn get_scroll_distance(&self, horizontal: bool) -> f32 {
+        let (action, per_unit) = if horizontal {
+            (SPI_GETWHEELSCROLLCHARS, POINTS_PER_CHAR)
+        } else {
+            (SPI_GETWHEELSCROLLLINES, POINTS_PER_LINE)
+        };
+
+        let mut amount = 3u32;
+        unsafe {
+            SystemParametersInfoW(
+                action,
+                0,
+                &mut amount as *mut _ as _,
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            );
+        }
+
+        if amount == WHEEL_PAGESCROLL {
+            let rect = self.get_screen_rect();
+            if horizontal {
+                rect.width()
+            } else {
+                rect.height()
+            }
+        } else {
+            amount as f32 * per_unit
+        }
+    }
+
+    fn screen_to_client(&self, lparam: isize) -> Pos2 {
+        let mut point = POINT {
+            x: (lparam & 0xFFFF) as i16 as i32,
+            y: (lparam >> 16 & 0xFFFF) as i16 as i32,
+        };
+
+        unsafe {
+            ScreenToClient(self.hwnd, &mut point);
+        }
+
+        Pos2::new(point.x as f32, point.y as f32)
+    }
+
+    /// Shows composition string as egui's selected preview text. egui ignores empty updates
+    /// and ends of a composition, so a preview that is cleared is deleted with a backspace.
+    fn update_composition(&self, events: &mut Vec<Event>, comp: String) {
+        let preview = &mut *self.ime_preview.lock();
+        if !comp.is_empty() {
+            *preview = true;
+            events.push(Event::CompositionUpdate(comp));
+        } else if std::mem::take(preview) {
+            for pressed in [true, false] {
+                events.push(Event::Key {
+                    key: Key::Backspace,
+                    pressed,
+                    modifiers: Modifiers::default(),
+                });
+            }
+        }
+    }
+
+    fn get_composition_string(&self, kind: u32) -> Option<String> {
+        unsafe {
+            let himc: HIMC = ImmGetContext(self.hwnd);
+            if himc.is_invalid() {
+                return None;
+            }
+
+            // Returned size is in bytes.
+            let len = ImmGetCompositionStringW(himc, kind, std::ptr::null_mut(), 0);
+            let result = if len > 0 {
+                let mut buf = vec![0u16; len as usize / 2];
+                ImmGetCompositionStringW(himc, kind, buf.as_mut_ptr() as _, len as _);
+                Some(String::from_utf16_lossy(&buf))
+            } else {
+                None
+            };
+
+            ImmReleaseContext(self.hwnd, himc);
+            result
         }
     }
 
     pub fn collect_input(&self) -> RawInput {
-        let events = std::mem::take(&mut *self.events.lock());
+        let time = self.time.now();
+
+        #[allow(unused_mut)]
+        let mut events = std::mem::take(&mut *self.events.lock());
+        let mut dropped_files = std::mem::take(&mut *self.dropped_files.lock());
+        let mut hovered_files = vec![];
+
+        if let Some(target) = &self.drop_target {
+            let state = &mut *target.state().lock();
+            if let Some(pos) = state.pos.take() {
+                push_pointer_moved(&mut events, pos);
+            }
+            if let Some(text) = state.text.take() {
+                events.push(Event::Text(text));
+            }
+
+            hovered_files = state.hovered.clone();
+            dropped_files.append(&mut state.dropped);
+        }
+
+        let zoom = std::mem::take(&mut *self.zoom_notches.lock());
+        if zoom != 0. {
+            events.push(Event::Zoom(ZOOM_PER_NOTCH.powf(zoom)));
+        }
+
+        let gamepad = match self.options.xinput {
+            Some(index) => GamepadButtons::from_xinput(index),
+            None => *self.gamepad_buttons.lock(),
+        };
+        self.gamepad
+            .lock()
+            .update(gamepad.unwrap_or_default(), &mut events);
+
+        {
+            let current = &mut *self.extra_buttons.lock();
+            *self.frame_extra_buttons.lock() = *current;
+            current.pressed = [false; 2];
+            current.released = [false; 2];
+        }
+        {
+            let current = &mut *self.function_keys.lock();
+            *self.frame_function_keys.lock() = *current;
+            current.pressed = [false; 24];
+            current.released = [false; 24];
+        }
+
+        #[cfg(feature = "record")]
+        self.record_and_replay(time, &mut events);
 
         RawInput {
             screen_rect: Some(self.get_screen_rect()),
-            time: Some(Self::get_system_time()),
-            modifiers: Modifiers::default(),
+            time: Some(time),
+            modifiers: self.modifiers(),
             pixels_per_point: Some(1.),
             max_texture_side: None,
-            predicted_dt: 1. / 60.,
-            hovered_files: vec![],
-            dropped_files: vec![],
+            predicted_dt: self.frame_timer.lock().update(time),
+            hovered_files,
+            dropped_files,
             events,
         }
     }
 
-    /// Returns time in seconds.
-    pub fn get_system_time() -> f64 {
-        let mut time = 0;
-        unsafe {
-            expect!(NtQuerySystemTime(&mut time), "Failed to get system time.");
+    #[cfg(feature = "record")]
+    fn record_and_replay(&self, time: f64, events: &mut Vec<Event>) {
+        let recorder = &mut *self.recorder.lock();
+        if let Some(rec) = recorder {
+            if rec.record(time, events).is_err() {
+                *recorder = None;
+            }
         }
 
-        // dumb ass, read the docs. egui clearly says `in seconds`.
-        // Shouldn't have wasted 3 days on this.
-        // `NtQuerySystemTime` returns how many 100 nanosecond intervals
-        // past since 1st Jan, 1601.
-        (time as f64) / 10_000_000.
+        let replayer = &mut *self.replayer.lock();
+        if let Some(rep) = replayer {
+            // Live input is ignored while replaying so it can't interfere with recording.
+            *events = rep.next_events(time);
+
+            if rep.is_finished() {
+                *replayer = None;
+            }
+        }
+    }
+
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn set_recorder(&self, recorder: Option<InputRecorder>) {
+        *self.recorder.lock() = recorder;
+    }
+
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn set_replayer(&self, replayer: Option<InputReplayer>) {
+        *self.replayer.lock() = replayer;
+    }
+
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.lock().is_some()
+    }
+
+    #[cfg(feature = "record")]
+    #[inline]
+    pub fn is_replaying(&self) -> bool {
+        self.replayer.lock().is_some()
+    }
+
+    #[inline]
+    pub fn clipboard(&self) -> &dyn ClipboardProvider {
+        self.clipboard.as_ref()
+    }
+
+    #[inline]
+    pub fn set_gamepad(&self, buttons: Option<GamepadButtons>) {
+        *self.gamepad_buttons.lock() = buttons;
+    }
+
+    #[inline]
+    pub fn extra_buttons(&self) -> ExtraButtons {
+        *self.frame_extra_buttons.lock()
+    }
+
+    #[inline]
+    pub fn function_keys(&self) -> FunctionKeys {
+        *self.frame_function_keys.lock()
+    }
+
+    #[inline]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers.lock().modifiers()
+    }
+
+    /// Translates virtual key using the active keyboard layout,
+    /// so letters and digits match what is printed on the user's keyboard.
+    /// Keys producing non latin characters fall back to US mapping to keep shortcuts working.
+    /// Numpad keys are mapped regardless of the layout. With NumLock off Windows sends
+    /// navigation keys instead (`VK_HOME`, `VK_UP`, `VK_DELETE`...) and Num Enter is
+    /// `VK_RETURN` with the extended flag, all of them have their own `Key`.
+    fn get_key(&self, wparam: usize) -> Option<Key> {
+        if (VK_NUMPAD0.0..=VK_NUMPAD9.0).contains(&(wparam as u16)) {
+            return get_key(wparam);
+        }
+
+        let ch = unsafe { MapVirtualKeyExW(wparam as _, MAPVK_VK_TO_CHAR, *self.layout.lock()) };
+
+        // Highest bit marks dead keys.
+        char::from_u32(ch & 0x7FFF_FFFF)
+            .and_then(get_char_key)
+            .or_else(|| get_key(wparam))
+    }
+
+    /// Returns character of numpad operator or decimal key, egui has no `Key` for them.
+    /// Decimal separator depends on the layout.
+    fn get_numpad_char(&self, wparam: usize) -> Option<char> {
+        if !(VK_MULTIPLY.0..=VK_DIVIDE.0).contains(&(wparam as u16)) {
+            return None;
+        }
+
+        let ch = unsafe { MapVirtualKeyExW(wparam as _, MAPVK_VK_TO_CHAR, *self.layout.lock()) };
+        char::from_u32(ch).filter(|ch| !ch.is_control())
     }
 
     #[inline]
@@ -249,6 +1184,25 @@ impl InputCollector {
     }
 }
 
+/// Replaces position of the last event if it's also a move.
+/// High polling rate mice send thousands of moves per frame while egui only needs the latest one,
+/// all events of a frame share the same timestamp so they wouldn't improve velocity either.
+#[inline]
+fn push_pointer_moved(events: &mut Vec<Event>, pos: Pos2) {
+    match events.last_mut() {
+        Some(Event::PointerMoved(last)) => *last = pos,
+        _ => events.push(Event::PointerMoved(pos)),
+    }
+}
+
+/// Returns `true` if current mouse message was generated by Windows from touch input.
+fn is_touch_emulated() -> bool {
+    const MI_WP_SIGNATURE: u32 = 0xFF515700;
+    const SIGNATURE_MASK: u32 = 0xFFFFFF00;
+
+    unsafe { GetMessageExtraInfo().0 as u32 & SIGNATURE_MASK == MI_WP_SIGNATURE }
+}
+
 fn get_pos(lparam: isize) -> Pos2 {
     let x = (lparam & 0xFFFF) as i16 as f32;
     let y = (lparam >> 16 & 0xFFFF) as i16 as f32;
@@ -256,33 +1210,64 @@ fn get_pos(lparam: isize) -> Pos2 {
     Pos2::new(x, y)
 }
 
-fn get_modifiers(wparam: usize) -> Modifiers {
-    Modifiers {
-        alt: false,
-        ctrl: (wparam & MK_CONTROL as usize) != 0,
-        shift: (wparam & MK_SHIFT as usize) != 0,
-        mac_cmd: false,
-        command: (wparam & MK_CONTROL as usize) != 0,
-    }
-}
-
-fn get_key_modifiers(msg: u32) -> Modifiers {
-    let ctrl = unsafe { GetAsyncKeyState(VK_CONTROL.0 as _) != 0 };
-    let shift = unsafe { GetAsyncKeyState(VK_LSHIFT.0 as _) != 0 };
-
-    Modifiers {
-        alt: msg == WM_SYSKEYDOWN,
-        mac_cmd: false,
-        command: ctrl,
-        shift,
-        ctrl,
+fn get_char_key(ch: char) -> Option<Key> {
+    match ch.to_ascii_uppercase() {
+        c @ '0'..='9' => get_key(c as usize),
+        c @ 'A'..='Z' => get_key(c as usize),
+        _ => None,
     }
 }
 
 fn get_key(wparam: usize) -> Option<Key> {
     match wparam {
-        0x30..=0x39 => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x21)) },
-        0x41..=0x5A => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x28)) },
+        0x30 => Some(Key::Num0),
+        0x31 => Some(Key::Num1),
+        0x32 => Some(Key::Num2),
+        0x33 => Some(Key::Num3),
+        0x34 => Some(Key::Num4),
+        0x35 => Some(Key::Num5),
+        0x36 => Some(Key::Num6),
+        0x37 => Some(Key::Num7),
+        0x38 => Some(Key::Num8),
+        0x39 => Some(Key::Num9),
+        0x41 => Some(Key::A),
+        0x42 => Some(Key::B),
+        0x43 => Some(Key::C),
+        0x44 => Some(Key::D),
+        0x45 => Some(Key::E),
+        0x46 => Some(Key::F),
+        0x47 => Some(Key::G),
+        0x48 => Some(Key::H),
+        0x49 => Some(Key::I),
+        0x4A => Some(Key::J),
+        0x4B => Some(Key::K),
+        0x4C => Some(Key::L),
+        0x4D => Some(Key::M),
+        0x4E => Some(Key::N),
+        0x4F => Some(Key::O),
+        0x50 => Some(Key::P),
+        0x51 => Some(Key::Q),
+        0x52 => Some(Key::R),
+        0x53 => Some(Key::S),
+        0x54 => Some(Key::T),
+        0x55 => Some(Key::U),
+        0x56 => Some(Key::V),
+        0x57 => Some(Key::W),
+        0x58 => Some(Key::X),
+        0x59 => Some(Key::Y),
+        0x5A => Some(Key::Z),
+        // Numpad digits only arrive with NumLock on, otherwise Windows sends
+        // navigation keys instead. Operators and decimal are reported as text.
+        0x60 => Some(Key::Num0),
+        0x61 => Some(Key::Num1),
+        0x62 => Some(Key::Num2),
+        0x63 => Some(Key::Num3),
+        0x64 => Some(Key::Num4),
+        0x65 => Some(Key::Num5),
+        0x66 => Some(Key::Num6),
+        0x67 => Some(Key::Num7),
+        0x68 => Some(Key::Num8),
+        0x69 => Some(Key::Num9),
         _ => match VIRTUAL_KEY(wparam as u16) {
             VK_DOWN => Some(Key::ArrowDown),
             VK_LEFT => Some(Key::ArrowLeft),
@@ -304,15 +1289,262 @@ fn get_key(wparam: usize) -> Option<Key> {
     }
 }
 
-fn get_clipboard_text() -> Option<String> {
-    unsafe {
-        if OpenClipboard(HWND::default()).as_bool() {
-            let txt = GetClipboardData(CF_TEXT.0).0 as *const i8;
-            let data = Some(CStr::from_ptr(txt).to_str().ok()?.to_string());
-            CloseClipboard();
-            data
-        } else {
-            None
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{NoClipboard, TestWindow};
+    use std::{cell::Cell, rc::Rc};
+    use windows::Win32::UI::Input::KeyboardAndMouse::{VK_ADD, VK_DECIMAL, VK_SUBTRACT};
+
+    fn collector(window: &TestWindow, time: impl TimeSource + 'static) -> InputCollector {
+        InputCollector::new(
+            window.hwnd,
+            Box::new(NoClipboard),
+            Box::new(time),
+            InputOptions::default(),
+        )
+    }
+
+    fn texts(input: &RawInput) -> Vec<&str> {
+        input
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn keys(input: &RawInput) -> Vec<Key> {
+        input
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Key {
+                    key, pressed: true, ..
+                } => Some(*key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn buttons(input: &RawInput) -> Vec<bool> {
+        input
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::PointerButton { pressed, .. } => Some(*pressed),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buttons_stay_paired() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        // Pressed before the overlay saw it.
+        input.classify(WM_LBUTTONUP, 0, 0);
+        assert!(buttons(&input.collect_input()).is_empty());
+
+        // Second press of a double click, the release in between was lost.
+        input.classify(WM_LBUTTONDOWN, 0, 0);
+        input.classify(WM_LBUTTONDBLCLK, 0, 0);
+        input.classify(WM_LBUTTONUP, 0, 0);
+        input.classify(WM_LBUTTONUP, 0, 0);
+        assert_eq!(buttons(&input.collect_input()), [true, false, true, false]);
+    }
+
+    #[test]
+    fn buttons_carry_tracked_modifiers() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        // Right control is the extended variant of `VK_CONTROL`.
+        input.classify(WM_SYSKEYDOWN, VK_MENU.0 as _, 0);
+        input.classify(WM_KEYDOWN, VK_CONTROL.0 as _, 1 << 24);
+        input.classify(WM_LBUTTONDOWN, 0, 0);
+
+        let modifiers = input
+            .collect_input()
+            .events
+            .into_iter()
+            .find_map(|event| match event {
+                Event::PointerButton { modifiers, .. } => Some(modifiers),
+                _ => None,
+            });
+        assert_eq!(
+            modifiers,
+            Some(Modifiers {
+                alt: true,
+                ctrl: true,
+                shift: false,
+                mac_cmd: false,
+                command: true,
+            })
+        );
+    }
+
+    #[test]
+    fn numpad_keys_are_mapped() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        // NumLock on.
+        input.classify(WM_KEYDOWN, VK_NUMPAD0.0 as _, 0);
+        input.classify(WM_KEYDOWN, VK_NUMPAD9.0 as _, 0);
+        // Num Enter has the extended flag.
+        input.classify(WM_KEYDOWN, VK_RETURN.0 as _, 1 << 24);
+        // NumLock off, 8, 1 and decimal.
+        input.classify(WM_KEYDOWN, VK_UP.0 as _, 0);
+        input.classify(WM_KEYDOWN, VK_END.0 as _, 0);
+        input.classify(WM_KEYDOWN, VK_DELETE.0 as _, 0);
+
+        assert_eq!(
+            keys(&input.collect_input()),
+            [
+                Key::Num0,
+                Key::Num9,
+                Key::Enter,
+                Key::ArrowUp,
+                Key::End,
+                Key::Delete
+            ]
+        );
+    }
+
+    #[test]
+    fn function_keys_are_reported() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        input.classify(WM_KEYDOWN, VK_F1.0 as _, 0);
+        input.classify(WM_KEYDOWN, VK_F24.0 as _, 0);
+        input.classify(WM_KEYUP, VK_F24.0 as _, 0);
+        drop(input.collect_input());
+
+        let keys = input.function_keys();
+        assert!(keys.down[0] && keys.pressed[0] && !keys.released[0]);
+        assert!(!keys.down[23] && keys.pressed[23] && keys.released[23]);
+
+        drop(input.collect_input());
+        let keys = input.function_keys();
+        assert!(keys.down[0] && !keys.pressed[0]);
+    }
+
+    #[test]
+    fn numpad_operators_are_typed_once() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        // Game translating messages gets `WM_CHAR` after the key press.
+        input.classify(WM_KEYDOWN, VK_ADD.0 as _, 0);
+        input.classify(WM_CHAR, '+' as _, 0);
+        // Game that doesn't.
+        input.classify(WM_KEYDOWN, VK_SUBTRACT.0 as _, 0);
+        input.classify(WM_KEYDOWN, VK_DECIMAL.0 as _, 0);
+
+        let input = input.collect_input();
+        let texts = texts(&input);
+        assert_eq!(texts[..2], ["+", "-"]);
+        assert_eq!(texts.len(), 3);
+    }
+
+    #[test]
+    fn composition_result_chars_are_skipped() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        *input.ime_result.lock() = "ab".into();
+        input.classify(WM_KEYDOWN, VK_PROCESSKEY.0 as _, 0);
+        input.classify(WM_CHAR, 'a' as _, 0);
+        input.classify(WM_CHAR, 'b' as _, 0);
+
+        assert!(texts(&input.collect_input()).is_empty());
+    }
+
+    #[test]
+    fn cancelled_composition_removes_preview() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        input.classify(WM_IME_STARTCOMPOSITION, 0, 0);
+        input.update_composition(&mut input.events.lock(), "ka".into());
+        // Esc pressed, the IME ends composition without `GCS_RESULTSTR`.
+        input.classify(WM_IME_ENDCOMPOSITION, 0, 0);
+
+        assert!(matches!(
+            input.collect_input().events[..],
+            [
+                Event::CompositionStart,
+                Event::CompositionUpdate(_),
+                Event::Key {
+                    key: Key::Backspace,
+                    pressed: true,
+                    ..
+                },
+                Event::Key {
+                    key: Key::Backspace,
+                    pressed: false,
+                    ..
+                },
+                Event::CompositionEnd(_),
+            ]
+        ));
+    }
+
+    #[test]
+    fn ended_composition_without_preview_deletes_nothing() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        input.classify(WM_IME_STARTCOMPOSITION, 0, 0);
+        input.update_composition(&mut input.events.lock(), "ka".into());
+        // All composed characters deleted before ending.
+        input.update_composition(&mut input.events.lock(), String::new());
+        input.classify(WM_IME_ENDCOMPOSITION, 0, 0);
+
+        let backspaces = input
+            .collect_input()
+            .events
+            .iter()
+            .filter(|event| matches!(event, Event::Key { pressed: true, .. }))
+            .count();
+        assert_eq!(backspaces, 1);
+    }
+
+    #[test]
+    fn typed_char_matching_composition_is_kept() {
+        let window = TestWindow::new(64, 64);
+        let input = collector(&window, || 0.);
+
+        // Composition ended with "a", but its `WM_CHAR` never came before the next key press.
+        *input.ime_result.lock() = "a".into();
+        input.classify(WM_KEYDOWN, 'A' as _, 0);
+        input.classify(WM_CHAR, 'a' as _, 0);
+
+        assert_eq!(texts(&input.collect_input()), ["a"]);
+    }
+
+    #[test]
+    fn input_time_comes_from_time_source() {
+        let window = TestWindow::new(64, 64);
+        let clock = Rc::new(Cell::new(1.5));
+        let input = collector(&window, {
+            let clock = clock.clone();
+            move || clock.get()
+        });
+
+        let mut last = None;
+        for step in [0., 0.25, 0., 10.] {
+            clock.set(clock.get() + step);
+
+            let time = input.collect_input().time;
+            assert_eq!(time, Some(clock.get()));
+            assert!(last <= time);
+            last = time;
         }
     }
 }
@@ -0,0 +1,437 @@
+use egui::{Align2, Color32, Context, FontId, Id, LayerId, Order, Pos2, Rect, Stroke, Window};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::CString,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::windows::io::{AsRawHandle, FromRawHandle},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use windows::Win32::{
+    Foundation::{GetLastError, ERROR_PIPE_CONNECTED, HANDLE, PSTR},
+    Storage::FileSystem::{PIPE_ACCESS_INBOUND, PIPE_ACCESS_OUTBOUND},
+    System::{
+        Pipes::{
+            ConnectNamedPipe, CreateNamedPipeA, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+            PIPE_WAIT,
+        },
+        IO::CancelSynchronousIo,
+    },
+};
+
+use crate::sync::Mutex;
+
+/// Longest frame accepted from the companion, a longer line drops the connection.
+const MAX_LINE_LEN: usize = 4 * 1024 * 1024;
+
+/// Shape drawn on the foreground layer, coordinates are in points and colors are RGBA.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcShape {
+    Line {
+        from: [f32; 2],
+        to: [f32; 2],
+        width: f32,
+        color: [u8; 4],
+    },
+    Rect {
+        min: [f32; 2],
+        max: [f32; 2],
+        rounding: f32,
+        width: f32,
+        color: [u8; 4],
+    },
+    RectFilled {
+        min: [f32; 2],
+        max: [f32; 2],
+        rounding: f32,
+        color: [u8; 4],
+    },
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+        width: f32,
+        color: [u8; 4],
+    },
+    CircleFilled {
+        center: [f32; 2],
+        radius: f32,
+        color: [u8; 4],
+    },
+    Text {
+        pos: [f32; 2],
+        text: String,
+        size: f32,
+        color: [u8; 4],
+    },
+}
+
+/// Widget of an [`IpcWindow`]. Interactive widgets report changes with [`IpcEvent`]
+/// and show their `value` until the companion sends a new one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcWidget {
+    Label {
+        text: String,
+    },
+    Separator,
+    Button {
+        id: String,
+        text: String,
+    },
+    Checkbox {
+        id: String,
+        text: String,
+        value: bool,
+    },
+    Slider {
+        id: String,
+        text: String,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IpcWindow {
+    pub title: String,
+    pub widgets: Vec<IpcWidget>,
+}
+
+/// Everything shown by the overlay, sent by the companion as a single JSON line
+/// and replacing the previous frame.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IpcFrame {
+    #[serde(default)]
+    pub shapes: Vec<IpcShape>,
+    #[serde(default)]
+    pub windows: Vec<IpcWindow>,
+}
+
+/// Interaction sent back to the companion as JSON lines.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpcEvent {
+    Connected,
+    Clicked { id: String },
+    Toggled { id: String, value: bool },
+    Changed { id: String, value: f64 },
+}
+
+/// Renders UI described by an external companion process, keeping its logic
+/// out of the game. Frames are read from pipe `\\.\pipe\<name>.in` and events are written
+/// into `\\.\pipe\<name>.out`. A new companion can connect after the previous one exits.
+///
+/// Dropping the server disconnects the companion and joins its threads, so it has to be
+/// dropped before the module containing it is unloaded.
+pub struct IpcServer {
+    frame: Arc<Mutex<IpcFrame>>,
+    /// `None` wakes the writer thread up when the server is dropped.
+    events: Sender<Option<IpcEvent>>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl IpcServer {
+    /// Creates pipes and starts waiting for the companion on background threads.
+    pub fn start(name: &str) -> io::Result<Self> {
+        let (events, receiver) = channel();
+        let mut server = Self {
+            frame: Arc::new(Mutex::new(IpcFrame::default())),
+            events,
+            stop: Arc::new(AtomicBool::new(false)),
+            threads: vec![],
+        };
+
+        let input = format!(r"\\.\pipe\{}.in", name);
+        let output = format!(r"\\.\pipe\{}.out", name);
+
+        // Threads spawned before a failure are stopped when the server is dropped.
+        let frame = server.frame.clone();
+        let connected = server.events.clone();
+        let stop = server.stop.clone();
+        server.threads.push(
+            thread::Builder::new()
+                .name("egui-d3d11-ipc-in".into())
+                .spawn(move || read_frames(&input, &frame, &connected, &stop))?,
+        );
+
+        let stop = server.stop.clone();
+        server.threads.push(
+            thread::Builder::new()
+                .name("egui-d3d11-ipc-out".into())
+                .spawn(move || write_events(&output, receiver, &stop))?,
+        );
+
+        Ok(server)
+    }
+
+    /// Shows the last received frame. Should be called inside of the ui closure every frame.
+    pub fn show(&self, ctx: &Context) {
+        let frame = self.frame.lock().clone();
+
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("egui_d3d11_ipc")));
+        for shape in frame.shapes {
+            match shape {
+                IpcShape::Line {
+                    from,
+                    to,
+                    width,
+                    color,
+                } => {
+                    painter.line_segment([from.into(), to.into()], Stroke::new(width, rgba(color)))
+                }
+                IpcShape::Rect {
+                    min,
+                    max,
+                    rounding,
+                    width,
+                    color,
+                } => painter.rect_stroke(
+                    Rect::from_min_max(min.into(), max.into()),
+                    rounding,
+                    Stroke::new(width, rgba(color)),
+                ),
+                IpcShape::RectFilled {
+                    min,
+                    max,
+                    rounding,
+                    color,
+                } => painter.rect_filled(
+                    Rect::from_min_max(min.into(), max.into()),
+                    rounding,
+                    rgba(color),
+                ),
+                IpcShape::Circle {
+                    center,
+                    radius,
+                    width,
+                    color,
+                } => painter.circle_stroke(center.into(), radius, Stroke::new(width, rgba(color))),
+                IpcShape::CircleFilled {
+                    center,
+                    radius,
+                    color,
+                } => painter.circle_filled(center.into(), radius, rgba(color)),
+                IpcShape::Text {
+                    pos,
+                    text,
+                    size,
+                    color,
+                } => {
+                    painter.text(
+                        Pos2::from(pos),
+                        Align2::LEFT_TOP,
+                        text,
+                        FontId::proportional(size),
+                        rgba(color),
+                    );
+                }
+            }
+        }
+
+        for window in frame.windows {
+            Window::new(&window.title).show(ctx, |ui| {
+                for widget in window.widgets {
+                    let event = match widget {
+                        IpcWidget::Label { text } => {
+                            ui.label(text);
+                            None
+                        }
+                        IpcWidget::Separator => {
+                            ui.separator();
+                            None
+                        }
+                        IpcWidget::Button { id, text } => {
+                            ui.button(text).clicked().then(|| IpcEvent::Clicked { id })
+                        }
+                        IpcWidget::Checkbox {
+                            id,
+                            text,
+                            mut value,
+                        } => ui
+                            .checkbox(&mut value, text)
+                            .changed()
+                            .then(|| IpcEvent::Toggled { id, value }),
+                        IpcWidget::Slider {
+                            id,
+                            text,
+                            mut value,
+                            min,
+                            max,
+                        } => ui
+                            .add(egui::Slider::new(&mut value, min..=max).text(text))
+                            .changed()
+                            .then(|| IpcEvent::Changed { id, value }),
+                    };
+
+                    if let Some(event) = event {
+                        let _ = self.events.send(Some(event));
+                    }
+                }
+            });
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.events.send(None);
+
+        for handle in self.threads.drain(..) {
+            // Pipe calls block until a companion connects or sends data. Cancelling is repeated
+            // as the thread may not have entered the call yet when the flag was set.
+            while !handle.is_finished() {
+                unsafe { CancelSynchronousIo(HANDLE(handle.as_raw_handle() as _)) };
+                thread::sleep(Duration::from_millis(1));
+            }
+            let _ = handle.join();
+        }
+    }
+}
+
+#[inline]
+fn rgba([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Creates pipe instance and waits for a client, returns `None` if it can't be created.
+fn accept(name: &str, inbound: bool) -> Option<File> {
+    let name = CString::new(name).ok()?;
+    let mode = if inbound {
+        PIPE_ACCESS_INBOUND
+    } else {
+        PIPE_ACCESS_OUTBOUND
+    };
+
+    unsafe {
+        let handle: HANDLE = CreateNamedPipeA(
+            PSTR(name.as_ptr() as _),
+            mode,
+            // Pipes are reachable over SMB otherwise, only local companions are expected.
+            PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            64 * 1024,
+            64 * 1024,
+            0,
+            std::ptr::null(),
+        );
+        if handle.is_invalid() {
+            return None;
+        }
+
+        // Client connecting between creation and this call is reported as an error.
+        if !ConnectNamedPipe(handle, std::ptr::null_mut()).as_bool()
+            && GetLastError() != ERROR_PIPE_CONNECTED
+        {
+            drop(File::from_raw_handle(handle.0 as _));
+            return None;
+        }
+
+        Some(File::from_raw_handle(handle.0 as _))
+    }
+}
+
+fn read_frames(
+    name: &str,
+    frame: &Mutex<IpcFrame>,
+    events: &Sender<Option<IpcEvent>>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::SeqCst) {
+        let pipe = match accept(name, true) {
+            Some(pipe) => pipe,
+            None => break,
+        };
+        let _ = events.send(Some(IpcEvent::Connected));
+
+        let mut reader = BufReader::new(pipe);
+        let mut line = vec![];
+        while read_line(&mut reader, &mut line) {
+            // Malformed frames are skipped, the companion may be mid-development.
+            if let Ok(new) = serde_json::from_slice(&line) {
+                *frame.lock() = new;
+            }
+        }
+
+        // Companion exited, its UI shouldn't stay on screen.
+        *frame.lock() = IpcFrame::default();
+    }
+}
+
+/// Reads next line into `line`, returns `false` at the end of the stream, on error
+/// or if the line is longer than [`MAX_LINE_LEN`].
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> bool {
+    line.clear();
+    match reader
+        .by_ref()
+        .take(MAX_LINE_LEN as u64 + 1)
+        .read_until(b'\n', line)
+    {
+        Ok(0) | Err(_) => false,
+        // Line without the newline is either the last one or cut off by the limit.
+        Ok(_) => line.ends_with(b"\n") || line.len() <= MAX_LINE_LEN,
+    }
+}
+
+fn write_events(name: &str, events: Receiver<Option<IpcEvent>>, stop: &AtomicBool) {
+    while !stop.load(Ordering::SeqCst) {
+        let mut pipe = match accept(name, false) {
+            Some(pipe) => pipe,
+            None => break,
+        };
+
+        while let Ok(Some(event)) = events.recv() {
+            let mut line = match serde_json::to_string(&event) {
+                Ok(line) => line,
+                Err(_) => continue,
+            };
+            line.push('\n');
+
+            if pipe.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn lines_are_read_up_to_the_limit() {
+        let mut data = b"{}\n".to_vec();
+        data.extend(std::iter::repeat(b' ').take(MAX_LINE_LEN));
+        data.extend(b"\n{}");
+
+        let mut reader = Cursor::new(data);
+        let mut line = vec![];
+        assert!(read_line(&mut reader, &mut line));
+        assert_eq!(line, b"{}\n");
+        assert!(read_line(&mut reader, &mut line));
+        assert_eq!(line.len(), MAX_LINE_LEN + 1);
+        assert!(read_line(&mut reader, &mut line));
+        assert_eq!(line, b"{}");
+        assert!(!read_line(&mut reader, &mut line));
+    }
+
+    #[test]
+    fn overlong_line_drops_connection() {
+        let mut data = vec![b' '; MAX_LINE_LEN + 1];
+        data.extend(b"\n{}\n");
+
+        let mut reader = Cursor::new(data);
+        let mut line = vec![];
+        assert!(!read_line(&mut reader, &mut line));
+    }
+}
@@ -0,0 +1,251 @@
+use egui::Modifiers;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+/// Virtual key with modifiers that have to be held.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Keybind {
+    /// Win32 virtual key code, e.g. `0x2D` for `VK_INSERT`.
+    pub vk: u16,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Keybind {
+    #[inline]
+    pub fn new(vk: u16) -> Self {
+        Self {
+            vk,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    #[inline]
+    pub fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    #[inline]
+    pub fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    #[inline]
+    pub fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn from_modifiers(vk: u16, modifiers: Modifiers) -> Self {
+        Self {
+            vk,
+            ctrl: modifiers.ctrl,
+            shift: modifiers.shift,
+            alt: modifiers.alt,
+        }
+    }
+}
+
+/// Returned when binding a key that is already used by another action.
+#[derive(Debug, thiserror::Error)]
+#[error("{keybind:?} is already bound to {existing}")]
+pub struct KeybindConflict {
+    pub keybind: Keybind,
+    pub existing: String,
+}
+
+struct Binding {
+    keybind: Keybind,
+    action: Arc<dyn Fn()>,
+}
+
+/// Named actions triggered by key presses received in [`crate::DirectX11App::wnd_proc`].
+/// Keybinds fire whether the overlay is visible or not, but are suppressed while egui
+/// has keyboard focus so typing into text fields doesn't trigger them.
+#[derive(Default)]
+pub struct KeybindManager {
+    bindings: HashMap<String, Binding>,
+}
+
+impl KeybindManager {
+    /// Binds action under `name`, replacing previous action with the same name.
+    pub fn bind(
+        &mut self,
+        name: impl Into<String>,
+        keybind: Keybind,
+        action: impl Fn() + 'static,
+    ) -> Result<(), KeybindConflict> {
+        let name = name.into();
+        self.check_conflict(&name, keybind)?;
+
+        self.bindings.insert(
+            name,
+            Binding {
+                keybind,
+                action: Arc::new(action),
+            },
+        );
+        Ok(())
+    }
+
+    /// Changes key of already bound action. Returns `Ok(false)` if there's no such action.
+    pub fn rebind(&mut self, name: &str, keybind: Keybind) -> Result<bool, KeybindConflict> {
+        self.check_conflict(name, keybind)?;
+
+        match self.bindings.get_mut(name) {
+            Some(binding) => {
+                binding.keybind = keybind;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    #[inline]
+    pub fn unbind(&mut self, name: &str) -> bool {
+        self.bindings.remove(name).is_some()
+    }
+
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<Keybind> {
+        self.bindings.get(name).map(|binding| binding.keybind)
+    }
+
+    /// Returns keys of all actions, useful for persisting them.
+    pub fn keybinds(&self) -> HashMap<String, Keybind> {
+        self.bindings
+            .iter()
+            .map(|(name, binding)| (name.clone(), binding.keybind))
+            .collect()
+    }
+
+    /// Rebinds actions to previously saved keys. Unknown names are ignored,
+    /// keys conflicting with other saved or unmentioned bindings are skipped and returned.
+    /// Saved keys are validated together, so swapped keys of two actions are restored.
+    pub fn load(&mut self, keybinds: &HashMap<String, Keybind>) -> Vec<KeybindConflict> {
+        let mut loaded = keybinds
+            .iter()
+            .filter(|(name, _)| self.bindings.contains_key(name.as_str()))
+            .map(|(name, keybind)| (name.as_str(), *keybind))
+            .collect::<Vec<_>>();
+        // Which of two conflicting keys is skipped shouldn't depend on the map's order.
+        loaded.sort_by_key(|(name, _)| *name);
+
+        let mut conflicts = vec![];
+        loop {
+            let mut resolved = self
+                .bindings
+                .iter()
+                .map(|(name, binding)| (name.as_str(), binding.keybind))
+                .collect::<HashMap<_, _>>();
+            resolved.extend(loaded.iter().copied());
+
+            // Skipped entries keep their current keys, which can conflict with the rest again.
+            let conflict = loaded.iter().enumerate().find_map(|(i, (name, keybind))| {
+                resolved
+                    .iter()
+                    .find(|(other, key)| *other != name && *key == keybind)
+                    .map(|(other, _)| (i, other.to_string()))
+            });
+
+            match conflict {
+                Some((i, existing)) => {
+                    let (_, keybind) = loaded.remove(i);
+                    conflicts.push(KeybindConflict { keybind, existing });
+                }
+                None => break,
+            }
+        }
+
+        for (name, keybind) in loaded {
+            if let Some(binding) = self.bindings.get_mut(name) {
+                binding.keybind = keybind;
+            }
+        }
+        conflicts
+    }
+
+    pub(crate) fn find(&self, keybind: Keybind) -> Option<Arc<dyn Fn()>> {
+        self.bindings
+            .values()
+            .find(|binding| binding.keybind == keybind)
+            .map(|binding| binding.action.clone())
+    }
+
+    fn check_conflict(&self, name: &str, keybind: Keybind) -> Result<(), KeybindConflict> {
+        match self
+            .bindings
+            .iter()
+            .find(|(other, binding)| other.as_str() != name && binding.keybind == keybind)
+        {
+            Some((existing, _)) => Err(KeybindConflict {
+                keybind,
+                existing: existing.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> KeybindManager {
+        let mut manager = KeybindManager::default();
+        manager.bind("a", Keybind::new(0x41), || {}).unwrap();
+        manager.bind("b", Keybind::new(0x42), || {}).unwrap();
+        manager.bind("c", Keybind::new(0x43), || {}).unwrap();
+        manager
+    }
+
+    #[test]
+    fn load_restores_swapped_keys() {
+        let mut manager = manager();
+        let saved = HashMap::from([
+            ("a".to_string(), Keybind::new(0x42)),
+            ("b".to_string(), Keybind::new(0x41)),
+        ]);
+
+        assert!(manager.load(&saved).is_empty());
+        assert_eq!(manager.get("a"), Some(Keybind::new(0x42)));
+        assert_eq!(manager.get("b"), Some(Keybind::new(0x41)));
+        assert_eq!(manager.get("c"), Some(Keybind::new(0x43)));
+    }
+
+    #[test]
+    fn load_skips_keys_of_unmentioned_bindings() {
+        let mut manager = manager();
+        let saved = HashMap::from([
+            ("a".to_string(), Keybind::new(0x43)),
+            ("unknown".to_string(), Keybind::new(0x44)),
+        ]);
+
+        let conflicts = manager.load(&saved);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].existing, "c");
+        assert_eq!(manager.get("a"), Some(Keybind::new(0x41)));
+    }
+
+    #[test]
+    fn load_skips_conflicts_within_saved_keys() {
+        let mut manager = manager();
+        let saved = HashMap::from([
+            ("a".to_string(), Keybind::new(0x44)),
+            ("b".to_string(), Keybind::new(0x44)),
+        ]);
+
+        let conflicts = manager.load(&saved);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(manager.get("a"), Some(Keybind::new(0x41)));
+        assert_eq!(manager.get("b"), Some(Keybind::new(0x44)));
+    }
+}
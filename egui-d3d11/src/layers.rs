@@ -0,0 +1,306 @@
+use egui::{ClippedMesh, Context, Event, FullOutput, RawInput, TextureId, TexturesDelta};
+use std::{
+    iter::once,
+    sync::atomic::{AtomicU16, Ordering},
+};
+
+use crate::WantsInput;
+
+/// How often an additional context runs its ui closure and gets tessellated again.
+/// Between updates its last output is drawn again. All contexts also update whenever
+/// input arrived or egui requested a repaint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cadence {
+    EveryFrame,
+    /// Updates every `n`-th frame.
+    EveryNth(u32),
+    /// Only updates on input, repaint requests and [`crate::DirectX11App::request_update`].
+    OnDemand,
+}
+
+/// Handle of a context added with [`crate::DirectX11App::add_context`]
+/// or [`crate::DirectX11App::add_texture_panel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ContextId(u16);
+
+impl ContextId {
+    /// The app's own context.
+    pub const MAIN: Self = Self(0);
+
+    /// Returns id shared by no other context or texture panel.
+    pub(crate) fn next() -> Self {
+        // Zero is kept for the app's own context.
+        static NEXT: AtomicU16 = AtomicU16::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Managed textures of each context start from zero,
+    /// so they're moved into a range of their own in the shared allocator.
+    #[inline]
+    fn remap(self, id: TextureId) -> TextureId {
+        match id {
+            TextureId::Managed(id) => TextureId::Managed((self.0 as u64) << 48 | id),
+            user => user,
+        }
+    }
+
+    pub(crate) fn remap_delta(self, delta: TexturesDelta) -> TexturesDelta {
+        TexturesDelta {
+            set: delta
+                .set
+                .into_iter()
+                .map(|(tex, image)| (self.remap(tex), image))
+                .collect(),
+            free: delta.free.into_iter().map(|tex| self.remap(tex)).collect(),
+        }
+    }
+
+    pub(crate) fn remap_meshes(self, meshes: &mut [ClippedMesh]) {
+        for ClippedMesh(_, mesh) in meshes {
+            mesh.texture_id = self.remap(mesh.texture_id);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn owns(self, id: &TextureId) -> bool {
+        matches!(id, TextureId::Managed(id) if id >> 48 == self.0 as u64)
+    }
+}
+
+struct ContextLayer {
+    id: ContextId,
+    order: i32,
+    cadence: Cadence,
+    ctx: Context,
+    ui: Box<dyn FnMut(&Context)>,
+    meshes: Vec<ClippedMesh>,
+    frames: u32,
+    needs_update: bool,
+    input: RawInput,
+    wants: WantsInput,
+}
+
+impl ContextLayer {
+    fn should_update(&mut self, input: &RawInput) -> bool {
+        self.frames = self.frames.wrapping_add(1);
+
+        let scheduled = match self.cadence {
+            Cadence::EveryFrame => true,
+            Cadence::EveryNth(n) => self.frames % n.max(1) == 0,
+            Cadence::OnDemand => false,
+        };
+        scheduled || std::mem::take(&mut self.needs_update) || !input.events.is_empty()
+    }
+}
+
+/// Which contexts an input event is delivered to.
+enum Route {
+    /// Delivered from the topmost context down, until one that wants pointer input.
+    Pointer,
+    /// Delivered from the topmost context down, until one that has keyboard focus.
+    Keyboard,
+    /// Delivered to every context.
+    All,
+}
+
+impl Route {
+    fn of(event: &Event) -> Self {
+        match event {
+            Event::PointerMoved(_)
+            | Event::PointerButton { .. }
+            | Event::Scroll(_)
+            | Event::Zoom(_)
+            | Event::Touch { .. } => Self::Pointer,
+            Event::PointerGone => Self::All,
+            _ => Self::Keyboard,
+        }
+    }
+
+    #[inline]
+    fn is_consumed_by(&self, wants: WantsInput) -> bool {
+        match self {
+            Self::Pointer => wants.pointer,
+            Self::Keyboard => wants.keyboard,
+            Self::All => false,
+        }
+    }
+}
+
+/// Contexts drawn besides the app's own one, which has order `0`.
+#[derive(Default)]
+pub(crate) struct ContextLayers {
+    layers: Vec<ContextLayer>,
+    main_wants: WantsInput,
+}
+
+impl ContextLayers {
+    pub fn add(&mut self, order: i32, cadence: Cadence, ui: Box<dyn FnMut(&Context)>) -> ContextId {
+        let id = ContextId::next();
+
+        self.layers.push(ContextLayer {
+            id,
+            order,
+            cadence,
+            ctx: Context::default(),
+            ui,
+            meshes: vec![],
+            frames: 0,
+            needs_update: true,
+            input: RawInput::default(),
+            wants: WantsInput::default(),
+        });
+        // Stable, so layers with the same order are drawn in the order they were added.
+        self.layers.sort_by_key(|layer| layer.order);
+        id
+    }
+
+    pub fn remove(&mut self, id: ContextId) -> bool {
+        let len = self.layers.len();
+        self.layers.retain(|layer| layer.id != id);
+        self.layers.len() != len
+    }
+
+    pub fn context(&self, id: ContextId) -> Option<Context> {
+        self.layers
+            .iter()
+            .find(|layer| layer.id == id)
+            .map(|layer| layer.ctx.clone())
+    }
+
+    pub fn request_update(&mut self, id: ContextId) {
+        if let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == id) {
+            layer.needs_update = true;
+        }
+    }
+
+    /// Returns what context wanted at the end of its last update.
+    pub fn wants(&self, id: ContextId) -> Option<WantsInput> {
+        if id == ContextId::MAIN {
+            return Some(self.main_wants);
+        }
+        self.layers
+            .iter()
+            .find(|layer| layer.id == id)
+            .map(|layer| layer.wants)
+    }
+
+    #[inline]
+    pub fn set_main_wants(&mut self, wants: WantsInput) {
+        self.main_wants = wants;
+    }
+
+    /// Returns what any of the additional contexts wants.
+    pub fn any_wants(&self) -> WantsInput {
+        self.layers
+            .iter()
+            .fold(WantsInput::default(), |acc, layer| WantsInput {
+                pointer: acc.pointer || layer.wants.pointer,
+                keyboard: acc.keyboard || layer.wants.keyboard,
+            })
+    }
+
+    /// Indices of layers from the topmost one, `None` being the app's own context.
+    fn top_down(&self) -> impl Iterator<Item = Option<usize>> {
+        let split = self.layers.partition_point(|layer| layer.order < 0);
+        (split..self.layers.len())
+            .rev()
+            .map(Some)
+            .chain(once(None))
+            .chain((0..split).rev().map(Some))
+    }
+
+    /// Splits events between contexts in z-order, based on what they wanted last frame.
+    /// Each event stops at the first context that consumes it. Returns input of the app's
+    /// own context, others are kept until [`Self::update`].
+    pub fn route(&mut self, mut input: RawInput) -> RawInput {
+        if self.layers.is_empty() {
+            return input;
+        }
+
+        let events = std::mem::take(&mut input.events);
+        for layer in &mut self.layers {
+            layer.input = RawInput {
+                hovered_files: vec![],
+                dropped_files: vec![],
+                ..input.clone()
+            };
+        }
+
+        let order: Vec<_> = self.top_down().collect();
+        for event in events {
+            let route = Route::of(&event);
+            for &target in &order {
+                let (events, wants) = match target {
+                    Some(i) => {
+                        let layer = &mut self.layers[i];
+                        let wants = layer.wants;
+                        (&mut layer.input.events, wants)
+                    }
+                    None => (&mut input.events, self.main_wants),
+                };
+                events.push(event.clone());
+
+                if route.is_consumed_by(wants) {
+                    break;
+                }
+            }
+        }
+
+        input
+    }
+
+    /// Runs contexts that are due and returns their texture changes, with ids remapped.
+    pub fn update(&mut self) -> TexturesDelta {
+        let mut delta = TexturesDelta::default();
+
+        for layer in &mut self.layers {
+            let input = std::mem::take(&mut layer.input);
+            if !layer.should_update(&input) {
+                continue;
+            }
+
+            let ui = &mut layer.ui;
+            let FullOutput {
+                shapes,
+                textures_delta,
+                needs_repaint,
+                ..
+            } = layer.ctx.run(input, |ctx| ui(ctx));
+            layer.needs_update = needs_repaint;
+
+            let ctx = &layer.ctx;
+            layer.wants = WantsInput {
+                pointer: ctx.is_pointer_over_area()
+                    || ctx.is_using_pointer()
+                    || ctx.wants_pointer_input(),
+                keyboard: ctx.wants_keyboard_input(),
+            };
+
+            layer.meshes = layer.ctx.tessellate(shapes);
+            layer.id.remap_meshes(&mut layer.meshes);
+
+            let remapped = layer.id.remap_delta(textures_delta);
+            delta.set.extend(remapped.set);
+            delta.free.extend(remapped.free);
+        }
+
+        delta
+    }
+
+    /// Returns meshes of layers below and above the app's own context.
+    pub fn meshes(&self) -> (Vec<ClippedMesh>, Vec<ClippedMesh>) {
+        let mut below = vec![];
+        let mut above = vec![];
+
+        for layer in &self.layers {
+            let target = if layer.order < 0 {
+                &mut below
+            } else {
+                &mut above
+            };
+            target.extend(layer.meshes.iter().cloned());
+        }
+
+        (below, above)
+    }
+}
@@ -1,10 +1,14 @@
+/// Unwraps result of a call or reports [`ErrorCode`] variant `$code` to the error sink and panics.
 /// This macros allows to hide panicing messages in output binary when feature `no-msgs` is present.
 macro_rules! expect {
-    ($val:expr, $msg:expr) => {
-        if cfg!(feature = "no-msgs") {
-            $val.unwrap()
-        } else {
-            $val.expect($msg)
+    ($val:expr, $code:ident, $msg:expr) => {
+        match crate::error::Checked::check($val) {
+            Ok(val) => val,
+            Err(hresult) => crate::error::fail(
+                crate::ErrorCode::$code,
+                hresult,
+                if cfg!(feature = "no-msgs") { "" } else { $msg },
+            ),
         }
     };
 }
@@ -16,13 +20,162 @@ macro_rules! c_str {
     };
 }
 
+/// Opens profiling scope that lasts until the end of the current block.
+/// Expands to nothing unless `profiling` or `tracy` feature is present.
+macro_rules! profile_scope {
+    ($name:expr) => {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!($name);
+        #[cfg(feature = "tracy")]
+        let _tracy_span = tracy_client::Span::new($name, "", file!(), line!(), 0);
+    };
+}
+
+mod sync;
+pub use sync::MutexGuard;
+
+mod error;
+pub use error::{set_error_sink, ErrorCode, ErrorSink};
+
 mod app;
 pub use app::*;
 
-mod input;
-pub use input::InputResult;
+mod debug;
+pub use debug::{debug_window, MemoryUsage, RenderStats};
+
+mod builder;
+pub use builder::DirectX11AppBuilder;
+
+mod cell;
+pub use cell::{AppCell, AppGuard};
 
 mod backup;
+pub use backup::{BackupLevel, BackupSlots, ClearStages};
+
+mod constants;
+pub use constants::{FrameConstants, FRAME_CONSTANTS_SLOT};
+
+mod clipboard;
+pub use clipboard::{ClipboardProvider, Win32Clipboard};
+
+mod gamepad;
+pub use gamepad::GamepadButtons;
+
+mod keybind;
+pub use keybind::{Keybind, KeybindConflict, KeybindManager};
+
+mod input;
+pub use input::{
+    BlockReason, ExtraButtons, FunctionKeys, InputPolicy, InputResult, MessageInfo, MessageKind,
+    WantsInput,
+};
+
+mod texture;
+pub use texture::{AllocatedTexture, TextureAllocator};
+
+mod fonts;
+pub use fonts::load_system_font;
+
+mod theme;
+pub use theme::Theme;
+
+mod platform;
+pub use platform::PlatformOutputHandler;
+
+mod layers;
+pub use layers::{Cadence, ContextId};
+
+mod panel;
+
+mod dds;
+pub use dds::DdsError;
+
+mod loaders;
+pub use loaders::{forget_image, include_image_bytes, install_image_loaders, load_image};
+
+mod time;
+pub use time::{PerformanceCounter, TimeSource};
+
+mod world;
+pub use world::WorldPainter;
+
+mod overlay;
+pub use overlay::{OverlayLayer, OverlayPainter};
+
+mod widgets;
+pub use widgets::{fps_counter, frame_time_plot, FrameHistory, Watermark};
+
+#[cfg(feature = "record")]
+mod record;
+#[cfg(feature = "record")]
+pub use record::{InputRecorder, InputReplayer, RecordedFrame};
+
+#[cfg(feature = "logger")]
+mod logger;
+#[cfg(feature = "logger")]
+pub use logger::{LogRecord, LogSink, LogViewer};
+
+#[cfg(feature = "profiling")]
+mod profiling;
+#[cfg(feature = "profiling")]
+pub use profiling::profiler_window;
+
+#[cfg(feature = "video")]
+mod video;
+#[cfg(feature = "video")]
+pub use video::VideoPlayer;
+
+#[cfg(any(feature = "external", feature = "composition"))]
+mod swapchain;
+
+#[cfg(feature = "external")]
+mod external;
+#[cfg(feature = "external")]
+pub use external::ExternalOverlay;
+
+#[cfg(feature = "composition")]
+mod composition;
+#[cfg(feature = "composition")]
+pub use composition::CompositionOverlay;
+
+#[cfg(feature = "d3d10")]
+mod d3d10;
+#[cfg(feature = "d3d10")]
+pub use d3d10::DirectX10App;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "scripting")]
+mod script;
+#[cfg(feature = "scripting")]
+pub use script::ScriptPanel;
+
+#[cfg(feature = "plugins")]
+mod plugin;
+#[cfg(feature = "plugins")]
+pub use plugin::{
+    HostApi, PluginDesc, PluginEntry, PluginError, PluginHost, PLUGIN_ABI_VERSION, PLUGIN_ENTRY,
+};
+
+#[cfg(feature = "ipc")]
+mod ipc;
+#[cfg(feature = "ipc")]
+pub use ipc::{IpcEvent, IpcFrame, IpcServer, IpcShape, IpcWidget, IpcWindow};
+
+#[cfg(feature = "profiles")]
+mod profile;
+#[cfg(feature = "profiles")]
+pub use profile::ProfileManager;
+
+mod cursor;
+mod depth;
+mod drop;
 mod mesh;
+mod pool;
 mod shader;
-mod texture;
+mod stream;
+mod wic;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,74 @@
+use egui::{Context, Id, TextureHandle};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::{sync::Mutex, wic};
+
+/// Decoded images and registered bytes shared through egui's memory.
+#[derive(Default)]
+struct ImageLoaders {
+    bytes: Mutex<HashMap<String, Arc<[u8]>>>,
+    /// Failed loads are cached as `None` so they're not retried every frame.
+    cache: Mutex<HashMap<String, Option<TextureHandle>>>,
+}
+
+impl ImageLoaders {
+    fn load(&self, ctx: &Context, uri: &str) -> Option<TextureHandle> {
+        if let Some(cached) = self.cache.lock().get(uri) {
+            return cached.clone();
+        }
+
+        let image = if let Some(name) = uri.strip_prefix("bytes://") {
+            let bytes = self.bytes.lock().get(name).cloned();
+            bytes.and_then(|bytes| wic::decode_bytes(&bytes).ok())
+        } else {
+            let path = uri.strip_prefix("file://").unwrap_or(uri);
+            wic::decode_file(Path::new(path)).ok()
+        };
+
+        let texture = image.map(|image| ctx.load_texture(uri, image));
+        self.cache.lock().insert(uri.to_owned(), texture.clone());
+        texture
+    }
+}
+
+#[inline]
+fn loaders_id() -> Id {
+    Id::new("egui_d3d11_image_loaders")
+}
+
+fn get_loaders(ctx: &Context) -> Option<Arc<ImageLoaders>> {
+    ctx.memory().data.get_temp(loaders_id())
+}
+
+/// Enables [`load_image`] for the context. Images are decoded with Windows Imaging Component
+/// and uploaded through egui's texture manager, so they're freed with the context.
+/// egui 0.17 doesn't have `Image::from_uri` yet, this is the closest equivalent.
+pub fn install_image_loaders(ctx: &Context) {
+    if get_loaders(ctx).is_none() {
+        ctx.memory()
+            .data
+            .insert_temp(loaders_id(), Arc::new(ImageLoaders::default()));
+    }
+}
+
+/// Registers encoded image that can be loaded with `bytes://{name}` uri,
+/// useful with `include_bytes!`. Does nothing if loaders weren't installed.
+pub fn include_image_bytes(ctx: &Context, name: impl Into<String>, bytes: impl Into<Arc<[u8]>>) {
+    if let Some(loaders) = get_loaders(ctx) {
+        loaders.bytes.lock().insert(name.into(), bytes.into());
+    }
+}
+
+/// Returns texture of image at `uri`, decoding it on the first call.
+/// Supports `file://` and `bytes://` schemes, uri without scheme is treated as a file path.
+/// Returns `None` if the image can't be loaded or loaders weren't installed.
+pub fn load_image(ctx: &Context, uri: &str) -> Option<TextureHandle> {
+    get_loaders(ctx)?.load(ctx, uri)
+}
+
+/// Drops cached texture of `uri` so it's loaded again on the next call.
+pub fn forget_image(ctx: &Context, uri: &str) {
+    if let Some(loaders) = get_loaders(ctx) {
+        loaders.cache.lock().remove(uri);
+    }
+}
@@ -0,0 +1,165 @@
+use egui::{Color32, ComboBox, Context, RichText, ScrollArea, TextEdit, Ui};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::{collections::VecDeque, time::Instant};
+
+use crate::sync::{Mutex, MutexGuard};
+
+/// Single captured `log` record.
+#[derive(Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// Seconds since creation of the sink.
+    pub time: f64,
+}
+
+/// `log` implementation that keeps last records in memory so they can be displayed with [`LogViewer`].
+pub struct LogSink {
+    records: Mutex<VecDeque<LogRecord>>,
+    capacity: usize,
+    level: LevelFilter,
+    start: Instant,
+}
+
+impl LogSink {
+    pub fn new(level: LevelFilter, capacity: usize) -> Self {
+        Self {
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+            start: Instant::now(),
+            capacity,
+            level,
+        }
+    }
+
+    /// Creates sink and installs it as global logger.
+    pub fn install(level: LevelFilter, capacity: usize) -> Result<&'static Self, SetLoggerError> {
+        let sink = Box::leak(Box::new(Self::new(level, capacity)));
+        log::set_logger(sink)?;
+        log::set_max_level(level);
+        Ok(sink)
+    }
+
+    #[inline]
+    pub fn records(&self) -> MutexGuard<VecDeque<LogRecord>> {
+        self.records.lock()
+    }
+
+    #[inline]
+    pub fn clear(&self) {
+        self.records.lock().clear();
+    }
+}
+
+impl Log for LogSink {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let lock = &mut *self.records.lock();
+        if lock.len() == self.capacity {
+            lock.pop_front();
+        }
+
+        lock.push_back(LogRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            time: self.start.elapsed().as_secs_f64(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Scrollable console displaying records captured by [`LogSink`].
+pub struct LogViewer {
+    filter: String,
+    level: LevelFilter,
+    auto_scroll: bool,
+}
+
+impl Default for LogViewer {
+    fn default() -> Self {
+        Self {
+            filter: String::new(),
+            level: LevelFilter::Trace,
+            auto_scroll: true,
+        }
+    }
+}
+
+impl LogViewer {
+    /// Shows viewer inside of its own window.
+    pub fn window(&mut self, ctx: &Context, sink: &LogSink) {
+        egui::Window::new("Log")
+            .default_size([520., 260.])
+            .show(ctx, |ui| self.ui(ui, sink));
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, sink: &LogSink) {
+        ui.horizontal(|ui| {
+            ComboBox::from_id_source("egui_d3d11_log_level")
+                .selected_text(self.level.to_string())
+                .show_ui(ui, |ui| {
+                    for level in LevelFilter::iter() {
+                        ui.selectable_value(&mut self.level, level, level.to_string());
+                    }
+                });
+
+            ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter"));
+            ui.checkbox(&mut self.auto_scroll, "Auto scroll");
+
+            if ui.button("Clear").clicked() {
+                sink.clear();
+            }
+        });
+        ui.separator();
+
+        let filter = self.filter.to_lowercase();
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for record in sink.records().iter() {
+                    if record.level > self.level {
+                        continue;
+                    }
+
+                    if !filter.is_empty()
+                        && !record.message.to_lowercase().contains(&filter)
+                        && !record.target.to_lowercase().contains(&filter)
+                    {
+                        continue;
+                    }
+
+                    ui.label(
+                        RichText::new(format!(
+                            "[{:>9.3}] {:<5} {}: {}",
+                            record.time, record.level, record.target, record.message
+                        ))
+                        .monospace()
+                        .color(level_color(record.level)),
+                    );
+                }
+
+                if self.auto_scroll {
+                    ui.scroll_to_cursor(egui::Align::BOTTOM);
+                }
+            });
+    }
+}
+
+fn level_color(level: Level) -> Color32 {
+    match level {
+        Level::Error => Color32::LIGHT_RED,
+        Level::Warn => Color32::YELLOW,
+        Level::Info => Color32::LIGHT_GRAY,
+        Level::Debug => Color32::LIGHT_BLUE,
+        Level::Trace => Color32::GRAY,
+    }
+}
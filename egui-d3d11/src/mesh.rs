@@ -1,9 +1,5 @@
-use egui::{epaint::Vertex, ClippedMesh, Pos2, Rect, Rgba, TextureId};
+use egui::{epaint::Vertex, ClippedMesh, Pos2, Rect, Rgba, TextureId, Vec2};
 use std::mem::size_of;
-use windows::Win32::Graphics::Direct3D11::{
-    ID3D11Buffer, ID3D11Device, D3D11_BIND_INDEX_BUFFER, D3D11_BIND_VERTEX_BUFFER,
-    D3D11_BUFFER_DESC, D3D11_SUBRESOURCE_DATA, D3D11_USAGE_DEFAULT,
-};
 
 /// Egui's [`egui::epaint::Vertex`] uses sRGB colors.
 /// I can't be asked to make them work out of the box with hlsl.
@@ -38,100 +34,219 @@ impl GpuVertex {
     }
 }
 
-#[repr(C)]
+/// Mesh keeping egui's vertices, they're converted to [`GpuVertex`]
+/// while being written straight into the mapped vertex buffer.
 pub struct GpuMesh {
-    pub vertices: Vec<GpuVertex>,
+    pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
     pub tex_id: TextureId,
     pub rect: Rect,
 }
 
+impl GpuMesh {
+    /// Returns amount of video memory used by vertices and indices of the mesh.
+    #[inline]
+    pub fn byte_size(&self) -> usize {
+        self.vertices.len() * size_of::<GpuVertex>() + self.indices.len() * size_of::<u32>()
+    }
+
+    /// Converts vertices for the shader, positions are mapped from points
+    /// of a screen with `screen_size` to directx coords which looks like this.
+    /// (-1, 1) ============ (1 , 1)
+    /// ||                        ||
+    /// ||         (0, 0)         ||
+    /// ||                        ||
+    /// (-1,-1) ============ (1 ,-1)
+    pub fn gpu_vertices(&self, screen_size: Pos2) -> impl ExactSizeIterator<Item = GpuVertex> + '_ {
+        let half = Vec2::new(screen_size.x / 2., screen_size.y / 2.);
+        let convert = if self.tex_id == TextureId::default() {
+            GpuVertex::new_alpha
+        } else {
+            GpuVertex::new_rgba
+        };
+
+        self.vertices.iter().map(move |v| {
+            let mut vertex = convert(*v);
+            vertex.pos.x = (vertex.pos.x - half.x) / half.x;
+            vertex.pos.y = (vertex.pos.y - half.y) / -half.y;
+            vertex
+        })
+    }
+
+    /// Appends triangles of another mesh, its indices are offset past the current vertices.
+    fn append(&mut self, other: GpuMesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices
+            .extend(other.indices.into_iter().map(|i| i + offset));
+    }
+}
+
 impl From<ClippedMesh> for GpuMesh {
     #[inline]
-    fn from(cm: ClippedMesh) -> Self {
+    fn from(ClippedMesh(rect, mesh): ClippedMesh) -> Self {
         Self {
-            vertices: if cm.1.texture_id == TextureId::default() {
-                cm.1.vertices
-                    .into_iter()
-                    .map(GpuVertex::new_alpha)
-                    .collect()
-            } else {
-                cm.1.vertices.into_iter().map(GpuVertex::new_rgba).collect()
-            },
-            tex_id: cm.1.texture_id,
-            indices: cm.1.indices,
-            rect: cm.0,
+            vertices: mesh.vertices,
+            indices: mesh.indices,
+            tex_id: mesh.texture_id,
+            rect,
         }
     }
 }
 
-pub struct MeshBuffers {
-    pub vertex: ID3D11Buffer,
-    pub index: ID3D11Buffer,
-}
+/// Converts meshes that can be drawn and are visible inside of `screen`,
+/// returns them with the number of dropped ones. Consecutive meshes with the same
+/// texture and clip rect are merged so they're drawn with a single call.
+pub fn convert_meshes(clipped: Vec<ClippedMesh>, screen: Rect) -> (Vec<GpuMesh>, usize) {
+    // Vertices would be divided by zero while being converted.
+    if !has_area(screen.size()) {
+        return (vec![], clipped.len());
+    }
 
-impl MeshBuffers {
-    pub fn new(device: &ID3D11Device, mesh: &GpuMesh) -> Self {
-        Self {
-            vertex: Self::create_vertex_buffer(device, mesh),
-            index: Self::create_index_buffer(device, mesh),
+    let mut culled = 0;
+    let mut meshes: Vec<GpuMesh> = Vec::with_capacity(clipped.len());
+
+    for mesh in clipped {
+        if !is_drawable(&mesh) || !mesh.0.intersects(screen) {
+            culled += 1;
+            continue;
+        }
+
+        let mesh = GpuMesh::from(mesh);
+        match meshes.last_mut() {
+            Some(last) if last.tex_id == mesh.tex_id && last.rect == mesh.rect => last.append(mesh),
+            _ => meshes.push(mesh),
         }
     }
 
-    fn create_vertex_buffer(device: &ID3D11Device, mesh: &GpuMesh) -> ID3D11Buffer {
-        let buffer_desc = D3D11_BUFFER_DESC {
-            ByteWidth: (mesh.vertices.len() * size_of::<GpuVertex>()) as _,
-            Usage: D3D11_USAGE_DEFAULT,
-            BindFlags: D3D11_BIND_VERTEX_BUFFER.0,
-            CPUAccessFlags: 0,
-            MiscFlags: 0,
-            StructureByteStride: 0,
-        };
+    (meshes, culled)
+}
 
-        let init_data = D3D11_SUBRESOURCE_DATA {
-            pSysMem: mesh.vertices.as_ptr() as _,
-            SysMemPitch: 0,
-            SysMemSlicePitch: 0,
-        };
+/// Returns `false` for screens nothing can be drawn on, e.g. a minimized window's client area.
+#[inline]
+pub fn has_area(size: Vec2) -> bool {
+    // Negated comparisons would let NaN sizes through.
+    size.x >= 1. && size.y >= 1.
+}
+
+/// Mesh is skipped if it has no triangles or its clip rect has no area.
+#[inline]
+fn is_drawable(ClippedMesh(rect, mesh): &ClippedMesh) -> bool {
+    // Negated so NaN rects are culled as well.
+    let empty_rect = !(rect.width() > 0. && rect.height() > 0.);
 
-        unsafe {
-            expect!(
-                device.CreateBuffer(&buffer_desc, &init_data),
-                "Failed to create mesh's vertex buffer"
-            )
+    !empty_rect
+        && !mesh.vertices.is_empty()
+        && !mesh.indices.is_empty()
+        && mesh.indices.len() % 3 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{epaint::Mesh, Color32};
+
+    fn quads(count: usize) -> Vec<ClippedMesh> {
+        (0..count)
+            .map(|i| {
+                let rect = Rect::from_min_size(Pos2::new(i as f32 * 10., 5.), Vec2::splat(8.));
+                let mut mesh = Mesh::default();
+                mesh.add_colored_rect(rect, Color32::WHITE);
+                ClippedMesh(Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.)), mesh)
+            })
+            .collect()
+    }
+
+    fn screen(width: f32, height: f32) -> Rect {
+        Rect::from_min_size(Pos2::ZERO, Vec2::new(width, height))
+    }
+
+    #[test]
+    fn degenerate_sizes_have_no_area() {
+        assert!(!has_area(Vec2::ZERO));
+        assert!(!has_area(Vec2::new(0., 600.)));
+        assert!(!has_area(Vec2::new(800., 0.)));
+        assert!(!has_area(Vec2::new(f32::NAN, 600.)));
+        assert!(!has_area(Vec2::new(800., f32::NAN)));
+        assert!(!has_area(Vec2::splat(0.5)));
+        assert!(!has_area(Vec2::new(-800., -600.)));
+        assert!(has_area(Vec2::splat(1.)));
+        assert!(has_area(Vec2::new(800., 600.)));
+    }
+
+    #[test]
+    fn degenerate_screens_cull_everything() {
+        for screen in [
+            screen(0., 0.),
+            screen(0., 600.),
+            screen(800., 0.),
+            screen(f32::NAN, f32::NAN),
+            screen(800., f32::NAN),
+            screen(0.5, 0.5),
+        ] {
+            let (meshes, culled) = convert_meshes(quads(3), screen);
+            assert!(meshes.is_empty(), "meshes drawn on {:?}", screen);
+            assert_eq!(culled, 3);
         }
     }
 
-    fn create_index_buffer(device: &ID3D11Device, mesh: &GpuMesh) -> ID3D11Buffer {
-        let buffer_desc = D3D11_BUFFER_DESC {
-            ByteWidth: (mesh.indices.len() * size_of::<u32>()) as _,
-            Usage: D3D11_USAGE_DEFAULT,
-            BindFlags: D3D11_BIND_INDEX_BUFFER.0,
-            CPUAccessFlags: 0,
-            MiscFlags: 0,
-            StructureByteStride: 0,
-        };
+    #[test]
+    fn valid_screen_produces_finite_vertices() {
+        let screen = screen(1., 1.);
+        let (meshes, culled) = convert_meshes(quads(3), screen);
+        assert_eq!(culled, 0);
 
-        let init_data = D3D11_SUBRESOURCE_DATA {
-            pSysMem: mesh.indices.as_ptr() as _,
-            SysMemPitch: 0,
-            SysMemSlicePitch: 0,
-        };
+        for mesh in &meshes {
+            for vertex in mesh.gpu_vertices(screen.max) {
+                assert!(vertex.pos.x.is_finite() && vertex.pos.y.is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn adjacent_meshes_are_merged() {
+        let clipped = quads(3);
+        let first = clipped[0].1.indices.clone();
+
+        let (meshes, culled) = convert_meshes(clipped, screen(800., 600.));
+        assert_eq!(culled, 0);
+        assert_eq!(meshes.len(), 1);
 
-        unsafe {
-            expect!(
-                device.CreateBuffer(&buffer_desc, &init_data),
-                "Failed to create mesh's index buffer"
-            )
+        let mesh = &meshes[0];
+        assert_eq!(mesh.vertices.len(), 12);
+        assert_eq!(mesh.indices.len(), first.len() * 3);
+        for (i, chunk) in mesh.indices.chunks(first.len()).enumerate() {
+            let offset = i as u32 * 4;
+            assert!(chunk.iter().zip(&first).all(|(a, b)| *a == b + offset));
         }
     }
-}
 
-#[inline]
-pub fn convert_meshes(clipped: Vec<ClippedMesh>) -> Vec<GpuMesh> {
-    clipped
-        .into_iter()
-        .filter(|m| !m.1.indices.is_empty() && m.1.indices.len() % 3 == 0)
-        .map(GpuMesh::from)
-        .collect()
+    #[test]
+    fn different_rects_and_textures_are_not_merged() {
+        let mut clipped = quads(3);
+        clipped[1].0 = Rect::from_min_size(Pos2::ZERO, Vec2::splat(50.));
+        clipped[2].1.texture_id = TextureId::User(1);
+
+        let (meshes, culled) = convert_meshes(clipped, screen(800., 600.));
+        assert_eq!(culled, 0);
+        assert_eq!(meshes.len(), 3);
+        for mesh in &meshes {
+            assert_eq!(mesh.vertices.len(), 4);
+            assert!(mesh.indices.iter().all(|&i| i < 4));
+        }
+    }
+
+    #[test]
+    fn offscreen_and_degenerate_meshes_are_culled() {
+        let mut clipped = quads(5);
+        clipped[1].0 = Rect::from_min_size(Pos2::new(1000., 0.), Vec2::splat(100.));
+        clipped[2].1.indices.clear();
+        clipped[3].1.indices.truncate(4);
+        clipped[4].0 = Rect::from_min_size(Pos2::ZERO, Vec2::new(0., 100.));
+
+        let (meshes, culled) = convert_meshes(clipped, screen(800., 600.));
+        assert_eq!(culled, 4);
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].vertices.len(), 4);
+    }
 }
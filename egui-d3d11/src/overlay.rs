@@ -0,0 +1,157 @@
+use egui::{Align2, Color32, Context, FontId, Id, LayerId, Order, Pos2, Rect, Shape, Stroke};
+
+/// Layer that [`OverlayPainter`] draws on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayLayer {
+    /// Below all egui windows.
+    Background,
+    /// Above all egui windows.
+    Foreground,
+}
+
+impl OverlayLayer {
+    fn layer_id(self) -> LayerId {
+        match self {
+            Self::Background => {
+                LayerId::new(Order::Background, Id::new("egui_d3d11_overlay_background"))
+            }
+            Self::Foreground => {
+                LayerId::new(Order::Foreground, Id::new("egui_d3d11_overlay_foreground"))
+            }
+        }
+    }
+}
+
+struct OverlayText {
+    pos: Pos2,
+    anchor: Align2,
+    text: String,
+    font: FontId,
+    color: Color32,
+}
+
+/// Shapes queued with [`crate::DirectX11App::draw_overlay`], painted once during the next present.
+#[derive(Default)]
+pub(crate) struct OverlayShapes {
+    background: OverlayPainter,
+    foreground: OverlayPainter,
+}
+
+impl OverlayShapes {
+    #[inline]
+    pub fn layer(&mut self, layer: OverlayLayer) -> &mut OverlayPainter {
+        match layer {
+            OverlayLayer::Background => &mut self.background,
+            OverlayLayer::Foreground => &mut self.foreground,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.background.clear();
+        self.foreground.clear();
+    }
+
+    /// Moves queued shapes into the context's layers.
+    pub fn paint(&mut self, ctx: &Context) {
+        for layer in [OverlayLayer::Background, OverlayLayer::Foreground] {
+            let painter = self.layer(layer);
+            if painter.shapes.is_empty() && painter.texts.is_empty() {
+                continue;
+            }
+
+            let egui_painter = ctx.layer_painter(layer.layer_id());
+            for text in painter.texts.drain(..) {
+                let galley = ctx.fonts().layout_no_wrap(text.text, text.font, text.color);
+                let rect = text
+                    .anchor
+                    .anchor_rect(Rect::from_min_size(text.pos, galley.size()));
+                painter.shapes.push(Shape::galley(rect.min, galley));
+            }
+
+            // Single nested shape avoids growing the layer's list one shape at a time.
+            egui_painter.add(Shape::Vec(std::mem::take(&mut painter.shapes)));
+        }
+    }
+}
+
+/// Immediate mode painter drawing on a layer without any egui windows.
+/// Queued shapes are shown for a single frame, so they have to be added every frame.
+/// Coordinates are in points, same as in the ui closure.
+#[derive(Default)]
+pub struct OverlayPainter {
+    shapes: Vec<Shape>,
+    texts: Vec<OverlayText>,
+}
+
+impl OverlayPainter {
+    /// Reserves space for `additional` shapes, avoids reallocations when drawing a lot of them.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.shapes.reserve(additional);
+    }
+
+    #[inline]
+    pub fn line(&mut self, from: Pos2, to: Pos2, stroke: impl Into<Stroke>) {
+        self.shapes.push(Shape::line_segment([from, to], stroke));
+    }
+
+    /// Connected line through all points.
+    #[inline]
+    pub fn polyline(&mut self, points: Vec<Pos2>, stroke: impl Into<Stroke>) {
+        self.shapes.push(Shape::line(points, stroke));
+    }
+
+    #[inline]
+    pub fn rect(&mut self, rect: Rect, rounding: f32, stroke: impl Into<Stroke>) {
+        self.shapes.push(Shape::rect_stroke(rect, rounding, stroke));
+    }
+
+    #[inline]
+    pub fn rect_filled(&mut self, rect: Rect, rounding: f32, color: impl Into<Color32>) {
+        self.shapes.push(Shape::rect_filled(rect, rounding, color));
+    }
+
+    #[inline]
+    pub fn circle(&mut self, center: Pos2, radius: f32, stroke: impl Into<Stroke>) {
+        self.shapes
+            .push(Shape::circle_stroke(center, radius, stroke));
+    }
+
+    #[inline]
+    pub fn circle_filled(&mut self, center: Pos2, radius: f32, color: impl Into<Color32>) {
+        self.shapes
+            .push(Shape::circle_filled(center, radius, color));
+    }
+
+    /// Text is laid out during present, when fonts are available.
+    #[inline]
+    pub fn text(
+        &mut self,
+        pos: Pos2,
+        anchor: Align2,
+        text: impl Into<String>,
+        font: FontId,
+        color: Color32,
+    ) {
+        self.texts.push(OverlayText {
+            pos,
+            anchor,
+            text: text.into(),
+            font,
+            color,
+        });
+    }
+
+    /// Adds any egui shape, e.g. a mesh built by the caller.
+    #[inline]
+    pub fn add(&mut self, shape: Shape) {
+        self.shapes.push(shape);
+    }
+
+    /// Removes everything queued for the next frame.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+        self.texts.clear();
+    }
+}
@@ -0,0 +1,151 @@
+use egui::{ClippedMesh, Context, Event, FullOutput, Pos2, RawInput, Rect, TexturesDelta};
+use std::ptr::null;
+use windows::Win32::Graphics::{
+    Direct3D11::{
+        ID3D11Device, ID3D11DeviceContext, ID3D11RenderTargetView, ID3D11ShaderResourceView,
+        ID3D11Texture2D, D3D11_BIND_FLAG, D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE,
+        D3D11_CPU_ACCESS_FLAG, D3D11_RESOURCE_MISC_FLAG, D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT,
+        D3D11_VIEWPORT,
+    },
+    Dxgi::Common::{DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SAMPLE_DESC},
+};
+
+use crate::ContextId;
+
+/// Texture a panel is rendered into, created on the first frame.
+struct PanelTarget {
+    view: ID3D11RenderTargetView,
+    resource: ID3D11ShaderResourceView,
+}
+
+impl PanelTarget {
+    fn new(device: &ID3D11Device, size: [u32; 2]) -> Self {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: size[0],
+            Height: size[1],
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_FLAG(D3D11_BIND_RENDER_TARGET.0 | D3D11_BIND_SHADER_RESOURCE.0),
+            CPUAccessFlags: D3D11_CPU_ACCESS_FLAG(0),
+            MiscFlags: D3D11_RESOURCE_MISC_FLAG(0),
+        };
+
+        unsafe {
+            let texture: ID3D11Texture2D = expect!(
+                device.CreateTexture2D(&desc, null()),
+                CreateTexture,
+                "Failed to create panel texture."
+            );
+
+            Self {
+                view: expect!(
+                    device.CreateRenderTargetView(&texture, null()),
+                    CreateRenderTargetView,
+                    "Failed to create panel render target view."
+                ),
+                resource: expect!(
+                    device.CreateShaderResourceView(&texture, null()),
+                    CreateShaderResourceView,
+                    "Failed to create panel shader resource view."
+                ),
+            }
+        }
+    }
+}
+
+/// Context rendered into a texture instead of the screen.
+pub(crate) struct TexturePanel {
+    pub id: ContextId,
+    ctx: Context,
+    ui: Box<dyn FnMut(&Context)>,
+    size: [u32; 2],
+    target: Option<PanelTarget>,
+    events: Vec<Event>,
+}
+
+impl TexturePanel {
+    pub fn new(size: [u32; 2], ui: Box<dyn FnMut(&Context)>) -> Self {
+        Self {
+            id: ContextId::next(),
+            ctx: Context::default(),
+            ui,
+            size: [size[0].max(1), size[1].max(1)],
+            target: None,
+            events: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn push_events(&mut self, events: impl IntoIterator<Item = Event>) {
+        self.events.extend(events);
+    }
+
+    #[inline]
+    pub fn resource(&self) -> Option<ID3D11ShaderResourceView> {
+        self.target.as_ref().map(|target| target.resource.clone())
+    }
+
+    #[inline]
+    pub fn context(&self) -> Context {
+        self.ctx.clone()
+    }
+
+    /// Runs the ui closure, returns meshes and texture changes with remapped ids.
+    pub fn run(&mut self, time: Option<f64>) -> (Vec<ClippedMesh>, TexturesDelta) {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_max(
+                Pos2::ZERO,
+                Pos2::new(self.size[0] as _, self.size[1] as _),
+            )),
+            pixels_per_point: Some(1.),
+            time,
+            events: std::mem::take(&mut self.events),
+            ..Default::default()
+        };
+
+        let ui = &mut self.ui;
+        let FullOutput {
+            shapes,
+            textures_delta,
+            ..
+        } = self.ctx.run(input, |ctx| ui(ctx));
+
+        let mut meshes = self.ctx.tessellate(shapes);
+        self.id.remap_meshes(&mut meshes);
+        (meshes, self.id.remap_delta(textures_delta))
+    }
+
+    /// Returns cleared render target and its viewport.
+    pub fn begin(
+        &mut self,
+        device: &ID3D11Device,
+        context: &ID3D11DeviceContext,
+    ) -> (ID3D11RenderTargetView, D3D11_VIEWPORT) {
+        let size = self.size;
+        let target = self
+            .target
+            .get_or_insert_with(|| PanelTarget::new(device, size));
+
+        unsafe {
+            context.ClearRenderTargetView(target.view.clone(), [0., 0., 0., 0.].as_ptr());
+        }
+
+        (
+            target.view.clone(),
+            D3D11_VIEWPORT {
+                TopLeftX: 0.,
+                TopLeftY: 0.,
+                Width: size[0] as _,
+                Height: size[1] as _,
+                MinDepth: 0.,
+                MaxDepth: 1.,
+            },
+        )
+    }
+}
@@ -0,0 +1,25 @@
+use egui::{
+    output::{OpenUrl, PlatformOutput},
+    CursorIcon,
+};
+
+/// Receives egui's platform output every frame, after the ui closure ran.
+/// Allows implementing what the crate doesn't do itself, like opening urls or positioning
+/// IME windows at [`PlatformOutput::text_cursor_pos`], and taking over built-in behaviors.
+pub trait PlatformOutputHandler {
+    /// Called with the whole output first, including widget events and text cursor.
+    fn output(&mut self, _output: &PlatformOutput) {}
+
+    /// Called when egui copied text. Return `true` to skip writing it into the clipboard.
+    fn copied_text(&mut self, _text: &str) -> bool {
+        false
+    }
+
+    /// Called with the cursor egui wants. Return `true` to keep the crate from setting it.
+    fn cursor_icon(&mut self, _icon: CursorIcon) -> bool {
+        false
+    }
+
+    /// Called when a link was clicked, the crate doesn't open urls by itself.
+    fn open_url(&mut self, _url: &OpenUrl) {}
+}
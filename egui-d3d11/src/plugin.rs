@@ -0,0 +1,290 @@
+//! Loading of separately compiled UI modules through a thin C ABI.
+//!
+//! A plugin is a DLL exporting [`PLUGIN_ENTRY`] with the [`PluginEntry`] signature.
+//! It receives [`HostApi`] with painter functions and returns [`PluginDesc`] describing
+//! how to draw it. Neither side depends on the other's Rust or egui version.
+
+use egui::{Context, Id, LayerId, Order};
+use std::{
+    ffi::{c_void, CStr, CString},
+    fs,
+    os::raw::c_char,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+use windows::Win32::{
+    Foundation::{HINSTANCE, PSTR},
+    System::LibraryLoader::{FreeLibrary, GetProcAddress, LoadLibraryA},
+};
+
+use crate::ffi::{
+    egui_d3d11_painter_circle, egui_d3d11_painter_circle_filled, egui_d3d11_painter_line,
+    egui_d3d11_painter_rect, egui_d3d11_painter_rect_filled, egui_d3d11_painter_text, EguiPainter,
+};
+
+/// Version of [`HostApi`] and [`PluginDesc`] layouts.
+/// Bumped on every incompatible change, plugins built for another version are rejected.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin has to export.
+pub const PLUGIN_ENTRY: &str = "egui_d3d11_plugin_entry";
+
+/// Exported by plugins, returns null if the plugin doesn't support the host's version.
+pub type PluginEntry = unsafe extern "C" fn(host: *const HostApi) -> *const PluginDesc;
+
+/// Functions of the host available to plugins.
+#[repr(C)]
+pub struct HostApi {
+    pub abi_version: u32,
+    pub line: unsafe extern "C" fn(*mut EguiPainter, f32, f32, f32, f32, f32, u32),
+    pub rect: unsafe extern "C" fn(*mut EguiPainter, f32, f32, f32, f32, f32, f32, u32),
+    pub rect_filled: unsafe extern "C" fn(*mut EguiPainter, f32, f32, f32, f32, f32, u32),
+    pub circle: unsafe extern "C" fn(*mut EguiPainter, f32, f32, f32, f32, u32),
+    pub circle_filled: unsafe extern "C" fn(*mut EguiPainter, f32, f32, f32, u32),
+    pub text: unsafe extern "C" fn(*mut EguiPainter, f32, f32, *const c_char, f32, u32),
+}
+
+static HOST_API: HostApi = HostApi {
+    abi_version: PLUGIN_ABI_VERSION,
+    line: egui_d3d11_painter_line,
+    rect: egui_d3d11_painter_rect,
+    rect_filled: egui_d3d11_painter_rect_filled,
+    circle: egui_d3d11_painter_circle,
+    circle_filled: egui_d3d11_painter_circle_filled,
+    text: egui_d3d11_painter_text,
+};
+
+/// Returned by plugin's entry, has to stay valid until `unload` is called.
+/// Fields present in version 1 keep their offsets in every later version,
+/// so the host can release plugins built for a version it rejects.
+#[repr(C)]
+pub struct PluginDesc {
+    pub abi_version: u32,
+    /// Zero terminated UTF-8 name, also used for the plugin's layer.
+    pub name: *const c_char,
+    pub user_data: *mut c_void,
+    /// Called every frame while the plugin is loaded.
+    pub draw: unsafe extern "C" fn(painter: *mut EguiPainter, user_data: *mut c_void),
+    /// Called before the library is freed.
+    pub unload: Option<unsafe extern "C" fn(user_data: *mut c_void)>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("Failed to load plugin library: {0}")]
+    Library(#[from] windows::core::Error),
+    #[error("Failed to copy plugin library: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Plugin `{0}` isn't loaded.")]
+    NotLoaded(String),
+    #[error("Plugin path contains a nul byte.")]
+    InvalidPath,
+    #[error("Library doesn't export `egui_d3d11_plugin_entry`.")]
+    MissingEntry,
+    #[error("Plugin doesn't support ABI version {0}.")]
+    IncompatibleVersion(u32),
+    #[error("Plugin with name `{0}` is already loaded.")]
+    DuplicateName(String),
+    #[error("Plugin's name is null.")]
+    MissingName,
+}
+
+struct LoadedPlugin {
+    name: String,
+    desc: *const PluginDesc,
+    library: HINSTANCE,
+    /// Library the plugin was loaded from and its modification time at that moment.
+    source: PathBuf,
+    modified: Option<SystemTime>,
+    /// Copy that is actually loaded, so the source stays writable for the compiler.
+    shadow: PathBuf,
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        unsafe {
+            let desc = &*self.desc;
+            if let Some(unload) = desc.unload {
+                unload(desc.user_data);
+            }
+            FreeLibrary(self.library);
+        }
+        let _ = fs::remove_file(&self.shadow);
+    }
+}
+
+/// Copies library into the temp directory under a unique name,
+/// Windows keeps loaded libraries locked.
+fn shadow_copy(source: &Path) -> Result<PathBuf, PluginError> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join("egui_d3d11_plugins");
+    fs::create_dir_all(&dir)?;
+
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let shadow = dir.join(format!(
+        "{}-{}-{}.dll",
+        stem,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::copy(source, &shadow)?;
+    Ok(shadow)
+}
+
+#[inline]
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Plugins loaded into the running app, each drawn on its own foreground layer.
+/// [`Self::draw`] has to be called inside of the ui closure.
+///
+/// Libraries are loaded from a copy, so plugins can be rebuilt while the game runs
+/// and picked up with [`Self::reload_changed`]. Plugins only draw through the painter,
+/// their shapes live for a single frame and nothing they drew outlives unloading.
+#[derive(Default)]
+pub struct PluginHost {
+    plugins: Vec<LoadedPlugin>,
+}
+
+impl PluginHost {
+    /// Loads plugin DLL at `path` and returns its name.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<String, PluginError> {
+        let plugin = Self::open(path.as_ref())?;
+        if self.plugins.iter().any(|p| p.name == plugin.name) {
+            return Err(PluginError::DuplicateName(plugin.name.clone()));
+        }
+
+        let name = plugin.name.clone();
+        self.plugins.push(plugin);
+        Ok(name)
+    }
+
+    fn open(source: &Path) -> Result<LoadedPlugin, PluginError> {
+        let modified = modified(source);
+        let shadow = shadow_copy(source)?;
+        let path = CString::new(shadow.to_string_lossy().as_bytes())
+            .map_err(|_| PluginError::InvalidPath)?;
+
+        unsafe {
+            let library = LoadLibraryA(PSTR(path.as_ptr() as _));
+            if library.is_invalid() {
+                let _ = fs::remove_file(&shadow);
+                return Err(windows::core::Error::from_win32().into());
+            }
+
+            match Self::init(library) {
+                Ok((name, desc)) => Ok(LoadedPlugin {
+                    name,
+                    desc,
+                    library,
+                    source: source.to_owned(),
+                    modified,
+                    shadow,
+                }),
+                Err(e) => {
+                    FreeLibrary(library);
+                    let _ = fs::remove_file(&shadow);
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    unsafe fn init(library: HINSTANCE) -> Result<(String, *const PluginDesc), PluginError> {
+        let mut entry_name: Vec<u8> = PLUGIN_ENTRY.bytes().chain(Some(0)).collect();
+        let entry = GetProcAddress(library, PSTR(entry_name.as_mut_ptr()))
+            .ok_or(PluginError::MissingEntry)?;
+        let entry: PluginEntry = std::mem::transmute(entry);
+
+        let desc = entry(&HOST_API);
+        let d = desc
+            .as_ref()
+            .ok_or(PluginError::IncompatibleVersion(PLUGIN_ABI_VERSION))?;
+
+        let error = if d.abi_version != PLUGIN_ABI_VERSION {
+            PluginError::IncompatibleVersion(PLUGIN_ABI_VERSION)
+        } else if d.name.is_null() {
+            PluginError::MissingName
+        } else {
+            return Ok((CStr::from_ptr(d.name).to_string_lossy().into_owned(), desc));
+        };
+
+        // Plugin is already initialized, it has to clean up before the library is freed.
+        if let Some(unload) = d.unload {
+            unload(d.user_data);
+        }
+        Err(error)
+    }
+
+    /// Unloads plugin, returns `false` if none with such name is loaded.
+    pub fn unload(&mut self, name: &str) -> bool {
+        let len = self.plugins.len();
+        self.plugins.retain(|p| p.name != name);
+        self.plugins.len() != len
+    }
+
+    /// Unloads plugin and loads its library again, keeping its position in the draw order.
+    /// If the new library fails to load, the plugin stays unloaded.
+    pub fn reload(&mut self, name: &str) -> Result<(), PluginError> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|p| p.name == name)
+            .ok_or_else(|| PluginError::NotLoaded(name.to_owned()))?;
+
+        // Old library has to be gone before the new one initializes.
+        let source = self.plugins.remove(index).source.clone();
+        let plugin = Self::open(&source)?;
+        if self.plugins.iter().any(|p| p.name == plugin.name) {
+            return Err(PluginError::DuplicateName(plugin.name.clone()));
+        }
+
+        self.plugins.insert(index, plugin);
+        Ok(())
+    }
+
+    /// Reloads plugins whose library changed on disk since it was loaded.
+    /// Returns names of reloaded plugins with the result. Must not be called while drawing.
+    pub fn reload_changed(&mut self) -> Vec<(String, Result<(), PluginError>)> {
+        let changed: Vec<String> = self
+            .plugins
+            .iter()
+            .filter(|p| modified(&p.source).map_or(false, |m| Some(m) != p.modified))
+            .map(|p| p.name.clone())
+            .collect();
+
+        changed
+            .into_iter()
+            .map(|name| {
+                let result = self.reload(&name);
+                (name, result)
+            })
+            .collect()
+    }
+
+    /// Returns names of loaded plugins, in the order they're drawn.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(|p| p.name.as_str())
+    }
+
+    /// Draws all plugins.
+    pub fn draw(&self, ctx: &Context) {
+        for plugin in &self.plugins {
+            let layer = LayerId::new(
+                Order::Foreground,
+                Id::new(("egui_d3d11_plugin", &plugin.name)),
+            );
+            let mut painter = EguiPainter {
+                painter: ctx.layer_painter(layer),
+            };
+
+            unsafe {
+                let desc = &*plugin.desc;
+                (desc.draw)(&mut painter, desc.user_data);
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11Buffer, ID3D11Device, D3D11_BIND_FLAG, D3D11_BUFFER_DESC, D3D11_CPU_ACCESS_WRITE,
+    D3D11_USAGE_DYNAMIC,
+};
+
+/// Smallest buffer handed out by the pool, tiny meshes share a single size class.
+const MIN_SIZE: usize = 256;
+
+/// Buffers are grouped by bind flags and size rounded up to a power of two.
+type SizeClass = (u32, usize);
+
+/// Recycles dynamic buffers across frames so changing UI mostly reuses existing ones
+/// instead of allocating new buffers every frame.
+#[derive(Default)]
+pub(crate) struct BufferPool {
+    free: HashMap<SizeClass, Vec<ID3D11Buffer>>,
+    in_use: Vec<(SizeClass, ID3D11Buffer)>,
+}
+
+impl BufferPool {
+    /// Returns dynamic buffer of at least `size` bytes with undefined contents,
+    /// which stays reserved until [`Self::recycle`]. Returns its real size as well.
+    pub fn acquire(
+        &mut self,
+        device: &ID3D11Device,
+        bind: D3D11_BIND_FLAG,
+        size: usize,
+    ) -> (ID3D11Buffer, usize) {
+        let class = (bind.0, size.max(MIN_SIZE).next_power_of_two());
+
+        let buffer = match self.free.get_mut(&class).and_then(Vec::pop) {
+            Some(buffer) => buffer,
+            None => Self::create_buffer(device, class),
+        };
+
+        self.in_use.push((class, buffer.clone()));
+        (buffer, class.1)
+    }
+
+    /// Returns buffers used during the frame to the pool. Buffers that weren't needed
+    /// for a whole frame are released, so the pool only holds what the last frame used.
+    pub fn recycle(&mut self) {
+        self.free.clear();
+        for (class, buffer) in self.in_use.drain(..) {
+            self.free.entry(class).or_default().push(buffer);
+        }
+    }
+
+    /// Returns amount of video memory held by the pool.
+    pub fn byte_size(&self) -> usize {
+        let free = self
+            .free
+            .iter()
+            .map(|((_, size), buffers)| size * buffers.len());
+        let in_use = self.in_use.iter().map(|((_, size), _)| *size);
+        free.chain(in_use).sum()
+    }
+
+    fn create_buffer(device: &ID3D11Device, (bind, size): SizeClass) -> ID3D11Buffer {
+        let desc = D3D11_BUFFER_DESC {
+            ByteWidth: size as _,
+            Usage: D3D11_USAGE_DYNAMIC,
+            BindFlags: bind,
+            CPUAccessFlags: D3D11_CPU_ACCESS_WRITE.0,
+            MiscFlags: 0,
+            StructureByteStride: 0,
+        };
+
+        unsafe {
+            expect!(
+                device.CreateBuffer(&desc, std::ptr::null()),
+                CreateBuffer,
+                "Failed to create pooled buffer"
+            )
+        }
+    }
+}
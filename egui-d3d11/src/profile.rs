@@ -0,0 +1,103 @@
+use egui::Memory;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{DirectX11App, Keybind, KeybindConflict};
+
+const SETTINGS_FILE: &str = "settings.json";
+const MEMORY_FILE: &str = "memory.json";
+const KEYBINDS_FILE: &str = "keybinds.json";
+
+/// Storage of settings, egui memory (window positions, collapsed headers, etc.) and keybinds,
+/// kept separately for each game under `%APPDATA%\egui-d3d11\<exe name>`.
+/// The same overlay injected into different games doesn't share layouts.
+pub struct ProfileManager {
+    dir: PathBuf,
+}
+
+impl ProfileManager {
+    /// Creates manager for the executable of the current process.
+    /// Returns `None` if either `%APPDATA%` or the executable path can't be resolved.
+    pub fn current() -> Option<Self> {
+        let exe = std::env::current_exe().ok()?;
+        Self::new(exe.file_stem()?.to_str()?)
+    }
+
+    /// Creates manager for profile with `name`, usually the executable name without extension.
+    /// Returns `None` if `%APPDATA%` isn't set.
+    pub fn new(name: &str) -> Option<Self> {
+        let appdata = std::env::var_os("APPDATA")?;
+        Some(Self::with_dir(
+            Path::new(&appdata).join("egui-d3d11").join(name),
+        ))
+    }
+
+    /// Creates manager storing the profile directly in `dir`.
+    #[inline]
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns directory the profile is stored in, it's created on the first save.
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Loads settings saved with [`Self::save_settings`], `None` if there are none yet.
+    pub fn load_settings<S: DeserializeOwned>(&self) -> io::Result<Option<S>> {
+        self.read(SETTINGS_FILE)
+    }
+
+    pub fn save_settings<S: Serialize>(&self, settings: &S) -> io::Result<()> {
+        self.write(SETTINGS_FILE, settings)
+    }
+
+    /// Restores egui memory and rebinds actions to saved keys.
+    /// Actions have to be bound before, keys of unknown actions are ignored
+    /// and conflicting ones are skipped and returned.
+    pub fn restore<T>(&self, app: &DirectX11App<T>) -> io::Result<Vec<KeybindConflict>> {
+        if let Some(memory) = self.read::<Memory>(MEMORY_FILE)? {
+            *app.context().memory() = memory;
+        }
+
+        match self.read::<HashMap<String, Keybind>>(KEYBINDS_FILE)? {
+            Some(keybinds) => Ok(app.keybinds().load(&keybinds)),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Saves egui memory and keys of all bound actions.
+    pub fn store<T>(&self, app: &DirectX11App<T>) -> io::Result<()> {
+        let memory = app.context().memory().clone();
+        self.write(MEMORY_FILE, &memory)?;
+        self.write(KEYBINDS_FILE, &app.keybinds().keybinds())
+    }
+
+    fn read<V: DeserializeOwned>(&self, file: &str) -> io::Result<Option<V>> {
+        match File::open(self.dir.join(file)) {
+            Ok(f) => Ok(Some(serde_json::from_reader(BufReader::new(f))?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes into a temporary file first, so a crash of the game doesn't leave a truncated profile.
+    fn write<V: Serialize>(&self, file: &str, value: &V) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.dir.join(file);
+        let temp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        serde_json::to_writer(&mut writer, value)?;
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(temp, path)
+    }
+}
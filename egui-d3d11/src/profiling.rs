@@ -0,0 +1,12 @@
+use egui::Context;
+
+/// Shows `puffin_egui` profiler window.
+/// Enables collection of profiling scopes if it wasn't enabled before.
+/// Returns `false` if window was closed.
+pub fn profiler_window(ctx: &Context) -> bool {
+    if !puffin::are_scopes_on() {
+        puffin::set_scopes_on(true);
+    }
+
+    puffin_egui::profiler_window(ctx)
+}
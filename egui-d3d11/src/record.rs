@@ -0,0 +1,111 @@
+use egui::Event;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// Single frame worth of input stored by [`InputRecorder`].
+/// `time` is in seconds relative to the start of recording.
+#[derive(Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub time: f64,
+    pub events: Vec<Event>,
+}
+
+/// Writes all collected egui events into a file, one json encoded frame per line.
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    start: Option<f64>,
+}
+
+impl InputRecorder {
+    /// Creates (or truncates) file at `path` and starts recording into it.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            start: None,
+        })
+    }
+
+    /// Appends frame to the recording. Frames without events are skipped.
+    pub fn record(&mut self, time: f64, events: &[Event]) -> std::io::Result<()> {
+        let start = *self.start.get_or_insert(time);
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let frame = RecordedFrame {
+            time: time - start,
+            events: events.to_vec(),
+        };
+
+        serde_json::to_writer(&mut self.writer, &frame)?;
+        self.writer.write_all(b"\n")
+    }
+
+    #[inline]
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Feeds events previously saved by [`InputRecorder`] back into the app,
+/// preserving the original timing between frames.
+pub struct InputReplayer {
+    frames: VecDeque<RecordedFrame>,
+    start: Option<f64>,
+}
+
+impl InputReplayer {
+    /// Loads whole recording from file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut frames = VecDeque::new();
+
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            frames.push_back(serde_json::from_str(&line)?);
+        }
+
+        Ok(Self {
+            frames,
+            start: None,
+        })
+    }
+
+    /// Returns `true` if all recorded frames were already replayed.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns all events that should've happened until `time`.
+    pub fn next_events(&mut self, time: f64) -> Vec<Event> {
+        let elapsed = time - *self.start.get_or_insert(time);
+        let mut events = vec![];
+
+        while let Some(frame) = self.frames.pop_front() {
+            if frame.time > elapsed {
+                self.frames.push_front(frame);
+                break;
+            }
+
+            events.extend(frame.events);
+        }
+
+        events
+    }
+}
@@ -0,0 +1,244 @@
+use egui::{
+    plot::{Line, Plot, Value, Values},
+    Align2, Color32, Context, FontId, Id, LayerId, Order, Painter, Pos2, Stroke, Ui, Window,
+};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST, FLOAT, INT};
+use std::{
+    cell::Cell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+/// Handle to the current [`Ui`] passed to scripts, only valid during the `ui` call.
+/// Scripts can clone it or store it into `this`, so the pointer is shared by all copies
+/// and cleared once the call returns.
+#[derive(Clone)]
+struct ScriptUi {
+    ui: Rc<Cell<Option<*mut Ui>>>,
+    painter: Painter,
+}
+
+impl ScriptUi {
+    #[inline]
+    fn ui(&mut self) -> Result<&mut Ui, Box<EvalAltResult>> {
+        match self.ui.get() {
+            Some(ui) => Ok(unsafe { &mut *ui }),
+            None => Err("Ui can only be used during the `ui` call.".into()),
+        }
+    }
+}
+
+#[inline]
+fn color(rgba: INT) -> Color32 {
+    let [r, g, b, a] = (rgba as u32).to_be_bytes();
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn create_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(1_000_000);
+
+    engine
+        .register_type_with_name::<ScriptUi>("Ui")
+        .register_result_fn("label", |s: &mut ScriptUi, text: &str| {
+            s.ui()?.label(text);
+            Ok(())
+        })
+        .register_result_fn("heading", |s: &mut ScriptUi, text: &str| {
+            s.ui()?.heading(text);
+            Ok(())
+        })
+        .register_result_fn("separator", |s: &mut ScriptUi| {
+            s.ui()?.separator();
+            Ok(())
+        })
+        .register_result_fn("button", |s: &mut ScriptUi, text: &str| {
+            Ok(s.ui()?.button(text).clicked())
+        })
+        .register_result_fn("checkbox", |s: &mut ScriptUi, text: &str, value: bool| {
+            let mut value = value;
+            s.ui()?.checkbox(&mut value, text);
+            Ok(value)
+        })
+        .register_result_fn(
+            "slider",
+            |s: &mut ScriptUi, text: &str, value: FLOAT, min: FLOAT, max: FLOAT| {
+                let mut value = value;
+                s.ui()?
+                    .add(egui::Slider::new(&mut value, min..=max).text(text));
+                Ok(value)
+            },
+        )
+        .register_result_fn("plot", |s: &mut ScriptUi, name: &str, values: Array| {
+            let values = values
+                .iter()
+                .enumerate()
+                .filter_map(|(i, v)| Some(Value::new(i as f64, v.as_float().ok()?)))
+                .collect();
+            Plot::new(name).height(120.).show(s.ui()?, |plot| {
+                plot.line(Line::new(Values::from_values(values)))
+            });
+            Ok(())
+        })
+        .register_fn(
+            "paint_line",
+            |s: &mut ScriptUi,
+             x0: FLOAT,
+             y0: FLOAT,
+             x1: FLOAT,
+             y1: FLOAT,
+             width: FLOAT,
+             rgba: INT| {
+                s.painter.line_segment(
+                    [Pos2::new(x0 as _, y0 as _), Pos2::new(x1 as _, y1 as _)],
+                    Stroke::new(width as _, color(rgba)),
+                );
+            },
+        )
+        .register_fn(
+            "paint_circle",
+            |s: &mut ScriptUi, x: FLOAT, y: FLOAT, radius: FLOAT, rgba: INT| {
+                s.painter
+                    .circle_filled(Pos2::new(x as _, y as _), radius as _, color(rgba));
+            },
+        )
+        .register_fn(
+            "paint_text",
+            |s: &mut ScriptUi, x: FLOAT, y: FLOAT, text: &str, size: FLOAT, rgba: INT| {
+                s.painter.text(
+                    Pos2::new(x as _, y as _),
+                    Align2::LEFT_TOP,
+                    text,
+                    FontId::proportional(size as _),
+                    color(rgba),
+                );
+            },
+        );
+
+    engine
+}
+
+/// Window whose contents are drawn by a Rhai script, reloaded whenever the file changes.
+///
+/// The script defines `fn ui(ui)` called every frame, and optionally `fn init()` returning
+/// the initial state, available as `this` inside of `ui`:
+/// ```rhai
+/// fn init() { #{ speed: 1.0, god_mode: false } }
+///
+/// fn ui(ui) {
+///     this.speed = ui.slider("Speed", this.speed, 0.0, 10.0);
+///     this.god_mode = ui.checkbox("God mode", this.god_mode);
+///     if ui.button("Reset") { this.speed = 1.0; }
+/// }
+/// ```
+/// Errors are shown inside of the window instead of the contents.
+pub struct ScriptPanel {
+    engine: Engine,
+    path: PathBuf,
+    title: String,
+    modified: Option<SystemTime>,
+    ast: Option<AST>,
+    state: Dynamic,
+    error: Option<String>,
+}
+
+impl ScriptPanel {
+    /// Creates panel for script at `path`, titled with its file name.
+    /// The file is loaded during the first [`Self::show`].
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_owned();
+        Self {
+            engine: create_engine(),
+            title: path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path,
+            modified: None,
+            ast: None,
+            state: Dynamic::UNIT,
+            error: None,
+        }
+    }
+
+    /// Returns error of the last load or run, if it failed.
+    #[inline]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Reloads the script if it changed on disk, state is reset by calling `init` again.
+    pub fn reload_if_changed(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if self.ast.is_some() && modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        let ast = match self.engine.compile_file(self.path.clone()) {
+            Ok(ast) => ast,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
+
+        let has_init = ast.iter_functions().any(|f| f.name == "init");
+        self.state = if has_init {
+            match self.engine.call_fn(&mut Scope::new(), &ast, "init", ()) {
+                Ok(state) => state,
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+            }
+        } else {
+            Dynamic::from_map(Default::default())
+        };
+
+        self.ast = Some(ast);
+        self.error = None;
+    }
+
+    /// Shows the window. Should be called inside of the ui closure every frame.
+    pub fn show(&mut self, ctx: &Context) {
+        self.reload_if_changed();
+
+        let painter = ctx.layer_painter(LayerId::new(
+            Order::Foreground,
+            Id::new(("egui_d3d11_script", &self.path)),
+        ));
+
+        Window::new(&self.title)
+            .id(Id::new(("egui_d3d11_script_window", &self.path)))
+            .show(ctx, |ui| {
+                if let (Some(ast), None) = (&self.ast, &self.error) {
+                    let handle = Rc::new(Cell::new(Some(ui as *mut _)));
+                    let script_ui = ScriptUi {
+                        ui: handle.clone(),
+                        painter,
+                    };
+                    let result = self.engine.call_fn_raw(
+                        &mut Scope::new(),
+                        ast,
+                        false,
+                        false,
+                        "ui",
+                        Some(&mut self.state),
+                        [Dynamic::from(script_ui)],
+                    );
+                    handle.set(None);
+
+                    if let Err(e) = result {
+                        self.error = Some(e.to_string());
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+            });
+    }
+}
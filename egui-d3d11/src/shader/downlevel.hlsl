@@ -0,0 +1,44 @@
+// Variant for feature level 9_x, which has no integer vertex attributes.
+// Mode byte is read as the first channel of an unorm vector, so it's either 0 or 1/255.
+
+struct vs_in {
+  float2 position : POSITION;
+  float2 uv : TEXCOORD0;
+  float4 color : COLOR;
+  float4 mode : MODE;
+};
+
+struct vs_out {
+  float4 clip : SV_POSITION;
+  float4 color : COLOR;
+  float2 uv : TEXCOORD0;
+  float mode : TEXCOORD1;
+};
+
+vs_out vs_main(vs_in input) {
+  vs_out output;
+
+  output.clip = float4(input.position, 0.0, 1.0);
+  output.color = input.color;
+  output.uv = input.uv;
+  output.mode = input.mode.x * 255.0;
+
+  return output;
+}
+
+sampler sampler0;
+Texture2D texture0;
+
+float4 ps_main(vs_out input) : SV_TARGET {
+  if (input.mode < 0.5) {
+    float3 albedo = pow(
+      input.color.xyz,
+      (1.0 / 2.2).xxx
+    );
+    float alpha = input.color.w * texture0.Sample(sampler0, input.uv).x;
+
+    return float4(albedo, alpha);
+  } else {
+    return input.color * texture0.Sample(sampler0, input.uv);
+  }
+}
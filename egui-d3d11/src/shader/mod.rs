@@ -1,12 +1,15 @@
 use std::ptr::null_mut as null;
+
+use crate::texture::is_downlevel;
 use windows::Win32::{
     Foundation::PSTR,
     Graphics::{
         Direct3D::{
             Fxc::{D3DCompile, D3DCOMPILE_DEBUG, D3DCOMPILE_ENABLE_STRICTNESS},
-            ID3DBlob,
+            ID3DBlob, D3D_FEATURE_LEVEL_9_3,
         },
         Direct3D11::{ID3D11Device, ID3D11PixelShader, ID3D11VertexShader},
+        Dxgi::DXGI_ERROR_UNSUPPORTED,
     },
 };
 
@@ -35,6 +38,7 @@ impl Shader for ID3D11VertexShader {
 
         expect!(
             device.CreateVertexShader(ptr, len, None),
+            CreateVertexShader,
             "Failed to create vertex shader."
         )
     }
@@ -51,6 +55,7 @@ impl Shader for ID3D11PixelShader {
         };
         expect!(
             device.CreatePixelShader(ptr, len, None),
+            CreatePixelShader,
             "Failed to create pixel shader."
         )
     }
@@ -59,6 +64,8 @@ impl Shader for ID3D11PixelShader {
 pub struct CompiledShaders {
     pub vertex: ID3D11VertexShader,
     pub pixel: ID3D11PixelShader,
+    /// Compiled for feature level 9_x, vertex mode has to be bound as unorm instead of uint.
+    pub downlevel: bool,
     bytecode: ShaderData,
 }
 
@@ -83,8 +90,48 @@ impl CompiledShaders {
         }
     }
 
+    /// Embedded shader model 5 bytecode can't run on feature level 9_x devices,
+    /// those get the downlevel variant compiled at runtime.
+    /// Levels 9_1 and 9_2 are rejected, they can't run `level_9_3` shaders
+    /// and 9_1 can't draw with 32-bit indices.
+    fn new_downlevel(device: &ID3D11Device) -> Self {
+        if unsafe { device.GetFeatureLevel().0 } < D3D_FEATURE_LEVEL_9_3.0 {
+            crate::error::fail(
+                crate::ErrorCode::UnsupportedFeatureLevel,
+                DXGI_ERROR_UNSUPPORTED,
+                if cfg!(feature = "no-msgs") {
+                    ""
+                } else {
+                    "Feature level 9_3 or higher is required."
+                },
+            );
+        }
+
+        let vblob = compile_source(
+            DOWNLEVEL_SHADER_SOURCE,
+            c_str!("vs_main"),
+            c_str!("vs_4_0_level_9_3"),
+        );
+        let pblob = compile_source(
+            DOWNLEVEL_SHADER_SOURCE,
+            c_str!("ps_main"),
+            c_str!("ps_4_0_level_9_3"),
+        );
+
+        Self {
+            vertex: Self::create_shader(device, &ShaderData::CompiledBlob(vblob.clone())),
+            pixel: Self::create_shader(device, &ShaderData::CompiledBlob(pblob)),
+            downlevel: true,
+            bytecode: ShaderData::CompiledBlob(vblob),
+        }
+    }
+
     #[cfg(not(feature = "force-compile"))]
     pub fn new(device: &ID3D11Device) -> Self {
+        if is_downlevel(device) {
+            return Self::new_downlevel(device);
+        }
+
         static VERTEX_DATA: &[u8] = include_bytes!("vertex_blob.bin");
 
         let vertex = Self::create_shader::<ID3D11VertexShader>(
@@ -99,12 +146,17 @@ impl CompiledShaders {
         Self {
             vertex,
             pixel,
+            downlevel: false,
             bytecode: ShaderData::EmbeddedData(VERTEX_DATA),
         }
     }
 
     #[cfg(feature = "force-compile")]
     pub fn new(device: &ID3D11Device) -> Self {
+        if is_downlevel(device) {
+            return Self::new_downlevel(device);
+        }
+
         let vblob = Self::compile_shader::<ID3D11VertexShader>();
         let pblob = Self::compile_shader::<ID3D11PixelShader>();
 
@@ -142,6 +194,7 @@ impl CompiledShaders {
         Self {
             vertex,
             pixel,
+            downlevel: false,
             bytecode: ShaderData::CompiledBlob(vblob),
         }
     }
@@ -151,46 +204,7 @@ impl CompiledShaders {
     where
         S: Shader,
     {
-        const SHADER_TEXT: &str = include_str!("shader.hlsl");
-
-        let mut flags = D3DCOMPILE_ENABLE_STRICTNESS;
-        if cfg!(debug_assertions) {
-            flags |= D3DCOMPILE_DEBUG;
-        }
-
-        unsafe {
-            let mut blob = None;
-            let mut error = None;
-
-            if D3DCompile(
-                SHADER_TEXT.as_ptr() as _,
-                SHADER_TEXT.len() as _,
-                PSTR(null()),
-                null(),
-                None,
-                S::ENTRY_POINT,
-                S::TARGET,
-                flags,
-                0,
-                &mut blob,
-                &mut error,
-            )
-            .is_err()
-            {
-                if !cfg!(feature = "no-msgs") {
-                    let error = std::str::from_utf8_unchecked(std::slice::from_raw_parts(
-                        error.as_ref().unwrap().GetBufferPointer() as *const u8,
-                        error.as_ref().unwrap().GetBufferSize(),
-                    ));
-
-                    panic!("{}", error);
-                } else {
-                    unreachable!();
-                }
-            }
-
-            blob.unwrap()
-        }
+        compile_source(SHADER_SOURCE, S::ENTRY_POINT, S::TARGET)
     }
 
     #[inline]
@@ -201,3 +215,48 @@ impl CompiledShaders {
         unsafe { S::create(device, blob) }
     }
 }
+
+pub const SHADER_SOURCE: &str = include_str!("shader.hlsl");
+pub const DOWNLEVEL_SHADER_SOURCE: &str = include_str!("downlevel.hlsl");
+
+/// Compiles entry point of a bundled shader source for `target` profile,
+/// used when embedded bytecode can't be used.
+pub fn compile_source(source: &str, entry_point: PSTR, target: PSTR) -> ID3DBlob {
+    let mut flags = D3DCOMPILE_ENABLE_STRICTNESS;
+    if cfg!(debug_assertions) {
+        flags |= D3DCOMPILE_DEBUG;
+    }
+
+    unsafe {
+        let mut blob = None;
+        let mut error = None;
+
+        if let Err(e) = D3DCompile(
+            source.as_ptr() as _,
+            source.len() as _,
+            PSTR(null()),
+            null(),
+            None,
+            entry_point,
+            target,
+            flags,
+            0,
+            &mut blob,
+            &mut error,
+        ) {
+            let log = match &error {
+                Some(error) if !cfg!(feature = "no-msgs") => {
+                    std::str::from_utf8_unchecked(std::slice::from_raw_parts(
+                        error.GetBufferPointer() as *const u8,
+                        error.GetBufferSize(),
+                    ))
+                }
+                _ => "",
+            };
+
+            crate::error::fail(crate::ErrorCode::CompileShader, e.code(), log);
+        }
+
+        expect!(blob, CompileShader, "Compiler didn't return bytecode.")
+    }
+}
@@ -0,0 +1,143 @@
+use egui::Pos2;
+use std::mem::size_of;
+use windows::Win32::{
+    Foundation::E_BOUNDS,
+    Graphics::Direct3D11::{
+        ID3D11Buffer, ID3D11Device, ID3D11DeviceContext, D3D11_BIND_FLAG, D3D11_BIND_INDEX_BUFFER,
+        D3D11_BIND_VERTEX_BUFFER, D3D11_MAP, D3D11_MAP_WRITE_DISCARD, D3D11_MAP_WRITE_NO_OVERWRITE,
+    },
+};
+
+use crate::{
+    mesh::{GpuMesh, GpuVertex},
+    pool::BufferPool,
+};
+
+/// Dynamic buffer that meshes of a frame are appended to. The first write of the frame
+/// discards the previous contents and the following ones map with `NO_OVERWRITE`,
+/// so the driver doesn't have to rename the buffer for every mesh.
+struct StreamBuffer {
+    buffer: ID3D11Buffer,
+    capacity: usize,
+    offset: usize,
+    discarded: bool,
+}
+
+impl StreamBuffer {
+    fn new(
+        pool: &mut BufferPool,
+        device: &ID3D11Device,
+        bind: D3D11_BIND_FLAG,
+        size: usize,
+    ) -> Self {
+        let (buffer, capacity) = pool.acquire(device, bind, size);
+        Self {
+            buffer,
+            capacity,
+            offset: 0,
+            discarded: false,
+        }
+    }
+
+    /// Writes elements after the previously appended data, returns offset in elements of `T`.
+    /// All data appended to the buffer has to be of the same type.
+    fn append<T>(
+        &mut self,
+        ctx: &ID3D11DeviceContext,
+        data: impl ExactSizeIterator<Item = T>,
+    ) -> usize {
+        let offset = self.offset;
+        let count = data.len();
+        let len = count * size_of::<T>();
+        if offset + len > self.capacity {
+            crate::error::fail(
+                crate::ErrorCode::StreamOverflow,
+                E_BOUNDS,
+                if cfg!(feature = "no-msgs") {
+                    ""
+                } else {
+                    "Geometry doesn't fit into the stream buffer."
+                },
+            );
+        }
+
+        let map_type: D3D11_MAP = if self.discarded {
+            D3D11_MAP_WRITE_NO_OVERWRITE
+        } else {
+            self.discarded = true;
+            D3D11_MAP_WRITE_DISCARD
+        };
+
+        unsafe {
+            let mapped = expect!(
+                ctx.Map(&self.buffer, 0, map_type, 0),
+                MapBuffer,
+                "Failed to map stream buffer"
+            );
+            // Iterator is trusted only up to the reserved length.
+            let dst = (mapped.pData as *mut u8).add(offset) as *mut T;
+            for (i, item) in data.take(count).enumerate() {
+                dst.add(i).write_unaligned(item);
+            }
+            ctx.Unmap(&self.buffer, 0);
+        }
+
+        self.offset += len;
+        offset / size_of::<T>()
+    }
+}
+
+/// Vertex and index buffers holding all meshes of a single frame.
+pub(crate) struct GeometryStream {
+    vertices: StreamBuffer,
+    indices: StreamBuffer,
+}
+
+/// Where a mesh was written in [`GeometryStream`], passed to `DrawIndexed`.
+pub(crate) struct StreamedMesh {
+    pub base_vertex: i32,
+    pub start_index: u32,
+}
+
+impl GeometryStream {
+    /// Acquires buffers big enough to hold all `meshes`.
+    pub fn new(pool: &mut BufferPool, device: &ID3D11Device, meshes: &[GpuMesh]) -> Self {
+        let vertex_bytes = meshes
+            .iter()
+            .map(|m| m.vertices.len() * size_of::<GpuVertex>())
+            .sum();
+        let index_bytes = meshes
+            .iter()
+            .map(|m| m.indices.len() * size_of::<u32>())
+            .sum();
+
+        Self {
+            vertices: StreamBuffer::new(pool, device, D3D11_BIND_VERTEX_BUFFER, vertex_bytes),
+            indices: StreamBuffer::new(pool, device, D3D11_BIND_INDEX_BUFFER, index_bytes),
+        }
+    }
+
+    #[inline]
+    pub fn vertex_buffer(&self) -> &ID3D11Buffer {
+        &self.vertices.buffer
+    }
+
+    #[inline]
+    pub fn index_buffer(&self) -> &ID3D11Buffer {
+        &self.indices.buffer
+    }
+
+    /// Converts mesh directly into the mapped buffers,
+    /// it has to be one of the meshes the stream was created for.
+    pub fn push(
+        &mut self,
+        ctx: &ID3D11DeviceContext,
+        mesh: &GpuMesh,
+        screen_size: Pos2,
+    ) -> StreamedMesh {
+        StreamedMesh {
+            base_vertex: self.vertices.append(ctx, mesh.gpu_vertices(screen_size)) as _,
+            start_index: self.indices.append(ctx, mesh.indices.iter().copied()) as _,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::ptr::{null, null_mut};
+use windows::Win32::{
+    Foundation::S_OK,
+    Graphics::{
+        Direct3D::{D3D_DRIVER_TYPE_HARDWARE, D3D_FEATURE_LEVEL},
+        Direct3D11::{
+            D3D11CreateDeviceAndSwapChain, ID3D11Device, ID3D11DeviceContext,
+            ID3D11RenderTargetView, ID3D11Texture2D, D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            D3D11_SDK_VERSION,
+        },
+        Dxgi::{Common::DXGI_FORMAT_UNKNOWN, IDXGISwapChain, DXGI_SWAP_CHAIN_DESC},
+    },
+};
+
+use crate::DirectX11App;
+
+/// Swapchain owned by an overlay instead of the game, together with its device
+/// and view of the back buffer. Shared by the external window and composition overlays.
+pub(crate) struct OverlaySwapChain {
+    pub swap_chain: IDXGISwapChain,
+    pub device: ID3D11Device,
+    pub context: ID3D11DeviceContext,
+    view: Option<ID3D11RenderTargetView>,
+}
+
+impl OverlaySwapChain {
+    pub unsafe fn new(
+        swap_chain: IDXGISwapChain,
+        device: ID3D11Device,
+        context: ID3D11DeviceContext,
+    ) -> Self {
+        let view = Some(create_view(&swap_chain, &device));
+        Self {
+            swap_chain,
+            device,
+            context,
+            view,
+        }
+    }
+
+    /// Resizes buffers through the app, so it releases its own view of them as well.
+    pub unsafe fn resize<T>(&mut self, app: &DirectX11App<T>, width: u32, height: u32) {
+        // Buffers can't be resized while any view of them is alive.
+        self.view = None;
+        let swap_chain = &self.swap_chain;
+        let _ = app.resize_buffers(swap_chain, || {
+            match swap_chain.ResizeBuffers(0, width, height, DXGI_FORMAT_UNKNOWN, 0) {
+                Ok(_) => S_OK,
+                Err(e) => e.code(),
+            }
+        });
+        self.view = Some(create_view(&self.swap_chain, &self.device));
+    }
+
+    /// Clears the back buffer to transparent, renders the app and presents it.
+    pub unsafe fn present<T>(&self, app: &DirectX11App<T>) {
+        if let Some(view) = &self.view {
+            self.context
+                .ClearRenderTargetView(view, [0., 0., 0., 0.].as_ptr());
+        }
+
+        app.present(&self.swap_chain, 1, 0);
+        let _ = self.swap_chain.Present(1, 0);
+    }
+}
+
+/// Creates hardware device with BGRA support, needed by composition swapchains.
+/// Windowed swapchain is created together with the device if `desc` is set.
+pub(crate) unsafe fn create_device(
+    desc: Option<&DXGI_SWAP_CHAIN_DESC>,
+) -> (Option<IDXGISwapChain>, ID3D11Device, ID3D11DeviceContext) {
+    let mut swap_chain = None;
+    let mut device = None;
+    let mut context = None;
+    let mut feature_level = D3D_FEATURE_LEVEL::default();
+
+    expect!(
+        D3D11CreateDeviceAndSwapChain(
+            None,
+            D3D_DRIVER_TYPE_HARDWARE,
+            None,
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            null(),
+            0,
+            D3D11_SDK_VERSION,
+            desc.map_or(null(), |desc| desc as *const _),
+            if desc.is_some() {
+                &mut swap_chain as *mut _
+            } else {
+                null_mut()
+            },
+            &mut device,
+            &mut feature_level,
+            &mut context,
+        ),
+        CreateDevice,
+        "Failed to create overlay's device."
+    );
+
+    (
+        swap_chain,
+        expect!(device, CreateDevice, "Device wasn't created."),
+        expect!(
+            context,
+            GetImmediateContext,
+            "Device context wasn't created."
+        ),
+    )
+}
+
+unsafe fn create_view(
+    swap_chain: &IDXGISwapChain,
+    device: &ID3D11Device,
+) -> ID3D11RenderTargetView {
+    let back_buffer: ID3D11Texture2D = expect!(
+        swap_chain.GetBuffer(0),
+        GetBackBuffer,
+        "Failed to get swapchain's back buffer."
+    );
+    expect!(
+        device.CreateRenderTargetView(&back_buffer, null()),
+        CreateRenderTargetView,
+        "Failed to create render target view."
+    )
+}
@@ -0,0 +1,31 @@
+//! Locks used across the crate. `parking_lot` is used with the feature of the same name,
+//! otherwise `std::sync` with the same API: locking never fails, poisoned locks
+//! are taken over since a panic inside of the overlay shouldn't disable it for good.
+
+#[cfg(feature = "parking_lot")]
+pub use parking_lot::{Mutex, MutexGuard};
+
+#[cfg(not(feature = "parking_lot"))]
+pub use std::sync::MutexGuard;
+
+#[cfg(not(feature = "parking_lot"))]
+#[derive(Default)]
+pub struct Mutex<T: ?Sized>(std::sync::Mutex<T>);
+
+#[cfg(not(feature = "parking_lot"))]
+impl<T> Mutex<T> {
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self(std::sync::Mutex::new(value))
+    }
+}
+
+#[cfg(not(feature = "parking_lot"))]
+impl<T: ?Sized> Mutex<T> {
+    #[inline]
+    pub fn lock(&self) -> MutexGuard<T> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    ffi::c_void,
+    mem::{transmute, transmute_copy},
+    ptr::{null, null_mut},
+};
+use windows::{
+    core::Interface,
+    Win32::{
+        Foundation::HWND,
+        Graphics::{
+            Direct3D::{D3D_DRIVER_TYPE_WARP, D3D_FEATURE_LEVEL},
+            Direct3D11::{
+                D3D11CreateDevice, D3D11CreateDeviceAndSwapChain, ID3D11Device,
+                ID3D11DeviceContext, ID3D11Texture2D, D3D11_BIND_FLAG, D3D11_CPU_ACCESS_READ,
+                D3D11_CREATE_DEVICE_FLAG, D3D11_MAP_READ, D3D11_RESOURCE_MISC_FLAG,
+                D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
+            },
+            Dxgi::{
+                Common::DXGI_FORMAT_R8G8B8A8_UNORM, IDXGISwapChain, DXGI_SWAP_CHAIN_DESC,
+                DXGI_SWAP_EFFECT_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            },
+        },
+        UI::WindowsAndMessaging::{CreateWindowExA, DestroyWindow, WINDOW_EX_STYLE, WS_POPUP},
+    },
+};
+
+use crate::ClipboardProvider;
+
+/// Clipboard which is always empty, so tests don't touch the system one.
+pub struct NoClipboard;
+
+impl ClipboardProvider for NoClipboard {
+    fn get_text(&self) -> Option<String> {
+        None
+    }
+
+    fn set_text(&self, _text: &str) -> bool {
+        false
+    }
+}
+
+/// Creates software device, available on every Windows machine including CI runners.
+pub fn warp_device() -> (ID3D11Device, ID3D11DeviceContext) {
+    let mut device = None;
+    let mut context = None;
+    let mut feature_level = D3D_FEATURE_LEVEL::default();
+
+    unsafe {
+        D3D11CreateDevice(
+            None,
+            D3D_DRIVER_TYPE_WARP,
+            None,
+            D3D11_CREATE_DEVICE_FLAG(0),
+            null(),
+            0,
+            D3D11_SDK_VERSION,
+            &mut device,
+            &mut feature_level,
+            &mut context,
+        )
+        .expect("Failed to create WARP device.");
+    }
+
+    (device.unwrap(), context.unwrap())
+}
+
+/// Hidden window with a swapchain of a WARP device, destroyed on drop.
+pub struct TestWindow {
+    pub hwnd: HWND,
+    pub swap_chain: IDXGISwapChain,
+}
+
+impl TestWindow {
+    pub fn new(width: i32, height: i32) -> Self {
+        unsafe {
+            // Predefined class, doesn't have to be registered.
+            let hwnd = CreateWindowExA(
+                WINDOW_EX_STYLE(0),
+                c_str!("STATIC"),
+                c_str!("egui-d3d11 test"),
+                WS_POPUP,
+                0,
+                0,
+                width,
+                height,
+                None,
+                None,
+                None,
+                null(),
+            );
+            assert!(!hwnd.is_invalid(), "Failed to create test window.");
+
+            let mut desc: DXGI_SWAP_CHAIN_DESC = std::mem::zeroed();
+            desc.BufferDesc.Format = DXGI_FORMAT_R8G8B8A8_UNORM;
+            desc.SampleDesc.Count = 1;
+            desc.BufferUsage = DXGI_USAGE_RENDER_TARGET_OUTPUT;
+            desc.BufferCount = 1;
+            desc.OutputWindow = hwnd;
+            desc.Windowed = true.into();
+            desc.SwapEffect = DXGI_SWAP_EFFECT_DISCARD;
+
+            let mut swap_chain = None;
+            let mut feature_level = D3D_FEATURE_LEVEL::default();
+            D3D11CreateDeviceAndSwapChain(
+                None,
+                D3D_DRIVER_TYPE_WARP,
+                None,
+                D3D11_CREATE_DEVICE_FLAG(0),
+                null(),
+                0,
+                D3D11_SDK_VERSION,
+                &desc,
+                &mut swap_chain,
+                null_mut(),
+                &mut feature_level,
+                null_mut(),
+            )
+            .expect("Failed to create WARP swapchain.");
+
+            Self {
+                hwnd,
+                swap_chain: swap_chain.unwrap(),
+            }
+        }
+    }
+}
+
+impl Drop for TestWindow {
+    fn drop(&mut self) {
+        unsafe {
+            DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+/// Copies texture into a staging one and returns its rows, trimmed to `width * bpp` bytes.
+pub fn read_texture(
+    device: &ID3D11Device,
+    ctx: &ID3D11DeviceContext,
+    texture: &ID3D11Texture2D,
+    bytes_per_pixel: usize,
+) -> Vec<Vec<u8>> {
+    unsafe {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        texture.GetDesc(&mut desc);
+        desc.Usage = D3D11_USAGE_STAGING;
+        desc.BindFlags = D3D11_BIND_FLAG(0);
+        desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+        desc.MiscFlags = D3D11_RESOURCE_MISC_FLAG(0);
+
+        let staging = device.CreateTexture2D(&desc, null()).unwrap();
+        ctx.CopyResource(&staging, texture);
+
+        let mapped = ctx.Map(&staging, 0, D3D11_MAP_READ, 0).unwrap();
+        let row_len = desc.Width as usize * bytes_per_pixel;
+        let rows = (0..desc.Height as usize)
+            .map(|y| {
+                let row = (mapped.pData as *const u8).add(y * mapped.RowPitch as usize);
+                std::slice::from_raw_parts(row, row_len).to_vec()
+            })
+            .collect();
+        ctx.Unmap(&staging, 0);
+        rows
+    }
+}
+
+/// Returns current reference count of a COM object, by adding and releasing a reference.
+pub fn ref_count<I: Interface>(object: &I) -> u32 {
+    type RefFn = unsafe extern "system" fn(*mut c_void) -> u32;
+
+    unsafe {
+        let raw: *mut c_void = transmute_copy(object);
+        // `IUnknown` vtable starts with `QueryInterface`, `AddRef` and `Release`.
+        let vtable = *(raw as *const *const [usize; 3]);
+        let add_ref: RefFn = transmute((*vtable)[1]);
+        let release: RefFn = transmute((*vtable)[2]);
+
+        add_ref(raw);
+        release(raw)
+    }
+}
@@ -1,24 +1,59 @@
-use egui::{Color32, ImageData, TextureId, TexturesDelta};
-use parking_lot::{Mutex, MutexGuard};
-use std::{collections::HashMap, slice::from_raw_parts_mut};
-use windows::Win32::Graphics::{
-    Direct3D::D3D11_SRV_DIMENSION_TEXTURE2D,
-    Direct3D11::{
-        ID3D11Device, ID3D11DeviceContext, ID3D11ShaderResourceView, ID3D11Texture2D,
-        D3D11_BIND_SHADER_RESOURCE, D3D11_CPU_ACCESS_WRITE, D3D11_MAP_WRITE_DISCARD,
-        D3D11_RESOURCE_MISC_FLAG, D3D11_SHADER_RESOURCE_VIEW_DESC,
-        D3D11_SHADER_RESOURCE_VIEW_DESC_0, D3D11_SUBRESOURCE_DATA, D3D11_TEX2D_SRV,
-        D3D11_TEXTURE2D_DESC, D3D11_USAGE_DYNAMIC,
-    },
-    Dxgi::Common::{
-        DXGI_FORMAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8_UNORM, DXGI_SAMPLE_DESC,
+use egui::{
+    epaint::ImageDelta, AlphaImage, Color32, ColorImage, ImageData, TextureId, TexturesDelta,
+};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use windows::{
+    core::{Interface, HRESULT},
+    Win32::{
+        Foundation::{E_INVALIDARG, HANDLE, S_OK},
+        Graphics::{
+            Direct3D::{
+                D3D11_SRV_DIMENSION_TEXTURE2D, D3D_FEATURE_LEVEL_10_0, D3D_FEATURE_LEVEL_11_0,
+                D3D_FEATURE_LEVEL_9_3,
+            },
+            Direct3D11::{
+                ID3D11Device, ID3D11DeviceContext, ID3D11ShaderResourceView, ID3D11Texture2D,
+                D3D11_BIND_SHADER_RESOURCE, D3D11_BOX, D3D11_CPU_ACCESS_FLAG,
+                D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION, D3D11_RESOURCE_MISC_FLAG,
+                D3D11_SHADER_RESOURCE_VIEW_DESC, D3D11_SHADER_RESOURCE_VIEW_DESC_0,
+                D3D11_SUBRESOURCE_DATA, D3D11_TEX2D_SRV, D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT,
+                D3D11_USAGE_IMMUTABLE,
+            },
+            Dxgi::Common::{
+                DXGI_FORMAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_FORMAT_R8_UNORM, DXGI_FORMAT_UNKNOWN,
+                DXGI_SAMPLE_DESC,
+            },
+            Dxgi::IDXGIKeyedMutex,
+        },
     },
 };
 
+use crate::{
+    dds::{self, DdsError},
+    sync::{Mutex, MutexGuard},
+    wic,
+};
+
 pub struct AllocatedTexture {
     resource: ID3D11ShaderResourceView,
-    texture: ID3D11Texture2D,
-    image: ImageData,
+    /// `None` for textures registered with [`TextureAllocator::register_native`].
+    texture: Option<ID3D11Texture2D>,
+    /// Size of the image, which may be larger than the texture if it had to be downscaled.
+    size: [usize; 2],
+    /// Full resolution image of a downscaled texture, updates are applied to it
+    /// and the whole texture is downscaled again.
+    source: Option<ImageData>,
+    format: DXGI_FORMAT,
+    /// Zero for native textures, their memory isn't owned by the allocator.
+    byte_size: usize,
+    /// Keyed mutex of a shared texture with keys it's acquired and released with.
+    keyed_mutex: Option<SharedMutex>,
+    /// Frame in which texture was last drawn or created, used for eviction.
+    last_used: AtomicU64,
 }
 
 impl AllocatedTexture {
@@ -27,60 +62,131 @@ impl AllocatedTexture {
         &self.resource
     }
 
+    #[inline]
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    /// Returns amount of video memory used by texture's pixels.
+    #[inline]
+    pub fn byte_size(&self) -> usize {
+        self.byte_size
+    }
+
+    /// Returns `true` if texture was registered with [`TextureAllocator::register_native`].
+    #[inline]
+    pub fn is_native(&self) -> bool {
+        self.texture.is_none()
+    }
+
+    /// Marks texture as used in the current frame.
+    #[inline]
+    pub(crate) fn touch(&self, frame: u64) {
+        self.last_used.store(frame, Ordering::Relaxed);
+    }
+
+    /// Acquires keyed mutex of a shared texture without waiting.
+    /// Returns `false` if the producer still holds it and the texture shouldn't be drawn.
+    #[inline]
+    pub(crate) fn acquire(&self) -> bool {
+        match &self.keyed_mutex {
+            Some(shared) => unsafe { acquire_sync(&shared.mutex, shared.acquire_key) == S_OK },
+            None => true,
+        }
+    }
+
+    /// Hands keyed mutex of a shared texture back to the producer.
+    #[inline]
+    pub(crate) fn release(&self) {
+        if let Some(shared) = &self.keyed_mutex {
+            unsafe {
+                let _ = shared.mutex.ReleaseSync(shared.release_key);
+            }
+        }
+    }
+
+    /// Uploads only the changed region, texture keeps the rest of its contents on the GPU.
     fn update(&mut self, [x, y]: [usize; 2], delta: ImageData, ctx: &ID3D11DeviceContext) {
-        unsafe {
-            let subr = ctx
-                .Map(&self.texture, 0, D3D11_MAP_WRITE_DISCARD, 0)
-                .unwrap();
-
-            match (&self.image, delta) {
-                (ImageData::Color(img), ImageData::Color(new)) => {
-                    let data = from_raw_parts_mut(
-                        subr.pData as *mut Color32,
-                        subr.RowPitch as usize * self.image.height(),
-                    );
-                    data.as_mut_ptr().copy_from_nonoverlapping(
-                        img.pixels.as_ptr(),
-                        subr.RowPitch as usize * self.image.height(),
-                    );
-
-                    let mut i = 0;
-                    for y in y..(y + new.height()) {
-                        for x in x..(x + new.width()) {
-                            data[y * img.width() + x] = new.pixels[i];
-                            i += 1;
-                        }
-                    }
-                }
-                (ImageData::Alpha(img), ImageData::Alpha(new)) => {
-                    let data = from_raw_parts_mut(
-                        subr.pData as *mut u8,
-                        subr.RowPitch as usize * self.image.height(),
-                    );
-                    data.as_mut_ptr().copy_from_nonoverlapping(
-                        img.pixels.as_ptr(),
-                        subr.RowPitch as usize * self.image.height(),
-                    );
-
-                    let mut i = 0;
-                    for y in y..(y + new.height()) {
-                        for x in x..(x + new.width()) {
-                            data[y * img.width() + x] = new.pixels[i];
-                            i += 1;
-                        }
-                    }
-                }
-                _ => unreachable!(),
+        let texture = match &self.texture {
+            Some(texture) => texture,
+            None => return,
+        };
+
+        // egui never changes format of existing texture.
+        if get_image_format(&delta) != self.format
+            || x + delta.width() > self.size[0]
+            || y + delta.height() > self.size[1]
+            || !has_tight_pixels(&delta)
+        {
+            return;
+        }
+
+        if let Some(source) = &mut self.source {
+            paste(source, [x, y], &delta);
+
+            let mut desc = D3D11_TEXTURE2D_DESC::default();
+            unsafe { texture.GetDesc(&mut desc) };
+            let image = resize(source, [desc.Width as _, desc.Height as _]);
+
+            unsafe {
+                ctx.UpdateSubresource(
+                    texture,
+                    0,
+                    std::ptr::null(),
+                    get_image_data(&image),
+                    (image.width() * image.bytes_per_pixel()) as _,
+                    0,
+                );
             }
+            return;
+        }
+
+        let region = D3D11_BOX {
+            left: x as _,
+            top: y as _,
+            front: 0,
+            right: (x + delta.width()) as _,
+            bottom: (y + delta.height()) as _,
+            back: 1,
+        };
 
-            ctx.Unmap(&self.texture, 0);
+        unsafe {
+            ctx.UpdateSubresource(
+                texture,
+                0,
+                &region,
+                get_image_data(&delta),
+                (delta.width() * delta.bytes_per_pixel()) as _,
+                0,
+            );
         }
     }
 }
 
+/// Keyed mutex of a texture registered with [`TextureAllocator::register_shared`].
+struct SharedMutex {
+    mutex: IDXGIKeyedMutex,
+    acquire_key: u64,
+    release_key: u64,
+}
+
+/// Calls `AcquireSync` without waiting and returns its raw result. `WAIT_TIMEOUT` and
+/// `WAIT_ABANDONED` are success codes, the `Result` returned by the binding can't tell them
+/// apart from actually acquiring the mutex.
+unsafe fn acquire_sync(mutex: &IDXGIKeyedMutex, key: u64) -> HRESULT {
+    (Interface::vtable(mutex).AcquireSync)(std::mem::transmute_copy(mutex), key, 0)
+}
+
+/// Owns textures created by egui and the ones registered by the user.
 #[derive(Default)]
 pub struct TextureAllocator {
     allocated: Mutex<HashMap<TextureId, AllocatedTexture>>,
+    next_user_id: AtomicU64,
+    frame: AtomicU64,
+    budget: Mutex<Option<usize>>,
+    on_evict: Mutex<Option<Box<dyn Fn(TextureId) + Send + Sync>>>,
+    /// Uploads queued from other threads, applied at the start of the next present.
+    pending: Mutex<Vec<(TextureId, ImageDelta)>>,
 }
 
 impl TextureAllocator {
@@ -89,8 +195,252 @@ impl TextureAllocator {
         self.allocated.lock()
     }
 
+    /// Registers existing shader resource view, e.g. render target or game's texture,
+    /// so it can be displayed with `ui.image()` without copying.
+    /// View has to be created on the same device as the app and stay valid until it's unregistered.
+    pub fn register_native(&self, resource: ID3D11ShaderResourceView) -> TextureId {
+        self.insert_native(resource, None)
+    }
+
+    /// Opens texture shared by another device or process with `OpenSharedResource`
+    /// and registers it as native texture. If `keys` are set, texture's keyed mutex is acquired
+    /// with the first key once per frame before the first draw and released with the second
+    /// one after the last draw, draws are skipped for the frame while producer holds it.
+    /// Producer handing the texture over with key 1 and taking it back with key 0
+    /// is registered with `Some((1, 0))`.
+    pub fn register_shared(
+        &self,
+        device: &ID3D11Device,
+        handle: HANDLE,
+        keys: Option<(u64, u64)>,
+    ) -> Option<TextureId> {
+        unsafe {
+            let mut texture: Option<ID3D11Texture2D> = None;
+            device.OpenSharedResource(handle, &mut texture).ok()?;
+            let texture = texture?;
+            let keyed_mutex = match keys {
+                Some((acquire_key, release_key)) => Some(SharedMutex {
+                    mutex: texture.cast().ok()?,
+                    acquire_key,
+                    release_key,
+                }),
+                None => None,
+            };
+            let resource = device
+                .CreateShaderResourceView(&texture, std::ptr::null())
+                .ok()?;
+
+            Some(self.insert_native(resource, keyed_mutex))
+        }
+    }
+
+    fn insert_native(
+        &self,
+        resource: ID3D11ShaderResourceView,
+        keyed_mutex: Option<SharedMutex>,
+    ) -> TextureId {
+        let size = unsafe {
+            let mut view = None;
+            resource.GetResource(&mut view);
+
+            match view.and_then(|r| r.cast::<ID3D11Texture2D>().ok()) {
+                Some(texture) => {
+                    let mut desc = D3D11_TEXTURE2D_DESC::default();
+                    texture.GetDesc(&mut desc);
+                    [desc.Width as usize, desc.Height as usize]
+                }
+                None => [0, 0],
+            }
+        };
+
+        self.insert_user(AllocatedTexture {
+            resource,
+            texture: None,
+            size,
+            source: None,
+            format: DXGI_FORMAT_UNKNOWN,
+            byte_size: 0,
+            keyed_mutex,
+            last_used: AtomicU64::default(),
+        })
+    }
+
+    /// Uploads image as a new user texture.
+    pub fn allocate_user(&self, image: impl Into<ImageData>, device: &ID3D11Device) -> TextureId {
+        self.insert_user(Self::allocate_texture(image.into(), device))
+    }
+
+    /// Queues image to be uploaded as a new user texture at the start of the next present.
+    /// Can be called from any thread, returned id is valid right away but meshes using it
+    /// are skipped until the upload is applied.
+    pub fn enqueue_upload(&self, image: impl Into<ImageData>) -> TextureId {
+        let id = TextureId::User(self.next_user_id.fetch_add(1, Ordering::Relaxed));
+        self.pending
+            .lock()
+            .push((id, ImageDelta::full(image.into())));
+        id
+    }
+
+    /// Queues update of region of user texture starting at `pos`,
+    /// applied at the start of the next present. Can be called from any thread.
+    pub fn enqueue_update(&self, id: TextureId, pos: [usize; 2], image: impl Into<ImageData>) {
+        self.pending
+            .lock()
+            .push((id, ImageDelta::partial(pos, image.into())));
+    }
+
+    /// Decodes image file with Windows Imaging Component and uploads it as a user texture.
+    pub fn load_image_file(
+        &self,
+        path: impl AsRef<Path>,
+        device: &ID3D11Device,
+    ) -> windows::core::Result<TextureId> {
+        let image = wic::decode_file(path.as_ref())?;
+        Ok(self.allocate_user(image, device))
+    }
+
+    /// Decodes encoded image (PNG, JPEG, BMP...) with Windows Imaging Component
+    /// and uploads it as a user texture.
+    pub fn load_image_bytes(
+        &self,
+        bytes: &[u8],
+        device: &ID3D11Device,
+    ) -> windows::core::Result<TextureId> {
+        let image = wic::decode_bytes(bytes)?;
+        Ok(self.allocate_user(image, device))
+    }
+
+    /// Uploads DDS image with block-compressed (BC1-BC7) or RGBA8 pixels and all of its mips
+    /// as a user texture. Compressed textures are several times smaller than decoded images.
+    pub fn load_dds(&self, bytes: &[u8], device: &ID3D11Device) -> Result<TextureId, DdsError> {
+        let image = dds::parse(bytes)?;
+
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: image.width,
+            Height: image.height,
+            MipLevels: image.mips.len() as _,
+            ArraySize: 1,
+            Format: image.format,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_IMMUTABLE,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE,
+            CPUAccessFlags: D3D11_CPU_ACCESS_FLAG(0),
+            MiscFlags: D3D11_RESOURCE_MISC_FLAG(0),
+        };
+
+        let init = image
+            .mips
+            .iter()
+            .map(|mip| D3D11_SUBRESOURCE_DATA {
+                pSysMem: mip.data.as_ptr() as _,
+                SysMemPitch: mip.pitch,
+                SysMemSlicePitch: 0,
+            })
+            .collect::<Vec<_>>();
+
+        let texture = unsafe { device.CreateTexture2D(&desc, init.as_ptr())? };
+        let resource = Self::create_resource(image.format, desc.MipLevels, &texture, device);
+
+        Ok(self.insert_user(AllocatedTexture {
+            size: [image.width as _, image.height as _],
+            source: None,
+            format: image.format,
+            byte_size: image.byte_size(),
+            texture: Some(texture),
+            keyed_mutex: None,
+            last_used: AtomicU64::default(),
+            resource,
+        }))
+    }
+
+    /// Removes user texture created by the allocator or registered with [`Self::register_native`]
+    /// and [`Self::register_shared`]. Returns `false` if there was no such texture.
+    pub fn unregister(&self, id: TextureId) -> bool {
+        match id {
+            TextureId::User(_) => self.allocated().remove(&id).is_some(),
+            TextureId::Managed(_) => false,
+        }
+    }
+
+    /// Limits video memory used by user textures created by the allocator.
+    /// When exceeded, least recently drawn ones are evicted at the end of the frame.
+    /// Textures created by egui and native ones are never evicted. No limit by default.
     #[inline]
-    pub fn resolve_delta(
+    pub fn set_budget(&self, bytes: Option<usize>) {
+        *self.budget.lock() = bytes;
+    }
+
+    /// Sets callback invoked with id of every evicted texture,
+    /// owner has to upload the image again to display it, which creates a new id.
+    #[inline]
+    pub fn on_evict(&self, callback: impl Fn(TextureId) + Send + Sync + 'static) {
+        *self.on_evict.lock() = Some(Box::new(callback));
+    }
+
+    #[inline]
+    pub(crate) fn frame(&self) -> u64 {
+        self.frame.load(Ordering::Relaxed)
+    }
+
+    /// Evicts user textures over the budget and starts a new frame.
+    pub(crate) fn end_frame(&self) {
+        let frame = self.frame.fetch_add(1, Ordering::Relaxed);
+
+        let budget = match *self.budget.lock() {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        let evicted = {
+            let lock = &mut *self.allocated();
+            let mut used = lock
+                .iter()
+                .filter(|(id, _)| matches!(id, TextureId::User(_)))
+                .map(|(_, tex)| tex.byte_size())
+                .sum::<usize>();
+
+            let mut candidates = lock
+                .iter()
+                .filter(|(id, tex)| {
+                    matches!(id, TextureId::User(_))
+                        && !tex.is_native()
+                        && tex.last_used.load(Ordering::Relaxed) < frame
+                })
+                .map(|(id, tex)| (*id, tex.last_used.load(Ordering::Relaxed), tex.byte_size()))
+                .collect::<Vec<_>>();
+            candidates.sort_by_key(|(_, last_used, _)| *last_used);
+
+            let mut evicted = vec![];
+            for (id, _, size) in candidates {
+                if used <= budget {
+                    break;
+                }
+
+                lock.remove(&id);
+                used -= size;
+                evicted.push(id);
+            }
+            evicted
+        };
+
+        if let Some(callback) = &*self.on_evict.lock() {
+            evicted.into_iter().for_each(callback);
+        }
+    }
+
+    fn insert_user(&self, tex: AllocatedTexture) -> TextureId {
+        tex.touch(self.frame());
+
+        let id = TextureId::User(self.next_user_id.fetch_add(1, Ordering::Relaxed));
+        self.allocated().insert(id, tex);
+        id
+    }
+
+    #[inline]
+    pub(crate) fn resolve_delta(
         &self,
         delta: TexturesDelta,
         device: &ID3D11Device,
@@ -103,9 +453,49 @@ impl TextureAllocator {
         }
 
         for (id, delta) in delta.set {
-            if let Some((region, tex)) = delta.pos.zip(lock.get_mut(&id)) {
-                tex.update(region, delta.image, ctx);
-            } else {
+            Self::apply_delta(lock, id, delta, device, ctx);
+        }
+    }
+
+    /// Applies uploads queued with [`Self::enqueue_upload`] and [`Self::enqueue_update`].
+    pub(crate) fn apply_pending(&self, device: &ID3D11Device, ctx: &ID3D11DeviceContext) {
+        let pending = std::mem::take(&mut *self.pending.lock());
+        if pending.is_empty() {
+            return;
+        }
+
+        let frame = self.frame();
+        let lock = &mut *self.allocated();
+
+        for (id, delta) in pending {
+            Self::apply_delta(lock, id, delta, device, ctx);
+            if let Some(tex) = lock.get(&id) {
+                tex.touch(frame);
+            }
+        }
+    }
+
+    fn apply_delta(
+        lock: &mut HashMap<TextureId, AllocatedTexture>,
+        id: TextureId,
+        delta: ImageDelta,
+        device: &ID3D11Device,
+        ctx: &ID3D11DeviceContext,
+    ) {
+        let delta = if is_downlevel(device) {
+            ImageDelta {
+                image: expand_alpha(delta.image),
+                pos: delta.pos,
+            }
+        } else {
+            delta
+        };
+
+        match (delta.pos, lock.get_mut(&id)) {
+            (Some(region), Some(tex)) => tex.update(region, delta.image, ctx),
+            // Update of a texture that was freed or evicted in the meantime.
+            (Some(_), None) => {}
+            (None, _) => {
                 let tex = Self::allocate_texture(delta.image, device);
                 lock.insert(id, tex);
             }
@@ -113,13 +503,29 @@ impl TextureAllocator {
     }
 
     fn allocate_texture(image: ImageData, device: &ID3D11Device) -> AllocatedTexture {
+        // Images are addressed with normalized coordinates so the smaller one looks the same,
+        // only with less detail.
+        let size = image.size();
+        let max_side = max_texture_side(device);
+        let (image, source) = if size[0] > max_side || size[1] > max_side {
+            (downscale(&image, max_side), Some(image))
+        } else {
+            (image, None)
+        };
+
         let texture = Self::create_texture(&image, device);
-        let resource = Self::create_resource(get_image_format(&image), &texture, device);
+        let format = get_image_format(&image);
+        let resource = Self::create_resource(format, 1, &texture, device);
 
         AllocatedTexture {
+            size,
+            source,
+            byte_size: image.width() * image.height() * image.bytes_per_pixel(),
+            format,
+            texture: Some(texture),
+            keyed_mutex: None,
+            last_used: AtomicU64::default(),
             resource,
-            image,
-            texture,
         }
     }
 
@@ -134,17 +540,26 @@ impl TextureAllocator {
                 Count: 1,
                 Quality: 0,
             },
-            Usage: D3D11_USAGE_DYNAMIC,
+            Usage: D3D11_USAGE_DEFAULT,
             BindFlags: D3D11_BIND_SHADER_RESOURCE,
-            CPUAccessFlags: D3D11_CPU_ACCESS_WRITE,
+            CPUAccessFlags: D3D11_CPU_ACCESS_FLAG(0),
             MiscFlags: D3D11_RESOURCE_MISC_FLAG(0),
         };
 
+        if !has_tight_pixels(image) {
+            crate::error::fail(
+                crate::ErrorCode::InvalidImageSize,
+                E_INVALIDARG,
+                if cfg!(feature = "no-msgs") {
+                    ""
+                } else {
+                    "Image size doesn't match its pixels."
+                },
+            );
+        }
+
         let init = D3D11_SUBRESOURCE_DATA {
-            pSysMem: match image {
-                ImageData::Color(c) => c.pixels.as_ptr() as _,
-                ImageData::Alpha(a) => a.pixels.as_ptr() as _,
-            },
+            pSysMem: get_image_data(image),
             SysMemPitch: (image.width() * image.bytes_per_pixel()) as _,
             SysMemSlicePitch: 0,
         };
@@ -152,6 +567,7 @@ impl TextureAllocator {
         unsafe {
             expect!(
                 device.CreateTexture2D(&desc, &init),
+                CreateTexture,
                 "Failed to create 2D texture."
             )
         }
@@ -159,6 +575,7 @@ impl TextureAllocator {
 
     fn create_resource(
         format: DXGI_FORMAT,
+        mip_levels: u32,
         texture: &ID3D11Texture2D,
         device: &ID3D11Device,
     ) -> ID3D11ShaderResourceView {
@@ -168,7 +585,7 @@ impl TextureAllocator {
             Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
                 Texture2D: D3D11_TEX2D_SRV {
                     MostDetailedMip: 0,
-                    MipLevels: 1,
+                    MipLevels: mip_levels,
                 },
             },
         };
@@ -176,16 +593,283 @@ impl TextureAllocator {
         unsafe {
             expect!(
                 device.CreateShaderResourceView(texture, &desc),
+                CreateShaderResourceView,
                 "Failed to create shader resource view."
             )
         }
     }
 }
 
-fn get_image_format(image: &ImageData) -> DXGI_FORMAT {
+/// Source pitch passed to D3D is always `width * bytes_per_pixel`, driver handles padding
+/// of the destination rows itself. Pixel buffer must be exactly that large,
+/// otherwise D3D would read out of its bounds.
+#[inline]
+pub(crate) fn has_tight_pixels(image: &ImageData) -> bool {
+    let len = match image {
+        ImageData::Color(c) => c.pixels.len(),
+        ImageData::Alpha(a) => a.pixels.len(),
+    };
+    len == image.width() * image.height()
+}
+
+/// Returns largest texture dimension supported by the device's feature level.
+pub(crate) fn max_texture_side(device: &ID3D11Device) -> usize {
+    match unsafe { device.GetFeatureLevel() } {
+        level if level.0 >= D3D_FEATURE_LEVEL_11_0.0 => {
+            D3D11_REQ_TEXTURE2D_U_OR_V_DIMENSION as usize
+        }
+        level if level.0 >= D3D_FEATURE_LEVEL_10_0.0 => 8192,
+        level if level.0 >= D3D_FEATURE_LEVEL_9_3.0 => 4096,
+        _ => 2048,
+    }
+}
+
+/// Returns `true` for feature level 9_x devices, which need the downlevel shader
+/// and can't sample single channel textures. Only 9_3 is supported of those.
+pub(crate) fn is_downlevel(device: &ID3D11Device) -> bool {
+    unsafe { device.GetFeatureLevel().0 < D3D_FEATURE_LEVEL_10_0.0 }
+}
+
+/// Expands alpha image into RGBA for devices without single channel textures,
+/// shader only reads the first channel of the font texture anyway.
+fn expand_alpha(image: ImageData) -> ImageData {
+    match image {
+        ImageData::Alpha(img) => ImageData::Color(ColorImage {
+            size: img.size,
+            pixels: img
+                .pixels
+                .iter()
+                .map(|&a| Color32::from_rgba_premultiplied(a, a, a, a))
+                .collect(),
+        }),
+        image => image,
+    }
+}
+
+/// Shrinks image with box filter so neither side exceeds `max_side`, keeping aspect ratio.
+fn downscale(image: &ImageData, max_side: usize) -> ImageData {
+    let [w, h] = image.size();
+    let scale = max_side as f32 / w.max(h) as f32;
+    let size = [
+        ((w as f32 * scale) as usize).clamp(1, max_side),
+        ((h as f32 * scale) as usize).clamp(1, max_side),
+    ];
+    resize(image, size)
+}
+
+/// Resamples image to `size` with box filter.
+fn resize(image: &ImageData, size: [usize; 2]) -> ImageData {
+    match image {
+        ImageData::Color(img) => ImageData::Color(ColorImage {
+            size,
+            pixels: box_filter(
+                &img.pixels,
+                img.size,
+                size,
+                |c| c.to_array(),
+                |[r, g, b, a]| Color32::from_rgba_premultiplied(r, g, b, a),
+            ),
+        }),
+        ImageData::Alpha(img) => ImageData::Alpha(AlphaImage {
+            size,
+            pixels: box_filter(&img.pixels, img.size, size, |a| [a, 0, 0, 0], |[a, ..]| a),
+        }),
+    }
+}
+
+/// Copies `delta` into `image` with its top left corner at `[x, y]`.
+fn paste(image: &mut ImageData, [x, y]: [usize; 2], delta: &ImageData) {
+    fn copy<T: Copy>(
+        dst: &mut [T],
+        width: usize,
+        src: &[T],
+        [w, h]: [usize; 2],
+        [x, y]: [usize; 2],
+    ) {
+        for row in 0..h {
+            let start = (y + row) * width + x;
+            dst[start..start + w].copy_from_slice(&src[row * w..(row + 1) * w]);
+        }
+    }
+
+    match (image, delta) {
+        (ImageData::Color(dst), ImageData::Color(src)) => {
+            copy(&mut dst.pixels, dst.size[0], &src.pixels, src.size, [x, y])
+        }
+        (ImageData::Alpha(dst), ImageData::Alpha(src)) => {
+            copy(&mut dst.pixels, dst.size[0], &src.pixels, src.size, [x, y])
+        }
+        _ => {}
+    }
+}
+
+fn box_filter<T: Copy>(
+    pixels: &[T],
+    [w, h]: [usize; 2],
+    [nw, nh]: [usize; 2],
+    split: impl Fn(T) -> [u8; 4],
+    join: impl Fn([u8; 4]) -> T,
+) -> Vec<T> {
+    let mut out = Vec::with_capacity(nw * nh);
+
+    for y in 0..nh {
+        let (y0, y1) = (y * h / nh, ((y + 1) * h / nh).max(y * h / nh + 1));
+        for x in 0..nw {
+            let (x0, x1) = (x * w / nw, ((x + 1) * w / nw).max(x * w / nw + 1));
+
+            let mut sum = [0u32; 4];
+            for sy in y0..y1 {
+                for sx in x0..x1 {
+                    let px = split(pixels[sy * w + sx]);
+                    sum.iter_mut().zip(px).for_each(|(s, p)| *s += p as u32);
+                }
+            }
+
+            let count = ((x1 - x0) * (y1 - y0)) as u32;
+            out.push(join(sum.map(|s| (s / count) as u8)));
+        }
+    }
+
+    out
+}
+
+#[inline]
+pub(crate) fn get_image_data(image: &ImageData) -> *const std::ffi::c_void {
+    match image {
+        ImageData::Color(c) => c.pixels.as_ptr() as _,
+        ImageData::Alpha(a) => a.pixels.as_ptr() as _,
+    }
+}
+
+pub(crate) fn get_image_format(image: &ImageData) -> DXGI_FORMAT {
     if image.bytes_per_pixel() == 1 {
         DXGI_FORMAT_R8_UNORM
     } else {
         DXGI_FORMAT_R8G8B8A8_UNORM
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_texture, warp_device};
+
+    fn alpha_image(size: [usize; 2], seed: u8) -> ImageData {
+        ImageData::Alpha(AlphaImage {
+            size,
+            pixels: (0..size[0] * size[1])
+                .map(|i| (i as u8).wrapping_mul(7).wrapping_add(seed))
+                .collect(),
+        })
+    }
+
+    fn color_image(size: [usize; 2], seed: u8) -> ImageData {
+        ImageData::Color(ColorImage {
+            size,
+            pixels: (0..size[0] * size[1])
+                .map(|i| {
+                    let v = (i as u8).wrapping_mul(11).wrapping_add(seed);
+                    Color32::from_rgba_premultiplied(v, v ^ 0x55, v.wrapping_add(3), 255 - v)
+                })
+                .collect(),
+        })
+    }
+
+    fn image_bytes(image: &ImageData) -> Vec<u8> {
+        match image {
+            ImageData::Alpha(a) => a.pixels.clone(),
+            ImageData::Color(c) => c.pixels.iter().flat_map(|c| c.to_array()).collect(),
+        }
+    }
+
+    fn set_delta(id: TextureId, delta: ImageDelta) -> TexturesDelta {
+        let mut textures = TexturesDelta::default();
+        textures.set.extend([(id, delta)]);
+        textures
+    }
+
+    /// Uploads `full`, updates it with `patch` at `pos` and compares the texture row by row.
+    fn check_upload_and_update(full: ImageData, patch: ImageData, [x, y]: [usize; 2]) {
+        let (device, ctx) = warp_device();
+        let alloc = TextureAllocator::default();
+        let id = TextureId::Managed(0);
+
+        let [width, height] = full.size();
+        let bpp = full.bytes_per_pixel();
+        let row_len = width * bpp;
+
+        let mut expected = image_bytes(&full);
+        let patch_bytes = image_bytes(&patch);
+        let patch_row = patch.width() * bpp;
+        for row in 0..patch.height() {
+            let dst = ((y + row) * width + x) * bpp;
+            expected[dst..dst + patch_row]
+                .copy_from_slice(&patch_bytes[row * patch_row..(row + 1) * patch_row]);
+        }
+
+        alloc.resolve_delta(set_delta(id, ImageDelta::full(full)), &device, &ctx);
+        alloc.resolve_delta(
+            set_delta(id, ImageDelta::partial([x, y], patch)),
+            &device,
+            &ctx,
+        );
+
+        let lock = alloc.allocated();
+        let texture = lock[&id].texture.as_ref().unwrap();
+        let rows = read_texture(&device, &ctx, texture, bpp);
+
+        assert_eq!(rows.len(), height);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(
+                row[..],
+                expected[i * row_len..(i + 1) * row_len],
+                "row {} differs",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn odd_width_alpha_texture() {
+        check_upload_and_update(alpha_image([13, 7], 1), alpha_image([5, 3], 100), [3, 2]);
+    }
+
+    #[test]
+    fn odd_width_color_texture() {
+        check_upload_and_update(color_image([13, 7], 1), color_image([5, 3], 100), [3, 2]);
+    }
+
+    #[test]
+    fn downscaled_texture_accepts_partial_updates() {
+        let (device, ctx) = warp_device();
+        let alloc = TextureAllocator::default();
+        let id = TextureId::Managed(0);
+
+        let side = max_texture_side(&device);
+        let mut full = color_image([side * 2, 2], 1);
+        let patch = color_image([64, 2], 100);
+
+        alloc.resolve_delta(set_delta(id, ImageDelta::full(full.clone())), &device, &ctx);
+        let before = resize(&full, [side, 1]);
+        alloc.resolve_delta(
+            set_delta(id, ImageDelta::partial([100, 0], patch.clone())),
+            &device,
+            &ctx,
+        );
+
+        paste(&mut full, [100, 0], &patch);
+        let expected = image_bytes(&resize(&full, [side, 1]));
+        assert_ne!(expected, image_bytes(&before));
+
+        let lock = alloc.allocated();
+        assert_eq!(lock[&id].size(), [side * 2, 2]);
+        let rows = read_texture(&device, &ctx, lock[&id].texture.as_ref().unwrap(), 4);
+        assert_eq!(rows, vec![expected]);
+    }
+
+    #[test]
+    fn odd_width_update_at_edge() {
+        check_upload_and_update(alpha_image([13, 7], 9), alpha_image([3, 7], 50), [10, 0]);
+        check_upload_and_update(color_image([13, 7], 9), color_image([1, 1], 50), [12, 6]);
+    }
+}
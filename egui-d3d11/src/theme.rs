@@ -0,0 +1,33 @@
+use egui::{epaint::Shadow, Color32, Style, Visuals};
+
+/// Style presets for [`crate::DirectX11AppBuilder::theme`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// egui's default dark visuals.
+    Dark,
+    Light,
+    /// Dark visuals with translucent windows, keeping the game visible behind them.
+    Overlay,
+}
+
+impl Theme {
+    pub fn style(self) -> Style {
+        let visuals = match self {
+            Self::Dark => Visuals::dark(),
+            Self::Light => Visuals::light(),
+            Self::Overlay => {
+                let mut visuals = Visuals::dark();
+                visuals.widgets.noninteractive.bg_fill = Color32::from_black_alpha(170);
+                visuals.faint_bg_color = Color32::from_white_alpha(8);
+                visuals.extreme_bg_color = Color32::from_black_alpha(200);
+                visuals.window_shadow = Shadow::small_dark();
+                visuals
+            }
+        };
+
+        Style {
+            visuals,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+/// Clock used for [`egui::RawInput::time`].
+/// Can be replaced with [`crate::DirectX11AppBuilder::time_source`], e.g. to drive time manually.
+pub trait TimeSource {
+    /// Returns monotonic time in seconds.
+    fn now(&self) -> f64;
+}
+
+impl<F> TimeSource for F
+where
+    F: Fn() -> f64,
+{
+    #[inline]
+    fn now(&self) -> f64 {
+        self()
+    }
+}
+
+/// Default clock backed by `QueryPerformanceCounter`.
+/// Unlike system time it has sub-microsecond resolution and isn't affected by clock changes.
+pub struct PerformanceCounter {
+    frequency: f64,
+}
+
+impl PerformanceCounter {
+    pub fn new() -> Self {
+        let mut frequency = 0;
+        unsafe {
+            expect!(
+                QueryPerformanceFrequency(&mut frequency).ok(),
+                QueryPerformanceFrequency,
+                "Failed to query performance counter frequency."
+            );
+        }
+
+        Self {
+            frequency: frequency as f64,
+        }
+    }
+}
+
+impl Default for PerformanceCounter {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for PerformanceCounter {
+    #[inline]
+    fn now(&self) -> f64 {
+        let mut counter = 0;
+        unsafe {
+            QueryPerformanceCounter(&mut counter);
+        }
+
+        counter as f64 / self.frequency
+    }
+}
@@ -0,0 +1,320 @@
+use egui::{Color32, ColorImage, TextureId};
+use std::{
+    ptr::null_mut,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use windows::{
+    core::{Error, Interface, Result, HRESULT},
+    Win32::{
+        Foundation::{E_FAIL, PWSTR},
+        Media::MediaFoundation::{
+            IMF2DBuffer, IMFAttributes, IMFSample, IMFSourceReader, MFCreateAttributes,
+            MFCreateMediaType, MFCreateSourceReaderFromURL, MFMediaType_Video, MFShutdown,
+            MFStartup, MFVideoFormat_RGB32, MFSTARTUP_FULL, MF_API_VERSION, MF_MT_DEFAULT_STRIDE,
+            MF_MT_FRAME_SIZE, MF_MT_MAJOR_TYPE, MF_MT_SUBTYPE, MF_SDK_VERSION,
+            MF_SOURCE_READERF_ENDOFSTREAM, MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING,
+            MF_SOURCE_READER_FIRST_VIDEO_STREAM,
+        },
+        System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED},
+    },
+};
+
+use crate::{sync::Mutex, TextureAllocator};
+
+/// Version passed to `MFStartup`, the `MF_VERSION` macro isn't exported by windows 0.32.
+const MF_VERSION: u32 = MF_SDK_VERSION << 16 | MF_API_VERSION;
+
+/// Video file or stream decoded with Media Foundation on a background thread.
+/// Decoded frames are uploaded through [`TextureAllocator::enqueue_update`],
+/// so the texture is refreshed at the start of the next present.
+pub struct VideoPlayer {
+    shared: Arc<Shared>,
+    size: [usize; 2],
+    texture: Option<TextureId>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    /// Latest decoded frame that wasn't uploaded yet.
+    frame: Mutex<Option<ColorImage>>,
+    stop: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl VideoPlayer {
+    /// Opens video at `url`, which can be a file path or a network url supported by
+    /// Media Foundation, and starts decoding it. Video is restarted after the end if `looping` is set.
+    pub fn open(url: impl Into<String>, looping: bool) -> Result<Self> {
+        let url = url.into();
+        let shared = Arc::new(Shared {
+            frame: Mutex::new(None),
+            stop: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        });
+
+        // Media Foundation objects aren't `Send`, reader is created on the decoding thread
+        // which reports back the frame size or the error.
+        let (tx, rx) = mpsc::channel();
+        let thread = std::thread::spawn({
+            let shared = shared.clone();
+            move || unsafe {
+                // Thread is ours, Media Foundation is started once for every reader created on it.
+                let com = CoInitializeEx(null_mut(), COINIT_MULTITHREADED).is_ok();
+                match MFStartup(MF_VERSION, MFSTARTUP_FULL) {
+                    Ok(()) => {
+                        play(&url, looping, &shared, tx);
+                        let _ = MFShutdown();
+                    }
+                    Err(e) => drop(tx.send(Err(e.code()))),
+                }
+                if com {
+                    CoUninitialize();
+                }
+
+                shared.finished.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let size = rx.recv().unwrap_or(Err(E_FAIL)).map_err(Error::from)?;
+
+        Ok(Self {
+            shared,
+            size,
+            texture: None,
+            thread: Some(thread),
+        })
+    }
+
+    /// Queues upload of the latest decoded frame and returns id of video's texture.
+    /// Should be called every frame the video is displayed, e.g. from the ui closure.
+    /// Returns `None` until the first frame is decoded.
+    pub fn update(&mut self, textures: &TextureAllocator) -> Option<TextureId> {
+        if let Some(frame) = self.shared.frame.lock().take() {
+            match self.texture {
+                Some(id) => textures.enqueue_update(id, [0, 0], frame),
+                None => self.texture = Some(textures.enqueue_upload(frame)),
+            }
+        }
+        self.texture
+    }
+
+    /// Returns id of video's texture or `None` if no frame was uploaded yet.
+    /// Texture isn't freed with the player, use [`TextureAllocator::unregister`] for that.
+    #[inline]
+    pub fn texture(&self) -> Option<TextureId> {
+        self.texture
+    }
+
+    /// Returns size of video frames in pixels.
+    #[inline]
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    /// Returns `true` if non-looping video reached its end or decoding failed.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.shared.finished.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            drop(thread.join());
+        }
+    }
+}
+
+/// Opens the video and decodes it until the end or until the player is dropped.
+/// Reports frame size or the error through `tx` once the reader is created.
+fn play(
+    url: &str,
+    looping: bool,
+    shared: &Shared,
+    tx: mpsc::Sender<std::result::Result<[usize; 2], HRESULT>>,
+) {
+    let reader = match create_reader(url) {
+        Ok(reader) => reader,
+        Err(e) => {
+            let _ = tx.send(Err(e.code()));
+            return;
+        }
+    };
+    let size = match frame_size(&reader) {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = tx.send(Err(e.code()));
+            return;
+        }
+    };
+    let _ = tx.send(Ok(size));
+
+    decode(reader, size, url, looping, shared);
+}
+
+fn create_reader(url: &str) -> Result<IMFSourceReader> {
+    unsafe {
+        // Lets the reader convert decoder's output (usually NV12) into RGB32.
+        let mut attributes: Option<IMFAttributes> = None;
+        MFCreateAttributes(&mut attributes, 1)?;
+        let attributes = expect!(
+            attributes,
+            CreateMediaAttributes,
+            "Failed to create reader attributes."
+        );
+        attributes.SetUINT32(&MF_SOURCE_READER_ENABLE_VIDEO_PROCESSING, 1)?;
+
+        let mut wide: Vec<u16> = url.encode_utf16().chain(Some(0)).collect();
+        let reader = MFCreateSourceReaderFromURL(PWSTR(wide.as_mut_ptr()), &attributes)?;
+
+        let media_type = MFCreateMediaType()?;
+        media_type.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)?;
+        media_type.SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)?;
+        reader.SetCurrentMediaType(
+            MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as _,
+            std::ptr::null_mut(),
+            &media_type,
+        )?;
+
+        Ok(reader)
+    }
+}
+
+fn frame_size(reader: &IMFSourceReader) -> Result<[usize; 2]> {
+    unsafe {
+        let media_type = reader.GetCurrentMediaType(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as _)?;
+        let packed = media_type.GetUINT64(&MF_MT_FRAME_SIZE)?;
+        Ok([(packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize])
+    }
+}
+
+/// Returns distance between rows of contiguous buffers in bytes, negative for bottom-up images.
+fn default_stride(reader: &IMFSourceReader, width: usize) -> isize {
+    unsafe {
+        reader
+            .GetCurrentMediaType(MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as _)
+            .and_then(|media_type| media_type.GetUINT32(&MF_MT_DEFAULT_STRIDE))
+            .map(|stride| stride as i32 as isize)
+            .unwrap_or(width as isize * 4)
+    }
+}
+
+fn decode(
+    mut reader: IMFSourceReader,
+    size: [usize; 2],
+    url: &str,
+    looping: bool,
+    shared: &Shared,
+) {
+    let mut start = Instant::now();
+    let mut stride = default_stride(&reader, size[0]);
+
+    while !shared.stop.load(Ordering::SeqCst) {
+        let (mut flags, mut timestamp, mut sample) = (0, 0, None);
+        let read = unsafe {
+            reader.ReadSample(
+                MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as _,
+                0,
+                std::ptr::null_mut(),
+                &mut flags,
+                &mut timestamp,
+                &mut sample,
+            )
+        };
+        if read.is_err() {
+            return;
+        }
+
+        if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+            if !looping {
+                return;
+            }
+
+            // Recreating the reader is simpler than seeking with a PROPVARIANT position.
+            reader = match create_reader(url) {
+                Ok(reader) => reader,
+                Err(_) => return,
+            };
+            start = Instant::now();
+            stride = default_stride(&reader, size[0]);
+            continue;
+        }
+
+        let frame = match sample.and_then(|sample| read_frame(&sample, size, stride).ok()) {
+            Some(frame) => frame,
+            None => continue,
+        };
+
+        // Timestamps are in 100 nanosecond units.
+        let due = start + Duration::from_nanos(timestamp as u64 * 100);
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+
+        *shared.frame.lock() = Some(frame);
+    }
+}
+
+fn read_frame(sample: &IMFSample, size: [usize; 2], stride: isize) -> Result<ColorImage> {
+    unsafe {
+        let buffer = sample.ConvertToContiguousBuffer()?;
+
+        // 2D buffers report the actual pitch of the frame.
+        if let Ok(buffer) = buffer.cast::<IMF2DBuffer>() {
+            let (mut scanline, mut pitch) = (null_mut(), 0);
+            buffer.Lock2D(&mut scanline, &mut pitch)?;
+            let frame = copy_rows(scanline, pitch as isize, size);
+            buffer.Unlock2D()?;
+            return frame;
+        }
+
+        let (mut data, mut len) = (null_mut(), 0);
+        buffer.Lock(&mut data, null_mut(), &mut len)?;
+
+        let [_, height] = size;
+        let frame = if height == 0 || (len as usize) < stride.unsigned_abs() * height {
+            Err(Error::from(E_FAIL))
+        } else if stride < 0 {
+            // Bottom-up images start with the last row.
+            copy_rows(data.offset(-stride * (height as isize - 1)), stride, size)
+        } else {
+            copy_rows(data, stride, size)
+        };
+
+        buffer.Unlock()?;
+        frame
+    }
+}
+
+/// Converts rows starting at `scanline` and `pitch` bytes apart into an image.
+unsafe fn copy_rows(
+    scanline: *const u8,
+    pitch: isize,
+    [width, height]: [usize; 2],
+) -> Result<ColorImage> {
+    // RGB32 is BGRX, rows may be padded but never shorter than the frame.
+    if scanline.is_null() || pitch.unsigned_abs() < width * 4 {
+        return Err(Error::from(E_FAIL));
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row = std::slice::from_raw_parts(scanline.offset(y as isize * pitch), width * 4);
+        pixels.extend(
+            row.chunks_exact(4)
+                .map(|px| Color32::from_rgb(px[2], px[1], px[0])),
+        );
+    }
+
+    Ok(ColorImage {
+        size: [width, height],
+        pixels,
+    })
+}
@@ -0,0 +1,115 @@
+use egui::{Color32, ColorImage};
+use std::{os::windows::ffi::OsStrExt, path::Path};
+use windows::{
+    core::Result,
+    Win32::{
+        Foundation::PWSTR,
+        Graphics::Imaging::{
+            CLSID_WICImagingFactory, GUID_WICPixelFormat32bppPRGBA, IWICBitmapDecoder,
+            IWICImagingFactory, WICBitmapDitherTypeNone, WICBitmapPaletteTypeCustom,
+            WICDecodeMetadataCacheOnDemand,
+        },
+        System::{
+            Com::{
+                CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+                COINIT_MULTITHREADED,
+            },
+            SystemServices::GENERIC_READ,
+        },
+    },
+};
+
+/// Decodes image file (PNG, JPEG, BMP, GIF, TIFF...) with Windows Imaging Component.
+pub(crate) fn decode_file(path: &Path) -> Result<ColorImage> {
+    let _com = ComScope::enter();
+    let factory = create_factory()?;
+    let mut wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let decoder = unsafe {
+        factory.CreateDecoderFromFilename(
+            PWSTR(wide.as_mut_ptr()),
+            std::ptr::null(),
+            GENERIC_READ,
+            WICDecodeMetadataCacheOnDemand,
+        )?
+    };
+
+    decode(&factory, &decoder)
+}
+
+/// Decodes encoded image held in memory with Windows Imaging Component.
+pub(crate) fn decode_bytes(bytes: &[u8]) -> Result<ColorImage> {
+    let _com = ComScope::enter();
+    let factory = create_factory()?;
+    let decoder = unsafe {
+        let stream = factory.CreateStream()?;
+        stream.InitializeFromMemory(bytes.as_ptr() as _, bytes.len() as _)?;
+        factory.CreateDecoderFromStream(
+            &stream,
+            std::ptr::null(),
+            WICDecodeMetadataCacheOnDemand,
+        )?
+    };
+
+    decode(&factory, &decoder)
+}
+
+/// Initializes COM for the calling thread while alive, declared before any COM object
+/// so it's dropped after them.
+struct ComScope {
+    initialized: bool,
+}
+
+impl ComScope {
+    fn enter() -> Self {
+        // Thread may already be initialized by the game with a different model, that's fine,
+        // but then it's not ours to uninitialize. `S_FALSE` still has to be balanced.
+        let initialized =
+            unsafe { CoInitializeEx(std::ptr::null_mut(), COINIT_MULTITHREADED) }.is_ok();
+        Self { initialized }
+    }
+}
+
+impl Drop for ComScope {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+fn create_factory() -> Result<IWICImagingFactory> {
+    unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) }
+}
+
+fn decode(factory: &IWICImagingFactory, decoder: &IWICBitmapDecoder) -> Result<ColorImage> {
+    unsafe {
+        let frame = decoder.GetFrame(0)?;
+
+        // egui expects premultiplied alpha, WIC converts to it directly.
+        let converter = factory.CreateFormatConverter()?;
+        converter.Initialize(
+            &frame,
+            &GUID_WICPixelFormat32bppPRGBA,
+            WICBitmapDitherTypeNone,
+            None,
+            0.,
+            WICBitmapPaletteTypeCustom,
+        )?;
+
+        let (mut width, mut height) = (0, 0);
+        converter.GetSize(&mut width, &mut height)?;
+
+        let mut pixels = vec![Color32::TRANSPARENT; width as usize * height as usize];
+        converter.CopyPixels(
+            std::ptr::null(),
+            width * 4,
+            (pixels.len() * 4) as _,
+            pixels.as_mut_ptr() as _,
+        )?;
+
+        Ok(ColorImage {
+            size: [width as _, height as _],
+            pixels,
+        })
+    }
+}
@@ -0,0 +1,164 @@
+use egui::{
+    Align2, Area, Color32, Context, FontId, Frame, Id, Order, Pos2, Sense, Shape, Stroke, Ui, Vec2,
+};
+use std::collections::VecDeque;
+
+use crate::DirectX11App;
+
+/// How many frames are kept in [`FrameHistory`].
+const MAX_FRAMES: usize = 240;
+
+/// Durations of the most recent presented frames, recorded by the app every present.
+#[derive(Clone, Default)]
+pub struct FrameHistory {
+    last_time: Option<f64>,
+    frame_times: VecDeque<f32>,
+}
+
+impl FrameHistory {
+    pub(crate) fn record(&mut self, time: f64) {
+        if let Some(last) = self.last_time.replace(time) {
+            if self.frame_times.len() == MAX_FRAMES {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back((time - last) as f32);
+        }
+    }
+
+    /// Returns frame durations in seconds, oldest first.
+    #[inline]
+    pub fn frame_times(&self) -> &VecDeque<f32> {
+        &self.frame_times
+    }
+
+    /// Returns average frame duration in seconds.
+    pub fn mean_frame_time(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+    }
+
+    /// Returns average frames per second.
+    pub fn fps(&self) -> f32 {
+        match self.mean_frame_time() {
+            t if t > 0. => 1. / t,
+            _ => 0.,
+        }
+    }
+}
+
+/// Shows label with average FPS and frame time.
+pub fn fps_counter(ui: &mut Ui, history: &FrameHistory) {
+    ui.label(format!(
+        "{:.0} FPS ({:.2} ms)",
+        history.fps(),
+        history.mean_frame_time() * 1000.
+    ));
+}
+
+/// Shows graph of recent frame times scaled to the slowest one, with a line marking 60 FPS.
+pub fn frame_time_plot(ui: &mut Ui, history: &FrameHistory, size: Vec2) {
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+
+    painter.rect_filled(rect, 2., visuals.extreme_bg_color);
+
+    let times = history.frame_times();
+    let max = times.iter().copied().fold(1. / 30., f32::max);
+    let to_y = |t: f32| rect.bottom() - (t / max).min(1.) * rect.height();
+
+    let target = to_y(1. / 60.);
+    painter.line_segment(
+        [
+            Pos2::new(rect.left(), target),
+            Pos2::new(rect.right(), target),
+        ],
+        Stroke::new(1., visuals.weak_text_color()),
+    );
+
+    if times.len() > 1 {
+        let step = rect.width() / (MAX_FRAMES - 1) as f32;
+        let start = rect.right() - step * (times.len() - 1) as f32;
+        let points = times
+            .iter()
+            .enumerate()
+            .map(|(i, t)| Pos2::new(start + step * i as f32, to_y(*t)))
+            .collect();
+
+        painter.add(Shape::line(
+            points,
+            Stroke::new(1., visuals.widgets.active.fg_stroke.color),
+        ));
+    }
+}
+
+/// Small always-on-top text in a corner of the screen, e.g. name of the tool and FPS.
+pub struct Watermark {
+    text: String,
+    anchor: Align2,
+    offset: Vec2,
+    show_fps: bool,
+    color: Color32,
+}
+
+impl Watermark {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            anchor: Align2::RIGHT_TOP,
+            offset: Vec2::new(-8., 8.),
+            show_fps: false,
+            color: Color32::WHITE,
+        }
+    }
+
+    /// Corner or edge of the screen to place the watermark at.
+    /// Top right by default.
+    #[inline]
+    pub fn anchor(mut self, anchor: Align2, offset: Vec2) -> Self {
+        self.anchor = anchor;
+        self.offset = offset;
+        self
+    }
+
+    /// Appends average FPS to the text.
+    /// Disabled by default.
+    #[inline]
+    pub fn show_fps(mut self, show_fps: bool) -> Self {
+        self.show_fps = show_fps;
+        self
+    }
+
+    /// Color of the text.
+    /// White by default.
+    #[inline]
+    pub fn color(mut self, color: Color32) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Shows the watermark. Should be called inside of the ui closure every frame.
+    pub fn show<T>(&self, ctx: &Context, app: &DirectX11App<T>) {
+        let text = if self.show_fps {
+            format!("{} | {:.0} FPS", self.text, app.frame_history().fps())
+        } else {
+            self.text.clone()
+        };
+
+        Area::new(Id::new("egui_d3d11_watermark"))
+            .order(Order::Foreground)
+            .anchor(self.anchor, self.offset)
+            .interactable(false)
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(
+                        egui::RichText::new(text)
+                            .font(FontId::monospace(12.))
+                            .color(self.color),
+                    );
+                });
+            });
+    }
+}
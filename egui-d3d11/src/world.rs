@@ -0,0 +1,146 @@
+use egui::{
+    Align2, Color32, Context, FontId, Id, LayerId, Order, Painter, Pos2, Rect, Shape, Stroke,
+};
+
+/// Points closer to the camera plane are treated as behind it.
+const NEAR_W: f32 = 1e-4;
+
+/// Projects 3D primitives to the screen and paints them on a background layer
+/// below all egui windows. Create a new one every frame with the current camera.
+pub struct WorldPainter {
+    view_proj: [[f32; 4]; 4],
+    painter: Painter,
+}
+
+impl WorldPainter {
+    /// Creates painter for combined view and projection matrix.
+    /// Matrix is row-major and multiplies row vectors (`v * M`), as in DirectXMath.
+    pub fn new(ctx: &Context, view_proj: [[f32; 4]; 4]) -> Self {
+        let screen = ctx.input().screen_rect();
+        let layer = LayerId::new(Order::Background, Id::new("egui_d3d11_world_painter"));
+
+        Self {
+            view_proj,
+            painter: Painter::new(ctx.clone(), layer, screen),
+        }
+    }
+
+    /// Replaces the camera, e.g. after painting a viewmodel with a different projection.
+    #[inline]
+    pub fn set_view_proj(&mut self, view_proj: [[f32; 4]; 4]) {
+        self.view_proj = view_proj;
+    }
+
+    /// Returns underlying painter, useful for adding shapes in screen space.
+    #[inline]
+    pub fn painter(&self) -> &Painter {
+        &self.painter
+    }
+
+    /// Projects world position to screen, `None` if it's behind the camera.
+    pub fn project(&self, pos: [f32; 3]) -> Option<Pos2> {
+        let clip = self.to_clip(pos);
+        (clip[3] > NEAR_W).then(|| self.to_screen(clip))
+    }
+
+    /// Paints filled circle with radius in points at world position.
+    pub fn point(&self, pos: [f32; 3], radius: f32, color: impl Into<Color32>) {
+        if let Some(pos) = self.project(pos) {
+            self.painter.circle_filled(pos, radius, color);
+        }
+    }
+
+    /// Paints line between world positions, clipped by the camera plane.
+    pub fn line(&self, a: [f32; 3], b: [f32; 3], stroke: impl Into<Stroke>) {
+        if let Some(points) = self.clip_line(self.to_clip(a), self.to_clip(b)) {
+            self.painter.line_segment(points, stroke);
+        }
+    }
+
+    /// Paints edges of axis aligned box between `min` and `max` corners.
+    pub fn aabb(&self, min: [f32; 3], max: [f32; 3], stroke: impl Into<Stroke>) {
+        let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+            self.to_clip([
+                if i & 1 == 0 { min[0] } else { max[0] },
+                if i & 2 == 0 { min[1] } else { max[1] },
+                if i & 4 == 0 { min[2] } else { max[2] },
+            ])
+        });
+        self.edges(&corners, stroke.into());
+    }
+
+    /// Paints edges of box with given corners, ordered like bits of index: x, y, z.
+    /// Allows drawing rotated boxes, e.g. oriented bounding boxes of entities.
+    pub fn cuboid(&self, corners: [[f32; 3]; 8], stroke: impl Into<Stroke>) {
+        self.edges(&corners.map(|c| self.to_clip(c)), stroke.into());
+    }
+
+    /// Paints text anchored at world position, `None` if it's behind the camera.
+    /// Returns screen rect of the text so it can be decorated further.
+    pub fn text(
+        &self,
+        pos: [f32; 3],
+        anchor: Align2,
+        text: impl ToString,
+        font: FontId,
+        color: Color32,
+    ) -> Option<Rect> {
+        let pos = self.project(pos)?;
+        Some(self.painter.text(pos, anchor, text, font, color))
+    }
+
+    fn edges(&self, corners: &[[f32; 4]; 8], stroke: Stroke) {
+        const EDGES: [(usize, usize); 12] = [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ];
+
+        let shapes = EDGES
+            .iter()
+            .filter_map(|&(a, b)| self.clip_line(corners[a], corners[b]))
+            .map(|points| Shape::line_segment(points, stroke))
+            .collect::<Vec<_>>();
+        self.painter.extend(shapes);
+    }
+
+    /// Cuts part of the segment behind the camera, `None` if it's all behind.
+    fn clip_line(&self, mut a: [f32; 4], mut b: [f32; 4]) -> Option<[Pos2; 2]> {
+        match (a[3] > NEAR_W, b[3] > NEAR_W) {
+            (false, false) => return None,
+            (true, true) => {}
+            (true, false) => b = lerp_to_near(a, b),
+            (false, true) => a = lerp_to_near(b, a),
+        }
+
+        Some([self.to_screen(a), self.to_screen(b)])
+    }
+
+    fn to_clip(&self, [x, y, z]: [f32; 3]) -> [f32; 4] {
+        let m = &self.view_proj;
+        [0, 1, 2, 3].map(|c| x * m[0][c] + y * m[1][c] + z * m[2][c] + m[3][c])
+    }
+
+    fn to_screen(&self, [x, y, _, w]: [f32; 4]) -> Pos2 {
+        let rect = self.painter.clip_rect();
+        Pos2::new(
+            rect.left() + (x / w * 0.5 + 0.5) * rect.width(),
+            rect.top() + (0.5 - y / w * 0.5) * rect.height(),
+        )
+    }
+}
+
+/// Moves `behind` along the segment to the point where it crosses the camera plane.
+fn lerp_to_near(front: [f32; 4], behind: [f32; 4]) -> [f32; 4] {
+    let t = (front[3] - NEAR_W) / (front[3] - behind[3]);
+    [0, 1, 2, 3].map(|i| front[i] + (behind[i] - front[i]) * t)
+}
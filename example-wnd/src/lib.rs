@@ -1,7 +1,7 @@
 use egui::{
     Color32, Context, Pos2, Rect, RichText, ScrollArea, Slider, Stroke, TextureId, Vec2, Widget,
 };
-use egui_d3d11::DirectX11App;
+use egui_d3d11::{AppCell, DirectX11App};
 use faithe::{internal::alloc_console, pattern::Pattern};
 use std::intrinsics::transmute;
 use windows::{
@@ -22,7 +22,7 @@ unsafe extern "stdcall" fn DllMain(hinst: usize, reason: u32) -> i32 {
     1
 }
 
-static mut APP: Option<DirectX11App<i32>> = None;
+static APP: AppCell<i32> = AppCell::new();
 static mut OLD_WND_PROC: Option<WNDPROC> = None;
 
 type FnPresent = unsafe extern "stdcall" fn(IDXGISwapChain, u32, u32) -> HRESULT;
@@ -37,9 +37,7 @@ unsafe extern "stdcall" fn hk_present(
     sync_interval: u32,
     flags: u32,
 ) -> HRESULT {
-    if APP.is_none() {
-        APP = Some(DirectX11App::new_with_default(ui, &swap_chain));
-
+    if APP.init(|| DirectX11App::new_with_default(ui, &swap_chain)) {
         let desc = swap_chain.GetDesc().unwrap();
         if desc.OutputWindow.is_invalid() {
             panic!("Invalid window handle.");
@@ -53,9 +51,7 @@ unsafe extern "stdcall" fn hk_present(
         )));
     }
 
-    APP.as_ref()
-        .unwrap()
-        .present(&swap_chain, sync_interval, flags);
+    APP.get().present(&swap_chain, sync_interval, flags);
 
     O_PRESENT.as_ref().unwrap()(swap_chain, sync_interval, flags)
 }
@@ -68,7 +64,7 @@ unsafe extern "stdcall" fn hk_resize_buffers(
     new_format: DXGI_FORMAT,
     swap_chain_flags: u32,
 ) -> HRESULT {
-    APP.as_ref().unwrap().resize_buffers(&swap_chain, || {
+    APP.get().resize_buffers(&swap_chain, || {
         O_RESIZE_BUFFERS.as_ref().unwrap()(
             swap_chain.clone(),
             buffer_count,
@@ -86,7 +82,11 @@ unsafe extern "stdcall" fn hk_wnd_proc(
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
-    APP.as_ref().unwrap().wnd_proc(msg, wparam, lparam);
+    if let Some(app) = APP.try_get() {
+        if !app.wnd_proc(msg, wparam, lparam).should_forward() {
+            return LRESULT(1);
+        }
+    }
 
     CallWindowProcW(OLD_WND_PROC.unwrap(), hwnd, msg, wparam, lparam)
 }
@@ -165,6 +165,10 @@ fn ui(ctx: &Context, i: &mut i32) {
         }
     });
 
+    if let Some(app) = APP.try_get() {
+        egui_d3d11::debug_window(ctx, &app);
+    }
+
     ctx.debug_painter().rect(
         Rect {
             min: Pos2::new(200.0, 200.0),